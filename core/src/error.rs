@@ -228,6 +228,40 @@ impl<E: Display> FormattedError for LispError<E> {
     }
 }
 
+/// A lexer or parser error, with its kind already formatted
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl<E: FormattedError> From<&E> for SyntaxError {
+    fn from(err: &E) -> Self {
+        Self {
+            message: err.message(),
+            span: err.span(),
+        }
+    }
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+impl FormattedError for SyntaxError {
+    fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    fn span(&self) -> Option<Span> {
+        self.span
+    }
+}
+
 pub trait AddIfNotSpannedExt {
     fn map_not_spanned(self, span: Span) -> Self;
 }
@@ -209,6 +209,10 @@ impl Lexer {
 
     fn match_new(&self, ch: char) -> Result<LexerToken, ()> {
         match (ch, self.peek_next_char()) {
+            // A `#!` line starting the file is skipped like a comment, so scripts can be run directly
+            ('#', Some('!')) if self.tokens.is_empty() => {
+                Ok(self.start_new_token(LexerTokenKind::Comment(ch.to_string())))
+            }
             (w, _) if Rules::whitespace(w) => {
                 Ok(self.start_new_token(LexerTokenKind::Whitespace(w.to_string())))
             }
@@ -451,4 +455,14 @@ mod test {
             LexerTokenKind::EOF
         ]
     );
+
+    lex_test!(
+        shebang,
+        "#!/usr/bin/env interpreter\n#t",
+        [
+            LexerTokenKind::Comment("#!/usr/bin/env interpreter\n".into()),
+            LexerTokenKind::Boolean("#t".into()),
+            LexerTokenKind::EOF
+        ]
+    );
 }
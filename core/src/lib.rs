@@ -1,4 +1,4 @@
-use error::ErrorWriter;
+use error::{ErrorWriter, SyntaxError};
 use lexer::Lexer;
 use parser::{ast::AST, Parser};
 
@@ -12,6 +12,26 @@ pub mod literal;
 pub struct LexerParser;
 
 impl LexerParser {
+    /// Lexes and parses without reporting, returning every error found instead
+    pub fn parse(file_id: usize, contents: &str) -> Result<Vec<AST>, Vec<SyntaxError>> {
+        let lexer_result = Lexer::new(file_id, contents).lex();
+        let mut errors = lexer_result.errors.iter().map(SyntaxError::from).collect::<Vec<_>>();
+
+        if let Some(parser) = Parser::new(lexer_result.tokens) {
+            let parser_result = parser.parse();
+            errors.extend(parser_result.errors.iter().map(SyntaxError::from));
+            if errors.is_empty() {
+                return Ok(parser_result.ast);
+            }
+        } else if errors.is_empty() {
+            errors.push(SyntaxError {
+                message: "Invalid literal".to_string(),
+                span: None,
+            });
+        }
+        Err(errors)
+    }
+
     pub fn from_string(file_id: usize, contents: String, error_writer: &ErrorWriter) -> Result<Vec<AST>, ()> {
         let lexer_result = Lexer::new(file_id, contents.as_str()).lex();
         let lexer_error =  error_writer.report_errors(lexer_result.errors).is_err();
@@ -16,6 +16,8 @@ pub enum AST {
     List(P<AST>, P<AST>, Span),
     // Empty List
     EmptyList(Span),
+    /// Dotted tail of a form, `rest` in `(a b . rest)`
+    Rest(P<AST>, Span),
 }
 
 impl AST {
@@ -26,16 +28,23 @@ impl AST {
             | AST::StringLiteral(_, s)
             | AST::Operation(_, _, s)
             | AST::List(_, _, s)
-            | AST::EmptyList(s) => *s,
+            | AST::EmptyList(s)
+            | AST::Rest(_, s) => *s,
         }
     }
 
-    pub fn list_from_vec(mut vec: Vec<AST>) -> AST {
+    pub fn list_from_vec(vec: Vec<AST>) -> AST {
+        let span = vec.last().unwrap().span();
+        Self::list_with_tail(vec, AST::EmptyList(span))
+    }
+
+    /// A list of the items ending in `tail` rather than the empty list
+    pub fn list_with_tail(mut vec: Vec<AST>, tail: AST) -> AST {
         let mut drain = vec.drain(..).rev();
         let head = drain.next().unwrap();
         let span = head.span();
 
-        let mut head = AST::List(Box::new(head), Box::new(AST::EmptyList(span)), span);
+        let mut head = AST::List(Box::new(head), Box::new(tail), span);
         for v in drain {
             let span = v.span();
             head = AST::List(Box::new(v), Box::new(head), span);
@@ -44,15 +53,66 @@ impl AST {
     }
 }
 
+/// Displays an [`AST`] as an indented tree, one node and its span per line
+pub struct Tree<'a>(&'a AST);
+
+impl AST {
+    pub fn tree(&self) -> Tree<'_> {
+        Tree(self)
+    }
+}
+
+impl Tree<'_> {
+    fn fmt_depth(ast: &AST, depth: usize, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let indent = "  ".repeat(depth);
+        let span = ast.span();
+        match ast {
+            AST::Identifier(ident, _) => writeln!(f, "{indent}Identifier {ident} {span}"),
+            AST::Literal(lit, _) => writeln!(f, "{indent}Literal {lit} {span}"),
+            AST::StringLiteral(s, _) => writeln!(f, "{indent}StringLiteral {s:?} {span}"),
+            AST::EmptyList(_) => writeln!(f, "{indent}EmptyList {span}"),
+            AST::Operation(op, params, _) => {
+                writeln!(f, "{indent}Operation {span}")?;
+                Self::fmt_depth(op, depth + 1, f)?;
+                params
+                    .iter()
+                    .try_for_each(|param| Self::fmt_depth(param, depth + 1, f))
+            }
+            AST::List(head, tail, _) => {
+                writeln!(f, "{indent}List {span}")?;
+                Self::fmt_depth(head, depth + 1, f)?;
+                Self::fmt_depth(tail, depth + 1, f)
+            }
+            AST::Rest(tail, _) => {
+                writeln!(f, "{indent}Rest {span}")?;
+                Self::fmt_depth(tail, depth + 1, f)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Tree<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Self::fmt_depth(self.0, 0, f)
+    }
+}
+
 impl std::fmt::Display for AST {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             AST::Identifier(ident, _) => write!(f, "{ident}"),
             AST::Literal(lit, _) => write!(f, "{lit}"),
-            AST::Operation(ident, params, _) => write!(f, "{ident} {params:?}"),
+            AST::Operation(ident, params, _) => {
+                write!(f, "({ident}")?;
+                for param in params {
+                    write!(f, " {param}")?;
+                }
+                write!(f, ")")
+            }
             AST::List(head, tail, _) => write!(f, "{head}:{tail}"),
             AST::StringLiteral(s, _) => write!(f, "{s}"),
             AST::EmptyList(_) => write!(f, "()"),
+            AST::Rest(tail, _) => write!(f, ". {tail}"),
         }
     }
 }
@@ -138,24 +138,48 @@ impl Parser {
             // Quote
             TK::Symbol(s) if &s == "'" => Self::parse_quoted(stream, span),
 
-            TK::Symbol(s) if &s == ".." => Ok(AST::Identifier("..".to_string(), span)),
+            // Dotted tails are read by the list they end, any other dot is misplaced
+            TK::Symbol(s) if &s == "." => Err(ParserError::spanned(ParseTokenError::UnexpectedDot, span)),
+            // Identifiers, only given meaning by the forms which read them, such as `match`
+            // and the parent directory of an `import`
+            TK::Symbol(s) if &s == "..." || &s == ".." => Ok(AST::Identifier(s, span)),
 
             _ => Err(ParserError::spanned(ParseTokenError::NoItemFound, span)),
         }
     }
 
-    fn parse_block(mut stream: TokenStream, span: Span) -> Result<AST, ParserError> {
-        if stream.is_empty() {
-            return Ok(AST::EmptyList(span));
-        }
+    /// Parses the items of a list, along with the item after a `.` ending it
+    fn parse_items(mut stream: TokenStream) -> Result<(Vec<AST>, Option<AST>), ParserError> {
+        let mut items = Vec::new();
+        while let Some(token) = stream.pop_front() {
+            if token.kind != ParserTokenKind::Symbol(".".to_string()) {
+                stream.push_front(token);
+                items.push(Self::parse_item(&mut stream)?);
+                continue;
+            }
 
-        let item = Self::parse_item(&mut stream)?;
+            let dot = ParserError::spanned(ParseTokenError::UnexpectedDot, token.span);
+            if items.is_empty() || stream.is_empty() {
+                return Err(dot);
+            }
+            let tail = Self::parse_item(&mut stream)?;
+            if !stream.is_empty() {
+                return Err(dot);
+            }
+            let span = token.span.max_span(tail.span());
+            return Ok((items, Some(AST::Rest(Box::new(tail), span))));
+        }
+        Ok((items, None))
+    }
 
-        let mut items = Vec::new();
-        while !stream.is_empty() {
-            let item = Self::parse_item(&mut stream)?;
-            items.push(item);
+    fn parse_block(stream: TokenStream, span: Span) -> Result<AST, ParserError> {
+        let (mut items, rest) = Self::parse_items(stream)?;
+        if items.is_empty() {
+            return Ok(AST::EmptyList(span));
         }
+
+        let item = items.remove(0);
+        items.extend(rest);
         Ok(AST::Operation(Box::new(item), items, span))
     }
 
@@ -185,16 +209,12 @@ impl Parser {
         }
     }
 
-    fn parse_list(mut stream: TokenStream, span: Span) -> Result<AST, ParserError> {
-        let mut items = Vec::new();
-        while !stream.is_empty() {
-            let item = Self::parse_item(&mut stream)?;
-            items.push(item);
-        }
-        if items.is_empty() {
-            Ok(AST::EmptyList(span))
-        } else {
-            Ok(AST::list_from_vec(items))
+    fn parse_list(stream: TokenStream, span: Span) -> Result<AST, ParserError> {
+        let (items, rest) = Self::parse_items(stream)?;
+        match rest {
+            _ if items.is_empty() => Ok(AST::EmptyList(span)),
+            Some(AST::Rest(tail, _)) => Ok(AST::list_with_tail(items, *tail)),
+            _ => Ok(AST::list_from_vec(items)),
         }
     }
 }
@@ -204,3 +224,46 @@ pub struct ParseResult {
     pub ast: Vec<AST>,
     pub errors: Vec<ParserError>,
 }
+
+#[cfg(test)]
+mod test {
+    use crate::LexerParser;
+
+    /// Parses a single form, printing it back
+    fn parse(s: &str) -> Result<String, String> {
+        LexerParser::parse(0, s)
+            .map(|ast| ast.iter().map(|a| a.to_string()).collect())
+            .map_err(|errors| errors[0].message.clone())
+    }
+
+    #[test]
+    fn dotted_tail_is_a_rest() {
+        assert_eq!(parse("(f a . b)"), Ok("(f a . b)".to_string()));
+        assert_eq!(parse("(lambda (a b . c) c)"), Ok("(lambda (a b . c) c)".to_string()));
+    }
+
+    #[test]
+    fn quoted_dotted_tail_ends_the_list() {
+        assert_eq!(parse("'(a b . c)"), Ok("a:b:c".to_string()));
+        assert_eq!(parse("'(a b)"), Ok("a:b:()".to_string()));
+    }
+
+    #[test]
+    fn misplaced_dot_is_an_error() {
+        let error = "A '.' must come before the last item of a list".to_string();
+        assert_eq!(parse("(f . a b)"), Err(error.clone()));
+        assert_eq!(parse("(f a .)"), Err(error.clone()));
+        assert_eq!(parse("(f . . a)"), Err(error.clone()));
+        assert_eq!(parse("."), Err(error));
+    }
+
+    #[test]
+    fn ellipsis_is_an_identifier() {
+        assert_eq!(parse("(a ...)"), Ok("(a ...)".to_string()));
+    }
+
+    #[test]
+    fn parent_directory_is_an_identifier() {
+        assert_eq!(parse("(import .. helper)"), Ok("(import .. helper)".to_string()));
+    }
+}
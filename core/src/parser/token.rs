@@ -32,6 +32,9 @@ pub enum ParseTokenError {
     QuoteWithoutItem,
 
     ItemCannotBeQuoted,
+
+    /// A `.` which is not followed by exactly one item ending a list
+    UnexpectedDot,
 }
 
 impl Error for ParseTokenError {}
@@ -46,6 +49,7 @@ impl std::fmt::Display for ParseTokenError {
             ParseTokenError::EmptyBlock => "Block is empty",
             ParseTokenError::QuoteWithoutItem => "No Item found to quote",
             ParseTokenError::ItemCannotBeQuoted => "Item cannot be quoted",
+            ParseTokenError::UnexpectedDot => "A '.' must come before the last item of a list",
         }
         .to_string();
         write!(f, "{}", s)?;
@@ -55,6 +55,20 @@ impl Span {
     }
 }
 
+/// One based `line:col-line:col`, as the error writer links them
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}-{}:{}",
+            self.start.line + 1,
+            self.start.col + 1,
+            self.end.line + 1,
+            self.end.col + 1
+        )
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct LineCol {
     pub line: usize,
@@ -0,0 +1,11 @@
+(define (find-first pred lst)
+  (call/cc (lambda (return)
+    (if (for-each (lambda (x) (if (pred x) (return x) #f)) lst) #f #f))))
+
+(write (find-first (lambda (x) (> x 2)) (list 1 2 3 4)))
+
+(write (call/cc (lambda (k)
+  (dynamic-wind
+    (lambda () (write "enter"))
+    (lambda () (k "escaped"))
+    (lambda () (write "leave"))))))
@@ -0,0 +1,18 @@
+(define (process record)
+  (if (number? record)
+    (* record 2)
+    (error "bad record" record)))
+
+(define (process-safely record)
+  (guard (e ((error-object? e)
+             (write "skipping" (error-object-message e) (error-object-irritants e))
+             0))
+    (process record)))
+
+(write (map process-safely (list 1 "two" 3)))
+
+(write (guard (e (else (error-object-message e))) (car '())))
+
+(write (with-exception-handler
+  (lambda (e) 10)
+  (lambda () (+ (raise-continuable "missing") 1))))
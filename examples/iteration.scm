@@ -0,0 +1,13 @@
+(define (sum-to n)
+  (do ((i 0 (+ i 1))
+       (acc 0 (+ acc i)))
+      ((> i n) acc)))
+
+(write (sum-to 100))
+
+(define area
+  (case-lambda
+    ((r) (* 3 r r))
+    ((w h) (* w h))))
+
+(write (area 2) (area 4 5))
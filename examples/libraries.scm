@@ -0,0 +1,11 @@
+(define-library (counter)
+  (export (rename make make-counter) next)
+  (begin
+    (define (step n) (+ n 1))
+    (define (make start) (list start))
+    (define (next counter) (list (step (car counter))))))
+
+(import (prefix (counter) c:))
+
+(write (c:next (c:next (c:make-counter 0))))
+(write (guard (e (#t (error-object-message e))) (step 1)))
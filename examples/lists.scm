@@ -1,3 +1,9 @@
 (define l '(1 2 3 4))
 
-(write (cons 0 (cons 1 l)))
\ No newline at end of file
+(write (cons 0 (cons 1 l)))
+
+(write (append (reverse l) (list 5 6)))
+
+(write (list-ref l 2) (length l))
+
+(write (assoc 2 (list (cons 1 "one") (cons 2 "two"))))
@@ -0,0 +1,5 @@
+(define (square x) (* x x))
+
+(write (map square (iota 10)))
+
+(write (fold + 0 (filter (lambda (x) (< x 50)) (map square (iota 10)))))
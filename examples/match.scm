@@ -0,0 +1,13 @@
+(define (describe x)
+  (match x
+    (() "empty")
+    ((? string? s) (list "string" s))
+    (((? number? n) . rest) (list "number then" rest))
+    (((k v) ...) (list "keys" k))
+    (_ "other")))
+
+(write (describe (list)))
+(write (describe "hi"))
+(write (describe (list 1 2 3)))
+(write (describe (list (list "a" 1) (list "b" 2))))
+(write (match (list 1 2 3 4 5) ((first middle ... last) middle)))
@@ -0,0 +1,7 @@
+(define indent (make-parameter 0 (lambda (n) (* n 2))))
+
+(define (show label) (list label (indent)))
+
+(write (show "top"))
+(write (parameterize ((indent 2)) (show "nested")))
+(write (guard (e (#t (show "after raise"))) (parameterize ((indent 4)) (raise "oops"))))
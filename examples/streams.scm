@@ -0,0 +1,10 @@
+(define (integers-from n) (stream-cons n (integers-from (+ n 1))))
+
+(define squares (stream-map (lambda (x) (* x x)) (integers-from 1)))
+
+(write (stream->list (stream-take 5 squares)))
+
+(write (stream->list (stream-filter (lambda (x) (> x 1000)) squares) 3))
+
+(define p (delay (+ 1 2)))
+(write (force p) (promise? p))
@@ -0,0 +1,10 @@
+(define (divide n d)
+  (receive (q r) (floor/ n d)
+    (list "quotient" q "remainder" r)))
+
+(write (divide 17 5))
+
+(write (call-with-values (lambda () (partition (lambda (x) (> x 2)) (list 1 2 3 4))) list))
+
+(define-values (root rest) (exact-integer-sqrt 17))
+(write root rest)
@@ -48,6 +48,7 @@ pub trait InterpreterHeapAlloc: Sized {
 
 impl InterpreterHeapAlloc for HeapObject {
     fn heap_alloc(self, interpreter: &InterpreterContext) -> InterpreterResult<ObjectPointer> {
+        interpreter.reserve_heap_object()?;
         Ok(interpreter.heap.alloc_heap_object(self))
     }
 }
@@ -73,6 +74,9 @@ impl InterpreterHeapAlloc for UnallocatedObject {
             UnallocatedObject::String(s) => HeapObject::String(s),
             UnallocatedObject::List(head, tail) => HeapObject::List(head, tail),
             UnallocatedObject::Value(v) => HeapObject::Value(v),
+            UnallocatedObject::Error(e) => HeapObject::Error(e),
+            UnallocatedObject::Values(v) => HeapObject::Values(v),
+            UnallocatedObject::Promise(p) => HeapObject::Promise(p),
             UnallocatedObject::Null => {
                 return Err(InterpreterError::new(
                     InterpreterErrorKind::CannotAllocateNull,
@@ -0,0 +1,190 @@
+use core::error::ErrorWriter;
+use std::{io::Write, path::PathBuf, time::Duration};
+
+use crate::{
+    capabilities::Capabilities, heap::InterpreterHeap, library, limits::Limits,
+    std_lib::StdModule, InterpreterContext,
+};
+
+/// Configures an interpreter before it is created. By default it has the whole
+/// standard library, collects garbage every five seconds and writes to stdout
+pub struct InterpreterBuilder {
+    error_writer: ErrorWriter,
+    modules: Vec<StdModule>,
+    #[cfg(feature = "prelude")]
+    prelude: bool,
+    gc_interval: Option<Duration>,
+    output: Box<dyn Write + Send>,
+    search_path: Vec<PathBuf>,
+    limits: Limits,
+    capabilities: Capabilities,
+    command_line: Vec<String>,
+}
+
+impl Default for InterpreterBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InterpreterBuilder {
+    pub fn new() -> Self {
+        Self {
+            error_writer: ErrorWriter::empty(),
+            modules: StdModule::ALL.to_vec(),
+            #[cfg(feature = "prelude")]
+            prelude: true,
+            gc_interval: Some(Duration::from_secs(5)),
+            output: Box::new(std::io::stdout()),
+            search_path: Vec::new(),
+            limits: Limits::default(),
+            capabilities: Capabilities::default(),
+            command_line: Vec::new(),
+        }
+    }
+
+    /// Sets the sources errors are reported against, as loaded with the main program
+    pub fn error_writer(mut self, error_writer: ErrorWriter) -> Self {
+        self.error_writer = error_writer;
+        self
+    }
+
+    /// Installs only `modules` of the standard library. Special forms are always
+    /// installed
+    pub fn modules(mut self, modules: &[StdModule]) -> Self {
+        self.modules = modules.to_vec();
+        self
+    }
+
+    /// Leaves `module` of the standard library out
+    pub fn without(mut self, module: StdModule) -> Self {
+        self.modules.retain(|m| *m != module);
+        self
+    }
+
+    /// Sets whether the procedures written in Scheme are loaded. Those which use a
+    /// module left out fail when called
+    #[cfg(feature = "prelude")]
+    pub fn prelude(mut self, prelude: bool) -> Self {
+        self.prelude = prelude;
+        self
+    }
+
+    /// Sets how long the collector thread waits between runs. With `None` no thread
+    /// is started and garbage is only freed by [`InterpreterContext::collect_garbage`]
+    pub fn gc_interval(mut self, interval: Option<Duration>) -> Self {
+        self.gc_interval = interval;
+        self
+    }
+
+    /// Sets where `write` prints to
+    pub fn output(mut self, output: impl Write + Send + 'static) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Adds a directory to search for imported libraries and files
+    pub fn search_path(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.search_path.push(dir.into());
+        self
+    }
+
+    /// Bounds each top level evaluation, see [`Limits`]
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Restricts what scripts may read, write and import, see [`Capabilities`]
+    pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Sets what `command-line` returns, the script's name followed by its arguments
+    pub fn command_line(mut self, command_line: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.command_line = command_line.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn build(self) -> InterpreterContext {
+        let (heap, gc) = InterpreterHeap::new();
+        let gc = self.gc_interval.map(|interval| gc.with_delay(interval));
+
+        let mut context = InterpreterContext::bare(self.error_writer, heap, gc, self.output);
+        context.with_syntax();
+        for module in self.modules {
+            context.with_module(module);
+        }
+        #[cfg(feature = "prelude")]
+        if self.prelude {
+            context.load_prelude();
+        }
+        library::register_builtin_libraries(&context);
+
+        for dir in self.search_path {
+            context.add_search_path(dir);
+        }
+        // Applied last, so loading the standard library is not held to them. The objects
+        // it leaves live are not counted either, the heap limit only bounding growth
+        context.limits = self.limits;
+        context.capabilities = self.capabilities;
+        context.command_line = self.command_line;
+        context
+    }
+}
+
+#[cfg(all(test, feature = "prelude"))]
+mod test {
+    use crate::{
+        testing::{builder, eval},
+        Interpreter,
+    };
+
+    #[test]
+    fn prelude_defines_derived_procedures() {
+        for (source, expected) in [
+            ("(not #f)", "true"),
+            ("(quotient 7 2)", "3"),
+            ("(remainder -7 2)", "-1"),
+            ("(modulo -7 2)", "1"),
+            ("(even? 4)", "true"),
+            ("(abs -3)", "3"),
+            ("(gcd 12 18)", "6"),
+            ("(lcm 4 6)", "12"),
+            ("(max 1 5 3)", "5"),
+            ("(min 4 2)", "2"),
+            ("(last (list 1 2 3))", "3"),
+            ("(take (list 1 2 3) 2)", "1:2:()"),
+            ("(drop (list 1 2 3) 2)", "3:()"),
+            ("(make-list 2 0)", "0:0:()"),
+            ("(list-tabulate 3 square)", "0:1:4:()"),
+            ("(append-map (lambda (x) (list x x)) (list 1 2))", "1:1:2:2:()"),
+            ("(filter-map (lambda (x) (if (> x 1) (* x 10) #f)) (list 1 2 3))", "20:30:()"),
+            ("(delete-duplicates (list 1 2 1 3 2))", "1:2:3:()"),
+        ] {
+            assert_eq!(eval(source), Ok(expected.to_string()), "{source}");
+        }
+    }
+
+    #[test]
+    fn prelude_parameters_do_not_shadow_callers() {
+        // Names in the prelude start with `%`, so a procedure passed in still sees the
+        // caller's variables under dynamic scoping
+        assert_eq!(
+            eval("(define (g l) (filter-map (lambda (x) (+ x (car l))) (list 1 2))) (g (list 10))"),
+            Ok("11:12:()".to_string())
+        );
+        assert_eq!(
+            eval("(define (g k) (list-tabulate 2 (lambda (i) (* i k)))) (g 5)"),
+            Ok("0:5:()".to_string())
+        );
+    }
+
+    #[test]
+    fn prelude_can_be_left_out() {
+        let interpreter = Interpreter::from_context(builder().prelude(false).build());
+        assert!(interpreter.eval_str("(abs -3)").is_err());
+        assert_eq!(interpreter.eval_str("(car (list 1))").unwrap().get::<i64>(), Some(1));
+    }
+}
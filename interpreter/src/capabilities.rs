@@ -0,0 +1,226 @@
+use std::path::{Path, PathBuf};
+
+use crate::{InterpreterError, InterpreterErrorKind, InterpreterResult};
+
+/// Which files `import` may load
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ImportPolicy {
+    /// Any file on the search path which may be read
+    #[default]
+    Any,
+    /// No files, only libraries which are already defined, such as `(scheme base)`
+    Disabled,
+    /// Only files within these directories
+    Within(Vec<PathBuf>),
+}
+
+/// What a script may touch outside the interpreter. Paths are compared once symlinks
+/// and `..` are resolved, so neither can lead out of a root
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Capabilities {
+    /// Directories files may be read from, `None` allowing any
+    pub read_roots: Option<Vec<PathBuf>>,
+    /// Directories files may be written to, `None` allowing any. Natives registered by
+    /// the host which write files should check against these with [`Capabilities::check_write`]
+    pub write_roots: Option<Vec<PathBuf>>,
+    pub imports: ImportPolicy,
+    /// Environment variables which may be read, `None` allowing any
+    pub environment: Option<Vec<String>>,
+    /// Programs `process-run` may start, by the name it is given, `None` allowing any.
+    /// A program runs with the host's own access, outside the other restrictions
+    pub programs: Option<Vec<String>>,
+}
+
+/// The absolute form of `path`. A path which does not exist yet, such as a file about
+/// to be written, is resolved through its parent
+fn resolve(path: &Path) -> Option<PathBuf> {
+    if let Ok(path) = path.canonicalize() {
+        return Some(path);
+    }
+    let parent = match path.parent()? {
+        parent if parent.as_os_str().is_empty() => Path::new("."),
+        parent => parent,
+    };
+    Some(parent.canonicalize().ok()?.join(path.file_name()?))
+}
+
+fn within(roots: &[PathBuf], path: &Path) -> bool {
+    let Some(path) = resolve(path) else {
+        return false;
+    };
+    roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| path.starts_with(root))
+}
+
+fn check(roots: Option<&[PathBuf]>, path: &Path, action: &'static str) -> InterpreterResult<()> {
+    match roots {
+        Some(roots) if !within(roots, path) => Err(InterpreterError::new(
+            InterpreterErrorKind::AccessDenied(action, path.to_string_lossy().into_owned()),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn allowed(names: Option<&[String]>, name: &str, action: &'static str) -> InterpreterResult<()> {
+    match names {
+        Some(names) if !names.iter().any(|n| n == name) => Err(InterpreterError::new(
+            InterpreterErrorKind::AccessDenied(action, name.to_string()),
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl Capabilities {
+    /// Confines reading, writing and importing to `dir`, with no environment variables
+    /// or programs
+    pub fn workspace(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        Self {
+            read_roots: Some(vec![dir.clone()]),
+            write_roots: Some(vec![dir.clone()]),
+            imports: ImportPolicy::Within(vec![dir]),
+            environment: Some(Vec::new()),
+            programs: Some(Vec::new()),
+        }
+    }
+
+    pub fn check_read(&self, path: &Path) -> InterpreterResult<()> {
+        check(self.read_roots.as_deref(), path, "reading")
+    }
+
+    pub fn check_write(&self, path: &Path) -> InterpreterResult<()> {
+        check(self.write_roots.as_deref(), path, "writing")
+    }
+
+    pub fn check_environment(&self, name: &str) -> InterpreterResult<()> {
+        allowed(self.environment.as_deref(), name, "reading the environment variable")
+    }
+
+    pub fn check_run(&self, program: &str) -> InterpreterResult<()> {
+        allowed(self.programs.as_deref(), program, "running")
+    }
+
+    /// Checks `path` may be imported, `name` being the import as it was written. An
+    /// import reads the file, so it must be within the read roots as well
+    pub fn check_import(&self, name: &str, path: &Path) -> InterpreterResult<()> {
+        match &self.imports {
+            ImportPolicy::Any => Ok(()),
+            ImportPolicy::Disabled => Err(InterpreterError::new(
+                InterpreterErrorKind::ImportDisabled(name.to_string()),
+            )),
+            ImportPolicy::Within(roots) => check(Some(roots), path, "importing"),
+        }?;
+        self.check_read(path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{Capabilities, ImportPolicy};
+    use crate::{testing::builder, Interpreter};
+
+    /// A workspace directory holding `lib.sld`, beside a directory holding `secret.sld`
+    fn directories(name: &str) -> (PathBuf, PathBuf) {
+        let base = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        let (workspace, secret) = (base.join("workspace"), base.join("secret"));
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::fs::create_dir_all(&secret).unwrap();
+        std::fs::write(
+            workspace.join("lib.sld"),
+            "(define-library (lib) (export x) (begin (define x 1)))",
+        )
+        .unwrap();
+        std::fs::write(
+            secret.join("secret.sld"),
+            "(define-library (secret) (export y) (begin (define y 2)))",
+        )
+        .unwrap();
+        (workspace, secret)
+    }
+
+    fn interpreter(capabilities: Capabilities, search_path: &[&PathBuf]) -> Interpreter {
+        let builder = search_path.iter().fold(
+            builder(),
+            |builder, dir| builder.search_path(*dir),
+        );
+        Interpreter::from_context(builder.capabilities(capabilities).build())
+    }
+
+    fn denied(interpreter: &Interpreter, source: &str) -> bool {
+        interpreter
+            .eval_str(source)
+            .is_err_and(|err| err.to_string().starts_with("Access denied"))
+    }
+
+    #[test]
+    fn reads_are_confined_to_the_roots() {
+        let (workspace, secret) = directories("capabilities-read");
+        let interpreter = interpreter(Capabilities::workspace(&workspace), &[]);
+
+        let inside = workspace.join("lib.sld");
+        let outside = secret.join("secret.sld");
+        let escape = workspace.join("..").join("secret").join("secret.sld");
+        assert!(interpreter
+            .eval_str(&format!("(file->string {:?})", inside))
+            .is_ok());
+        assert!(denied(&interpreter, &format!("(file->string {:?})", outside)));
+        assert!(denied(&interpreter, &format!("(file->string {:?})", escape)));
+    }
+
+    #[test]
+    fn imports_are_confined_to_the_read_roots() {
+        let (workspace, secret) = directories("capabilities-import");
+        let capabilities = Capabilities {
+            read_roots: Some(vec![workspace.clone()]),
+            imports: ImportPolicy::Any,
+            ..Capabilities::default()
+        };
+        let interpreter = interpreter(capabilities, &[&workspace, &secret]);
+
+        assert!(interpreter.eval_str("(import (lib)) x").is_ok());
+        assert!(denied(&interpreter, "(import (secret))"));
+    }
+
+    #[test]
+    fn imports_are_confined_to_the_import_roots() {
+        let (workspace, secret) = directories("capabilities-policy");
+        let capabilities = Capabilities {
+            imports: ImportPolicy::Within(vec![workspace.clone()]),
+            ..Capabilities::default()
+        };
+        let interpreter = interpreter(capabilities, &[&workspace, &secret]);
+
+        assert!(interpreter.eval_str("(import (lib)) x").is_ok());
+        assert!(denied(&interpreter, "(import (secret))"));
+    }
+
+    #[test]
+    fn disabled_imports_still_allow_builtin_libraries() {
+        let (workspace, _) = directories("capabilities-disabled");
+        let capabilities = Capabilities {
+            imports: ImportPolicy::Disabled,
+            ..Capabilities::default()
+        };
+        let interpreter = interpreter(capabilities, &[&workspace]);
+
+        assert!(interpreter.eval_str("(import (scheme write))").is_ok());
+        assert!(interpreter.eval_str("(import (lib))").is_err());
+    }
+
+    #[test]
+    fn workspace_denies_environment_and_programs() {
+        let (workspace, _) = directories("capabilities-process");
+        let interpreter = interpreter(Capabilities::workspace(&workspace), &[]);
+
+        assert!(denied(&interpreter, "(get-environment-variable \"PATH\")"));
+        assert!(denied(&interpreter, "(process-run \"true\")"));
+        assert!(interpreter
+            .eval_str("(get-environment-variables)")
+            .is_ok_and(|vars| vars.to_string() == "()"));
+    }
+}
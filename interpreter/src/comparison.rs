@@ -1,6 +1,8 @@
-use std::{cmp::Ordering, ops::Deref};
+use core::literal::{Literal, Numeric};
+use std::{cmp::Ordering, collections::HashSet, ops::Deref};
 
 use crate::{
+    alloc::InterpreterHeapAlloc,
     deref::InterpreterDeref,
     error::{InterpreterError, InterpreterErrorKind},
     object::{HeapObject, ObjectPointer, ObjectRef, StackObject},
@@ -9,179 +11,292 @@ use crate::{
 };
 
 pub trait InterpreterComparison<T> {
-    fn object_eq(&self, rhs: &T, interpreter: &InterpreterContext) -> InterpreterResult<bool>;
-
     fn object_cmp(&self, rhs: &T, interpreter: &InterpreterContext) -> InterpreterResult<Ordering>;
 }
 
 impl InterpreterComparison<Self> for StackObject {
-    fn object_eq(&self, rhs: &Self, interpreter: &InterpreterContext) -> InterpreterResult<bool> {
-        Ok(self == rhs
-            || InterpreterComparison::object_eq(
-                &self.deref(interpreter)?,
-                &rhs.deref(interpreter)?,
-                interpreter,
-            )?)
-    }
-
     fn object_cmp(
         &self,
         rhs: &Self,
         interpreter: &InterpreterContext,
     ) -> InterpreterResult<Ordering> {
-        InterpreterComparison::object_cmp(
-            &self.deref(interpreter)?,
-            &rhs.deref(interpreter)?,
-            interpreter,
-        )
+        ordering(self.deref(interpreter)?, rhs.deref(interpreter)?, interpreter)
     }
 }
 
 impl InterpreterComparison<Self> for ObjectPointer {
-    fn object_eq(&self, rhs: &Self, interpreter: &InterpreterContext) -> InterpreterResult<bool> {
-        Ok(self == rhs
-            || InterpreterComparison::object_eq(
-                &self.deref(interpreter)?,
-                &rhs.deref(interpreter)?,
-                interpreter,
-            )?)
-    }
-
     fn object_cmp(
         &self,
         rhs: &Self,
         interpreter: &InterpreterContext,
     ) -> InterpreterResult<Ordering> {
-        InterpreterComparison::object_cmp(
-            &self.deref(interpreter)?,
-            &rhs.deref(interpreter)?,
-            interpreter,
-        )
+        ordering(self.deref(interpreter)?, rhs.deref(interpreter)?, interpreter)
     }
 }
 
-impl InterpreterComparison<Self> for HeapObject {
-    fn object_eq(&self, rhs: &Self, interpreter: &InterpreterContext) -> InterpreterResult<bool> {
-        match (self, rhs) {
-            (HeapObject::Value(l), HeapObject::Value(r)) => Ok(l == r),
-            (HeapObject::String(l), HeapObject::String(r)) => Ok(l == r),
-            (HeapObject::Func(l), HeapObject::Func(r)) => Ok(l == r),
-            (HeapObject::List(l, ls), HeapObject::List(r, rs)) => {
-                Ok(l.object_eq(r, interpreter)? && ls.object_eq(rs, interpreter)?)
+fn list_parts(obj: &ObjectRef) -> Option<(ObjectPointer, ObjectPointer)> {
+    match obj {
+        ObjectRef::Object(o) => match o.deref() {
+            HeapObject::List(h, t) => Some((h.clone(), t.clone())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Orders numbers, characters, booleans and strings against their own kind,
+/// and lists lexicographically by element, a list ordering after any list it extends
+fn ordering<'a>(
+    mut l: ObjectRef<'a>,
+    mut r: ObjectRef<'a>,
+    interpreter: &'a InterpreterContext,
+) -> InterpreterResult<Ordering> {
+    loop {
+        let (Some((lh, lt)), Some((rh, rt))) = (list_parts(&l), list_parts(&r)) else {
+            return scalar_ordering(&l, &r, interpreter);
+        };
+        drop((l, r));
+
+        match lh.object_cmp(&rh, interpreter)? {
+            Ordering::Equal => {
+                l = lt.deref(interpreter)?;
+                r = rt.deref(interpreter)?;
             }
-            _ => Ok(false),
+            o => return Ok(o),
         }
     }
+}
 
-    fn object_cmp(
-        &self,
-        rhs: &Self,
-        interpreter: &InterpreterContext,
-    ) -> InterpreterResult<Ordering> {
-        match (self, rhs) {
-            (HeapObject::Value(l), HeapObject::Value(r)) => Ok(l.cmp(r)),
-            (l, r) => Err(InterpreterError::new(InterpreterErrorKind::CannotCompare(
-                dbg!(l).interpreter_fmt(interpreter),
-                dbg!(r).interpreter_fmt(interpreter),
-            ))),
-        }
+fn scalar_ordering(
+    l: &ObjectRef,
+    r: &ObjectRef,
+    interpreter: &InterpreterContext,
+) -> InterpreterResult<Ordering> {
+    let ordering = match (l, r) {
+        (ObjectRef::Null, ObjectRef::Null) => Some(Ordering::Equal),
+        (ObjectRef::Null, r) if list_parts(r).is_some() => Some(Ordering::Less),
+        (l, ObjectRef::Null) if list_parts(l).is_some() => Some(Ordering::Greater),
+        (ObjectRef::Object(lo), ObjectRef::Object(ro)) => match (lo.deref(), ro.deref()) {
+            (HeapObject::String(ls), HeapObject::String(rs)) => Some(ls.cmp(rs)),
+            _ => literal_ordering(l.literal(), r.literal()),
+        },
+        _ => literal_ordering(l.literal(), r.literal()),
+    };
+
+    ordering.ok_or_else(|| {
+        InterpreterError::new(InterpreterErrorKind::CannotCompare(
+            l.interpreter_fmt(interpreter),
+            r.interpreter_fmt(interpreter),
+        ))
+    })
+}
+
+fn literal_ordering(l: Option<Literal>, r: Option<Literal>) -> Option<Ordering> {
+    match (l?, r?) {
+        (Literal::Numeric(l), Literal::Numeric(r)) => Some(l.cmp(&r)),
+        (Literal::Character(l), Literal::Character(r)) => Some(l.cmp(&r)),
+        (Literal::Boolean(l), Literal::Boolean(r)) => Some(l.cmp(&r)),
+        _ => None,
     }
 }
 
-impl InterpreterComparison<Self> for ObjectRef<'_> {
-    fn object_eq(&self, rhs: &Self, interpreter: &InterpreterContext) -> InterpreterResult<bool> {
-        match (self, rhs) {
-            (ObjectRef::Null, ObjectRef::Null) => Ok(true),
-            (ObjectRef::Value(l), ObjectRef::Value(r)) => Ok(l == r),
-            (ObjectRef::Object(l), ObjectRef::Object(r)) => {
-                let l = &*l.deref();
-                let r = &*r.deref();
-                l.object_eq(r, interpreter)
-            }
-            (ObjectRef::Object(l), r @ ObjectRef::Value(_))
-            | (r @ ObjectRef::Value(_), ObjectRef::Object(l)) => {
-                r.object_eq(&*l.deref(), interpreter)
+/// What an object is compared by when checking equivalence
+#[derive(Debug, Clone, PartialEq)]
+enum Identity {
+    Null,
+    /// A literal value, with the heap slot it lives in if it has been allocated
+    Literal(Literal, Option<usize>),
+    Object(usize),
+}
+
+impl Identity {
+    fn of(pointer: &ObjectPointer, interpreter: &InterpreterContext) -> InterpreterResult<Self> {
+        Ok(match pointer {
+            ObjectPointer::Null => Identity::Null,
+            ObjectPointer::Heap(p) => match pointer.deref(interpreter)?.literal() {
+                Some(v) => Identity::Literal(v, Some(**p)),
+                None => Identity::Object(**p),
+            },
+            ObjectPointer::Stack(f, i) => {
+                Identity::of(&interpreter.stack.get_stack_object(*f, *i)?, interpreter)?
             }
-            _ => Ok(false),
+        })
+    }
+
+    fn is_eq(&self, rhs: &Self) -> bool {
+        match (self, rhs) {
+            (Identity::Literal(_, Some(l)), Identity::Literal(_, Some(r))) if l == r => true,
+            (Identity::Literal(Literal::Numeric(Numeric::Float(_)), _), _) => false,
+            (l, r) => l.is_eqv(r),
         }
     }
 
-    fn object_cmp(
-        &self,
-        rhs: &Self,
-        interpreter: &InterpreterContext,
-    ) -> InterpreterResult<Ordering> {
+    fn is_eqv(&self, rhs: &Self) -> bool {
         match (self, rhs) {
-            (ObjectRef::Value(l), ObjectRef::Value(r)) => Ok(l.cmp(r)),
-            (ObjectRef::Object(l), ObjectRef::Object(r)) => {
-                let l = &*l.deref();
-                let r = &*r.deref();
-                l.object_cmp(r, interpreter)
-            }
-            (ObjectRef::Object(l), r @ ObjectRef::Value(_)) => {
-                l.object_cmp(r, interpreter)
+            (Identity::Null, Identity::Null) => true,
+            (Identity::Literal(l, _), Identity::Literal(r, _)) => l == r,
+            (Identity::Object(l), Identity::Object(r)) => l == r,
+            _ => false,
+        }
+    }
+}
+
+pub trait InterpreterEquivalence {
+    /// `eq?`, identity of heap objects with booleans, characters and integers compared by value
+    fn is_eq(&self, rhs: &Self, interpreter: &InterpreterContext) -> InterpreterResult<bool>;
+
+    /// `eqv?`, as `eq?` but comparing every number and character by value
+    fn is_eqv(&self, rhs: &Self, interpreter: &InterpreterContext) -> InterpreterResult<bool>;
+
+    /// `equal?`, recursively comparing the contents of lists and strings
+    fn is_equal(&self, rhs: &Self, interpreter: &InterpreterContext) -> InterpreterResult<bool>;
+}
+
+impl InterpreterEquivalence for ObjectPointer {
+    fn is_eq(&self, rhs: &Self, interpreter: &InterpreterContext) -> InterpreterResult<bool> {
+        Ok(Identity::of(self, interpreter)?.is_eq(&Identity::of(rhs, interpreter)?))
+    }
+
+    fn is_eqv(&self, rhs: &Self, interpreter: &InterpreterContext) -> InterpreterResult<bool> {
+        Ok(Identity::of(self, interpreter)?.is_eqv(&Identity::of(rhs, interpreter)?))
+    }
+
+    fn is_equal(&self, rhs: &Self, interpreter: &InterpreterContext) -> InterpreterResult<bool> {
+        // Pairs of lists already being compared are assumed equal, so cycles terminate
+        let mut visited = HashSet::new();
+        let mut queue = vec![(self.clone(), rhs.clone())];
+
+        while let Some((l, r)) = queue.pop() {
+            let (l_id, r_id) = (Identity::of(&l, interpreter)?, Identity::of(&r, interpreter)?);
+            if l_id.is_eqv(&r_id) {
+                continue;
             }
-            (l @ ObjectRef::Value(_), ObjectRef::Object(r)) => {
-                r.object_cmp(l, interpreter)
+            let (Identity::Object(l_index), Identity::Object(r_index)) = (l_id, r_id) else {
+                return Ok(false);
+            };
+
+            let (l_ref, r_ref) = (l.deref(interpreter)?, r.deref(interpreter)?);
+            let (ObjectRef::Object(l_obj), ObjectRef::Object(r_obj)) = (&l_ref, &r_ref) else {
+                return Ok(false);
+            };
+            match (l_obj.deref(), r_obj.deref()) {
+                (HeapObject::String(l), HeapObject::String(r)) if l == r => (),
+                (HeapObject::List(lh, lt), HeapObject::List(rh, rt)) => {
+                    if visited.insert((l_index, r_index)) {
+                        queue.push((lt.clone(), rt.clone()));
+                        queue.push((lh.clone(), rh.clone()));
+                    }
+                }
+                _ => return Ok(false),
             }
-            (l, r) => Err(InterpreterError::new(InterpreterErrorKind::CannotCompare(
-                l.interpreter_fmt(interpreter),
-                r.interpreter_fmt(interpreter),
-            ))),
         }
+        Ok(true)
     }
 }
 
-impl InterpreterComparison<HeapObject> for ObjectRef<'_> {
-    fn object_eq(
-        &self,
-        rhs: &HeapObject,
-        _interpreter: &InterpreterContext,
-    ) -> InterpreterResult<bool> {
+impl InterpreterEquivalence for StackObject {
+    fn is_eq(&self, rhs: &Self, interpreter: &InterpreterContext) -> InterpreterResult<bool> {
         match (self, rhs) {
-            (ObjectRef::Value(l), HeapObject::Value(r)) => Ok(l == r),
-            _ => Ok(false),
+            (StackObject::Value(l), StackObject::Value(r)) => {
+                Ok(Identity::Literal(*l, None).is_eq(&Identity::Literal(*r, None)))
+            }
+            _ => self
+                .clone()
+                .heap_alloc(interpreter)?
+                .is_eq(&rhs.clone().heap_alloc(interpreter)?, interpreter),
         }
     }
 
-    fn object_cmp(
-        &self,
-        rhs: &HeapObject,
-        interpreter: &InterpreterContext,
-    ) -> InterpreterResult<Ordering> {
+    fn is_eqv(&self, rhs: &Self, interpreter: &InterpreterContext) -> InterpreterResult<bool> {
         match (self, rhs) {
-            (ObjectRef::Value(l), HeapObject::Value(r)) => Ok(l.cmp(r)),
-            (l, r) => Err(InterpreterError::new(InterpreterErrorKind::CannotCompare(
-                l.interpreter_fmt(interpreter),
-                r.interpreter_fmt(interpreter),
-            ))),
+            (StackObject::Value(l), StackObject::Value(r)) => Ok(l == r),
+            _ => self
+                .clone()
+                .heap_alloc(interpreter)?
+                .is_eqv(&rhs.clone().heap_alloc(interpreter)?, interpreter),
         }
     }
-}
 
-impl InterpreterComparison<ObjectRef<'_>> for HeapObject {
-    fn object_eq(
-        &self,
-        rhs: &ObjectRef<'_>,
-        _interpreter: &InterpreterContext,
-    ) -> InterpreterResult<bool> {
+    fn is_equal(&self, rhs: &Self, interpreter: &InterpreterContext) -> InterpreterResult<bool> {
         match (self, rhs) {
-            (HeapObject::Value(l), ObjectRef::Value(r)) => Ok(l == r),
-            _ => Ok(false),
+            (StackObject::Value(l), StackObject::Value(r)) => Ok(l == r),
+            _ => self
+                .clone()
+                .heap_alloc(interpreter)?
+                .is_equal(&rhs.clone().heap_alloc(interpreter)?, interpreter),
         }
     }
+}
 
-    fn object_cmp(
-        &self,
-        rhs: &ObjectRef<'_>,
-        interpreter: &InterpreterContext,
-    ) -> InterpreterResult<Ordering> {
-        match (self, rhs) {
-            (HeapObject::Value(l), ObjectRef::Value(r)) => Ok(l.cmp(r)),
-            (l, r) => Err(InterpreterError::new(InterpreterErrorKind::CannotCompare(
-                l.interpreter_fmt(interpreter),
-                r.interpreter_fmt(interpreter),
-            ))),
-        }
+#[cfg(test)]
+mod test {
+    use core::literal::{Literal, Numeric};
+
+    use super::InterpreterEquivalence;
+    use crate::{
+        alloc::InterpreterHeapAlloc,
+        list::{InterpreterList, InterpreterListAlloc},
+        object::{HeapObject, ObjectPointer},
+        testing::{eval, interpreter},
+        InterpreterContext,
+    };
+
+    /// The list of `values`, with its last pair pointing back to its first
+    fn cycle(interpreter: &InterpreterContext, values: &[i32]) -> ObjectPointer {
+        let values = values
+            .iter()
+            .map(|v| HeapObject::Value(Literal::Numeric(Numeric::Int(*v))).heap_alloc(interpreter))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let last = values.last().unwrap().clone();
+        let list = values.to_list(interpreter).unwrap();
+
+        let (spine, _) = list.list_spine(interpreter).unwrap();
+        let ObjectPointer::Heap(index) = &spine.last().unwrap().0 else {
+            panic!("pairs are on the heap");
+        };
+        let mut store = interpreter.heap.store.write().unwrap();
+        store[**index].as_mut().unwrap().0 = HeapObject::List(last, list.clone());
+        list
+    }
+
+    #[test]
+    fn eq_compares_identity() {
+        assert_eq!(eval("(define x (list 1)) (eq? x x)"), Ok("true".to_string()));
+        assert_eq!(eval("(eq? (list 1) (list 1))"), Ok("false".to_string()));
+        assert_eq!(eval("(eq? \"a\" \"a\")"), Ok("false".to_string()));
+        assert_eq!(eval("(eq? 2 2)"), Ok("true".to_string()));
+        assert_eq!(eval("(eq? car car)"), Ok("true".to_string()));
+        assert_eq!(eval("(eq? 1.5 1.5)"), Ok("false".to_string()));
+        assert_eq!(eval("(define x 1.5) (eq? x x)"), Ok("true".to_string()));
+    }
+
+    #[test]
+    fn eqv_compares_numbers_and_characters_by_value() {
+        assert_eq!(eval("(eqv? 1.5 1.5)"), Ok("true".to_string()));
+        assert_eq!(eval("(eqv? #\\a #\\a)"), Ok("true".to_string()));
+        assert_eq!(eval("(eqv? 2 2.0)"), Ok("false".to_string()));
+        assert_eq!(eval("(eqv? (list) (list))"), Ok("true".to_string()));
+        assert_eq!(eval("(eqv? (list 1) (list 1))"), Ok("false".to_string()));
+    }
+
+    #[test]
+    fn equal_compares_contents() {
+        assert_eq!(eval("(equal? (list 1 (list 2)) (list 1 (list 2)))"), Ok("true".to_string()));
+        assert_eq!(eval("(equal? (list 1 (list 2)) (list 1 (list 3)))"), Ok("false".to_string()));
+        assert_eq!(eval("(equal? \"a\" \"a\")"), Ok("true".to_string()));
+        assert_eq!(eval("(== (list 1) (list 1))"), Ok("true".to_string()));
+    }
+
+    #[test]
+    fn equal_terminates_on_cycles() {
+        let interpreter = interpreter();
+        let interpreter = interpreter.context();
+        let a = cycle(interpreter, &[1, 2]);
+        let b = cycle(interpreter, &[1, 2]);
+        let c = cycle(interpreter, &[1, 3]);
+        assert!(a.is_equal(&b, interpreter).unwrap());
+        assert!(!a.is_equal(&c, interpreter).unwrap());
+        assert!(!a.is_eq(&b, interpreter).unwrap());
     }
 }
@@ -0,0 +1,147 @@
+use core::parser::ast::AST;
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use crate::{
+    exception::Handler,
+    frame::Frame,
+    func::Func,
+    object::{ObjectPointer, StackObject},
+    parameter::Parameter,
+    QueueOp,
+};
+
+/// The heights of the data and frame stacks when an evaluation started,
+/// below which it never reads or writes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Base {
+    pub data: usize,
+    pub frames: usize,
+}
+
+impl Base {
+    pub(crate) fn rebase_data(&self, to: &Base, height: usize) -> usize {
+        height - self.data + to.data
+    }
+
+    pub(crate) fn rebase_frames(&self, to: &Base, height: usize) -> usize {
+        height - self.frames + to.frames
+    }
+}
+
+/// An entry on the `dynamic-wind` stack
+#[derive(Debug)]
+pub struct Winder {
+    pub before: ObjectPointer,
+    pub after: ObjectPointer,
+}
+
+/// Everything needed to carry on from a `call/cc`, taken from the evaluation it was
+/// called in. The queued work refers into `roots`, copies of the code left to run
+#[derive(Debug)]
+pub struct Snapshot {
+    pub(crate) evaluation: usize,
+    /// Whether the evaluation was a top level one, rather than nested in a native
+    pub(crate) outermost: bool,
+    pub(crate) base: Base,
+    pub(crate) op_stack: Vec<QueueOp<'static>>,
+    pub(crate) func_cache: HashMap<u64, Func>,
+    pub(crate) data: Vec<StackObject>,
+    pub(crate) frames: Vec<Frame>,
+    pub(crate) handlers: Vec<Handler>,
+    pub(crate) winders: Vec<Arc<Winder>>,
+    pub(crate) parameters: Vec<(Parameter, ObjectPointer)>,
+    #[allow(dead_code)] // only held, for `op_stack` to refer into
+    pub(crate) roots: Vec<Arc<AST>>,
+}
+
+/// A captured continuation, compared and hashed by identity
+#[derive(Debug, Clone)]
+pub struct Continuation(pub Arc<Snapshot>);
+
+impl Continuation {
+    pub fn snapshot(&self) -> &Snapshot {
+        &self.0
+    }
+}
+
+impl PartialEq for Continuation {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Continuation {}
+
+impl PartialOrd for Continuation {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Continuation {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        Arc::as_ptr(&self.0).cmp(&Arc::as_ptr(&other.0))
+    }
+}
+
+impl Hash for Continuation {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).hash(state)
+    }
+}
+
+/// Copies `ast` into `roots`, returning a reference which lives as long as they do
+pub(crate) fn own(roots: &mut Vec<Arc<AST>>, ast: &AST) -> &'static AST {
+    let ast = Arc::new(ast.clone());
+    // The AST is never moved or dropped while `roots` holds it, and the snapshot
+    // owning `roots` is held by any op stack made from it
+    let r = unsafe { &*Arc::as_ptr(&ast) };
+    roots.push(ast);
+    r
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::eval;
+
+    #[test]
+    fn escaping_continuations_discard_the_rest() {
+        assert_eq!(eval("(+ 1 (call/cc (lambda (k) (+ 10 (k 2)))))"), Ok("3".to_string()));
+        assert_eq!(eval("(call/cc (lambda (k) (map (lambda (x) (k x)) (list 1 2))))"), Ok("1".to_string()));
+    }
+
+    #[test]
+    fn top_level_continuations_can_be_reentered() {
+        assert_eq!(
+            eval("(define r (call/cc (lambda (c) c))) (if (procedure? r) (r 5) 0) r"),
+            Ok("5".to_string())
+        );
+        assert_eq!(
+            eval("(define r (call/cc (lambda (c) c))) (if (procedure? r) (+ 1 (r 5)) 0) (+ r 1)"),
+            Ok("6".to_string())
+        );
+    }
+
+    #[test]
+    fn let_bindings_can_be_reentered() {
+        assert_eq!(
+            eval("(define (f) (let ((k (call/cc (lambda (c) c)))) (if (procedure? k) (k 5) k))) (f)"),
+            Ok("5".to_string())
+        );
+        assert_eq!(
+            eval("(define (f) (let ((k (call/cc (lambda (c) c)))) (if (procedure? k) (k 5) (+ k 1))))
+                  (guard (e (#t 0)) (f))"),
+            Ok("6".to_string())
+        );
+        assert_eq!(
+            eval("(define (g) (let ((x (call/cc (lambda (k) (list k 0)))))
+                    (if (< (cadr x) 3) ((car x) (list (car x) (+ (cadr x) 1))) (cadr x))))
+                  (g)"),
+            Ok("3".to_string())
+        );
+    }
+}
@@ -0,0 +1,450 @@
+use core::literal::{Literal, Numeric};
+use std::{collections::HashMap, fmt::Display, hash::Hash, ops::Deref};
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    deref::InterpreterDeref,
+    func::NativeFunc,
+    list::{InterpreterList, InterpreterListAlloc},
+    object::{HeapObject, ObjectPointer, ObjectRef, StackObject},
+    std_lib::{
+        pop_params,
+        types::{expect_params, wrong_type},
+    },
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+/// Rust values which can be read out of Scheme objects
+pub trait FromScheme: Sized {
+    /// The expected kind of object, as it is named in a wrong type error
+    const EXPECTED: &'static str;
+
+    fn from_scheme(interpreter: &InterpreterContext, obj: &ObjectPointer) -> Option<Self>;
+
+    /// Reads argument `arg_index` of `procedure`, counting from 1
+    fn from_arg(
+        interpreter: &InterpreterContext,
+        procedure: &str,
+        arg_index: usize,
+        obj: &ObjectPointer,
+    ) -> InterpreterResult<Self> {
+        Self::from_scheme(interpreter, obj)
+            .ok_or_else(|| wrong_type(interpreter, procedure, arg_index, Self::EXPECTED, obj))
+    }
+}
+
+/// Rust values which can be turned into Scheme objects
+pub trait IntoScheme {
+    fn into_scheme(self, interpreter: &InterpreterContext) -> InterpreterResult<StackObject>;
+
+    fn into_scheme_pointer(self, interpreter: &InterpreterContext) -> InterpreterResult<ObjectPointer>
+    where
+        Self: Sized,
+    {
+        self.into_scheme(interpreter)?.heap_alloc(interpreter)
+    }
+}
+
+fn literal(interpreter: &InterpreterContext, obj: &ObjectPointer) -> Option<Literal> {
+    obj.deref(interpreter).ok()?.literal()
+}
+
+fn value(lit: Literal) -> InterpreterResult<StackObject> {
+    Ok(StackObject::Value(lit))
+}
+
+impl FromScheme for ObjectPointer {
+    const EXPECTED: &'static str = "an object";
+
+    fn from_scheme(_interpreter: &InterpreterContext, obj: &ObjectPointer) -> Option<Self> {
+        Some(obj.clone())
+    }
+}
+
+impl IntoScheme for ObjectPointer {
+    fn into_scheme(self, _interpreter: &InterpreterContext) -> InterpreterResult<StackObject> {
+        Ok(StackObject::Ref(self))
+    }
+}
+
+impl IntoScheme for () {
+    fn into_scheme(self, _interpreter: &InterpreterContext) -> InterpreterResult<StackObject> {
+        Ok(StackObject::Ref(ObjectPointer::Null))
+    }
+}
+
+impl FromScheme for i64 {
+    const EXPECTED: &'static str = "an integer";
+
+    fn from_scheme(interpreter: &InterpreterContext, obj: &ObjectPointer) -> Option<Self> {
+        match literal(interpreter, obj)? {
+            Literal::Numeric(Numeric::Int(i)) => Some(i as i64),
+            _ => None,
+        }
+    }
+}
+
+impl IntoScheme for i64 {
+    fn into_scheme(self, _interpreter: &InterpreterContext) -> InterpreterResult<StackObject> {
+        let i = i32::try_from(self).map_err(|_| {
+            InterpreterError::new(InterpreterErrorKind::CannotConvertType(
+                self.to_string(),
+                "integer".to_string(),
+            ))
+        })?;
+        value(Literal::Numeric(Numeric::Int(i)))
+    }
+}
+
+/// Floats are widened through their shortest decimal form, so 0.1 stays 0.1
+impl FromScheme for f64 {
+    const EXPECTED: &'static str = "a number";
+
+    fn from_scheme(interpreter: &InterpreterContext, obj: &ObjectPointer) -> Option<Self> {
+        match literal(interpreter, obj)? {
+            Literal::Numeric(Numeric::Int(i)) => Some(i as f64),
+            Literal::Numeric(Numeric::Float(f)) if f.is_finite() => f.to_string().parse().ok(),
+            Literal::Numeric(Numeric::Float(f)) => Some(f as f64),
+            _ => None,
+        }
+    }
+}
+
+/// Floats are single precision, so the value is rounded to the nearest one, and
+/// `16777217.0` becomes `16777216.0`. A finite value too large for a single precision
+/// float cannot be converted
+impl IntoScheme for f64 {
+    fn into_scheme(self, _interpreter: &InterpreterContext) -> InterpreterResult<StackObject> {
+        let f = self as f32;
+        if self.is_finite() && !f.is_finite() {
+            return Err(InterpreterError::new(InterpreterErrorKind::CannotConvertType(
+                self.to_string(),
+                "float".to_string(),
+            )));
+        }
+        value(Literal::Numeric(Numeric::Float(f)))
+    }
+}
+
+impl FromScheme for bool {
+    const EXPECTED: &'static str = "a boolean";
+
+    fn from_scheme(interpreter: &InterpreterContext, obj: &ObjectPointer) -> Option<Self> {
+        match literal(interpreter, obj)? {
+            Literal::Boolean(b) => Some(b),
+            _ => None,
+        }
+    }
+}
+
+impl IntoScheme for bool {
+    fn into_scheme(self, _interpreter: &InterpreterContext) -> InterpreterResult<StackObject> {
+        value(Literal::Boolean(self))
+    }
+}
+
+impl FromScheme for char {
+    const EXPECTED: &'static str = "a character";
+
+    fn from_scheme(interpreter: &InterpreterContext, obj: &ObjectPointer) -> Option<Self> {
+        match literal(interpreter, obj)? {
+            Literal::Character(c) => Some(c),
+            _ => None,
+        }
+    }
+}
+
+impl IntoScheme for char {
+    fn into_scheme(self, _interpreter: &InterpreterContext) -> InterpreterResult<StackObject> {
+        value(Literal::Character(self))
+    }
+}
+
+impl FromScheme for String {
+    const EXPECTED: &'static str = "a string";
+
+    fn from_scheme(interpreter: &InterpreterContext, obj: &ObjectPointer) -> Option<Self> {
+        match obj.deref(interpreter).ok()? {
+            ObjectRef::Object(o) => match o.deref() {
+                HeapObject::String(s) => Some(s.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl IntoScheme for String {
+    fn into_scheme(self, interpreter: &InterpreterContext) -> InterpreterResult<StackObject> {
+        Ok(StackObject::Ref(HeapObject::String(self).heap_alloc(interpreter)?))
+    }
+}
+
+impl IntoScheme for &str {
+    fn into_scheme(self, interpreter: &InterpreterContext) -> InterpreterResult<StackObject> {
+        self.to_string().into_scheme(interpreter)
+    }
+}
+
+/// A proper list whose every element converts
+impl<T: FromScheme> FromScheme for Vec<T> {
+    const EXPECTED: &'static str = "a list";
+
+    fn from_scheme(interpreter: &InterpreterContext, obj: &ObjectPointer) -> Option<Self> {
+        obj.list_to_vec(interpreter)
+            .ok()?
+            .iter()
+            .map(|element| T::from_scheme(interpreter, element))
+            .collect()
+    }
+}
+
+impl<T: IntoScheme> IntoScheme for Vec<T> {
+    fn into_scheme(self, interpreter: &InterpreterContext) -> InterpreterResult<StackObject> {
+        let list = self
+            .into_iter()
+            .map(|element| element.into_scheme_pointer(interpreter))
+            .collect::<InterpreterResult<Vec<_>>>()?
+            .to_list(interpreter)?;
+        Ok(StackObject::Ref(list))
+    }
+}
+
+/// `#f` for `None`, anything else converting to `Some`
+impl<T: FromScheme> FromScheme for Option<T> {
+    const EXPECTED: &'static str = T::EXPECTED;
+
+    fn from_scheme(interpreter: &InterpreterContext, obj: &ObjectPointer) -> Option<Self> {
+        match literal(interpreter, obj) {
+            Some(Literal::Boolean(false)) => Some(None),
+            _ => T::from_scheme(interpreter, obj).map(Some),
+        }
+    }
+}
+
+impl<T: IntoScheme> IntoScheme for Option<T> {
+    fn into_scheme(self, interpreter: &InterpreterContext) -> InterpreterResult<StackObject> {
+        match self {
+            Some(v) => v.into_scheme(interpreter),
+            None => value(Literal::Boolean(false)),
+        }
+    }
+}
+
+/// An association list of `(key . value)` pairs, later pairs replacing earlier ones
+impl<K: FromScheme + Eq + Hash, V: FromScheme> FromScheme for HashMap<K, V> {
+    const EXPECTED: &'static str = "an association list";
+
+    fn from_scheme(interpreter: &InterpreterContext, obj: &ObjectPointer) -> Option<Self> {
+        obj.list_to_vec(interpreter)
+            .ok()?
+            .iter()
+            .map(|pair| {
+                let (key, value) = match pair.deref(interpreter).ok()? {
+                    ObjectRef::Object(o) => match o.deref() {
+                        HeapObject::List(key, value) => (key.clone(), value.clone()),
+                        _ => return None,
+                    },
+                    _ => return None,
+                };
+                Some((K::from_scheme(interpreter, &key)?, V::from_scheme(interpreter, &value)?))
+            })
+            .collect()
+    }
+}
+
+impl<K: IntoScheme, V: IntoScheme> IntoScheme for HashMap<K, V> {
+    fn into_scheme(self, interpreter: &InterpreterContext) -> InterpreterResult<StackObject> {
+        let list = self
+            .into_iter()
+            .map(|(key, value)| {
+                HeapObject::List(
+                    key.into_scheme_pointer(interpreter)?,
+                    value.into_scheme_pointer(interpreter)?,
+                )
+                .heap_alloc(interpreter)
+            })
+            .collect::<InterpreterResult<Vec<_>>>()?
+            .to_list(interpreter)?;
+        Ok(StackObject::Ref(list))
+    }
+}
+
+/// Tuples convert to and from lists of the same length
+macro_rules! tuple_conversions {
+    ($len:literal: $($t:ident $i:tt),+) => {
+        impl<$($t: FromScheme),+> FromScheme for ($($t,)+) {
+            const EXPECTED: &'static str = concat!("a list of ", $len, " elements");
+
+            fn from_scheme(interpreter: &InterpreterContext, obj: &ObjectPointer) -> Option<Self> {
+                let elements = obj.list_to_vec(interpreter).ok()?;
+                if elements.len() != $len {
+                    return None;
+                }
+                Some(($($t::from_scheme(interpreter, &elements[$i])?,)+))
+            }
+        }
+
+        impl<$($t: IntoScheme),+> IntoScheme for ($($t,)+) {
+            fn into_scheme(self, interpreter: &InterpreterContext) -> InterpreterResult<StackObject> {
+                let list = vec![$(self.$i.into_scheme_pointer(interpreter)?),+].to_list(interpreter)?;
+                Ok(StackObject::Ref(list))
+            }
+        }
+    };
+}
+
+tuple_conversions!(1: A 0);
+tuple_conversions!(2: A 0, B 1);
+tuple_conversions!(3: A 0, B 1, C 2);
+tuple_conversions!(4: A 0, B 1, C 2, D 3);
+
+/// What a typed native may return, either a value or a `Result` whose error is raised
+/// as a Scheme error
+pub trait NativeReturn {
+    fn into_result(self, interpreter: &InterpreterContext) -> InterpreterResult<StackObject>;
+}
+
+impl<T: IntoScheme> NativeReturn for T {
+    fn into_result(self, interpreter: &InterpreterContext) -> InterpreterResult<StackObject> {
+        self.into_scheme(interpreter)
+    }
+}
+
+impl<T: IntoScheme, E: Display> NativeReturn for Result<T, E> {
+    fn into_result(self, interpreter: &InterpreterContext) -> InterpreterResult<StackObject> {
+        match self {
+            Ok(v) => v.into_scheme(interpreter),
+            Err(e) => Err(InterpreterError::new(InterpreterErrorKind::Host(e.to_string()))),
+        }
+    }
+}
+
+/// Rust functions which can become natives, checking the number and types of their
+/// arguments before each call
+pub trait IntoNative<Args> {
+    fn into_native(self, name: &str) -> NativeFunc;
+}
+
+macro_rules! typed_natives {
+    ($len:literal: $($t:ident $i:tt),*) => {
+        impl<F, R, $($t),*> IntoNative<($($t,)*)> for F
+        where
+            F: Fn($($t),*) -> R + Send + Sync + 'static,
+            R: NativeReturn,
+            $($t: FromScheme,)*
+        {
+            #[allow(unused_variables)]
+            fn into_native(self, name: &str) -> NativeFunc {
+                let name = name.to_string();
+                NativeFunc::new(move |interpreter, n| {
+                    expect_params(n, $len)?;
+                    let params = pop_params(interpreter, n)?;
+                    let out = (self)($($t::from_arg(interpreter, &name, $i + 1, &params[$i])?),*)
+                        .into_result(interpreter)?;
+                    interpreter.stack.push_data(out);
+                    Ok(())
+                })
+            }
+        }
+    };
+}
+
+typed_natives!(0:);
+typed_natives!(1: A 0);
+typed_natives!(2: A 0, B 1);
+typed_natives!(3: A 0, B 1, C 2);
+typed_natives!(4: A 0, B 1, C 2, D 3);
+typed_natives!(5: A 0, B 1, C 2, D 3, E 4);
+typed_natives!(6: A 0, B 1, C 2, D 3, E 4, G 5);
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::IntoScheme;
+    use crate::{embed::Error, testing::interpreter, InterpreterErrorKind};
+
+    #[test]
+    fn numbers_out_of_range_are_rejected() {
+        let interpreter = interpreter();
+        let context = interpreter.context();
+        assert!(i64::from(i32::MAX).into_scheme(context).is_ok());
+        let err = (i64::from(i32::MAX) + 1).into_scheme(context).unwrap_err();
+        assert_eq!(
+            err.kind,
+            InterpreterErrorKind::CannotConvertType("2147483648".to_string(), "integer".to_string())
+        );
+        let err = 1e39.into_scheme(context).unwrap_err();
+        assert_eq!(
+            err.kind,
+            InterpreterErrorKind::CannotConvertType(1e39.to_string(), "float".to_string())
+        );
+        assert!(f64::INFINITY.into_scheme(context).is_ok());
+
+        let rounded = interpreter.to_value(16777217.0).unwrap();
+        assert_eq!(rounded.get::<f64>(), Some(16777216.0));
+        assert_eq!(interpreter.eval_str("0.1").unwrap().get::<f64>(), Some(0.1));
+        assert_eq!(interpreter.eval_str("3").unwrap().get::<f64>(), Some(3.0));
+        assert_eq!(interpreter.eval_str("3.5").unwrap().get::<i64>(), None);
+    }
+
+    #[test]
+    fn values_are_read_by_their_kind() {
+        let interpreter = interpreter();
+        let get = |source: &str| interpreter.eval_str(source).unwrap();
+        assert_eq!(get("\"a\"").get::<String>(), Some("a".to_string()));
+        assert_eq!(get("#\\a").get::<char>(), Some('a'));
+        assert_eq!(get("#t").get::<bool>(), Some(true));
+        assert_eq!(get("1").get::<bool>(), None);
+        assert_eq!(get("(list 1 2 3)").get::<Vec<i64>>(), Some(vec![1, 2, 3]));
+        assert_eq!(get("(list 1 \"2\")").get::<Vec<i64>>(), None);
+        assert_eq!(get("#f").get::<Option<i64>>(), Some(None));
+        assert_eq!(get("1").get::<Option<i64>>(), Some(Some(1)));
+        assert_eq!(
+            get("(list 1 \"a\" #t)").get::<(i64, String, bool)>(),
+            Some((1, "a".to_string(), true))
+        );
+        assert_eq!(get("(list 1 2)").get::<(i64, i64, i64)>(), None);
+        assert_eq!(
+            get("(list (cons \"a\" 1) (cons \"b\" 2) (cons \"a\" 3))")
+                .get::<HashMap<String, i64>>(),
+            Some(HashMap::from([("a".to_string(), 3), ("b".to_string(), 2)]))
+        );
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let interpreter = interpreter();
+        let value = interpreter.to_value((vec![1i64, 2], Some('x'), None::<bool>)).unwrap();
+        assert_eq!(
+            value.get::<(Vec<i64>, Option<char>, Option<bool>)>(),
+            Some((vec![1, 2], Some('x'), None))
+        );
+        let map = HashMap::from([("k".to_string(), 1i64)]);
+        assert_eq!(interpreter.to_value(map.clone()).unwrap().get(), Some(map));
+    }
+
+    #[test]
+    fn typed_natives_convert_each_argument() {
+        let interpreter = interpreter();
+        interpreter.register_typed("scale", |xs: Vec<f64>, by: Option<f64>| {
+            xs.into_iter().map(|x| x * by.unwrap_or(2.0)).collect::<Vec<_>>()
+        });
+        assert_eq!(interpreter.eval_str("(scale (list 1 2) #f)").unwrap().to_string(), "2:4:()");
+        assert_eq!(interpreter.eval_str("(scale (list 1.5) 3)").unwrap().to_string(), "4.5:()");
+
+        let Err(Error::Eval(err)) = interpreter.eval_str("(scale (list 1) \"3\")") else {
+            panic!("expected an evaluation error");
+        };
+        assert_eq!(
+            err.kind,
+            InterpreterErrorKind::WrongType {
+                procedure: "scale".to_string(),
+                arg_index: 2,
+                expected: "a number",
+                got: "\"3\"".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use crate::{exception::Guard, object::ObjectPointer, InterpreterContext, InterpreterResult};
+
+pub type Continuation = Arc<dyn Fn(&InterpreterContext) -> InterpreterResult<()> + Send + Sync>;
+
+/// Work handed back to the interpreter loop by a native function,
+/// allowing natives to call into Scheme without recursing on the Rust stack
+#[derive(Clone)]
+pub enum Deferred {
+    /// Apply a function to already evaluated arguments, pushing a single result
+    Apply(ObjectPointer, Vec<ObjectPointer>),
+    /// Run once all previously deferred work has completed
+    Then(Continuation),
+    /// Apply a thunk with an exception handler installed for its extent
+    WithHandler(ObjectPointer, ObjectPointer),
+    /// Apply a procedure to the continuation of the native function which deferred this
+    CallWithContinuation(ObjectPointer),
+    /// Apply a thunk, evaluating the clauses of a `guard` instead if it raises
+    Guard(Guard, ObjectPointer),
+}
+
+impl Deferred {
+    pub fn then(f: impl Fn(&InterpreterContext) -> InterpreterResult<()> + Send + Sync + 'static) -> Self {
+        Deferred::Then(Arc::new(f))
+    }
+
+    /// Drops the result of the previous piece of work
+    pub fn discard() -> Self {
+        Deferred::then(|interpreter| interpreter.stack.pop_data().map(|_| ()))
+    }
+}
+
+impl std::fmt::Debug for Deferred {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Deferred::Apply(func, args) => write!(f, "Apply({func}, {args:?})"),
+            Deferred::Then(_) => write!(f, "Then"),
+            Deferred::WithHandler(handler, thunk) => write!(f, "WithHandler({handler}, {thunk})"),
+            Deferred::CallWithContinuation(func) => write!(f, "CallWithContinuation({func})"),
+            Deferred::Guard(guard, thunk) => write!(f, "Guard({}, {thunk})", guard.ident),
+        }
+    }
+}
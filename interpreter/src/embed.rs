@@ -0,0 +1,353 @@
+use core::{
+    error::{ErrorWriter, SyntaxError},
+    token::span::Span,
+    LexerParser,
+};
+use std::sync::Arc;
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    convert::{FromScheme, IntoNative, IntoScheme},
+    deref::InterpreterDeref,
+    object::ObjectPointer,
+    print::InterpreterPrint,
+    InterpreterContext, InterpreterError, InterpreterErrorKind,
+};
+
+/// Why evaluating from Rust failed
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// The source did not lex or parse, with every error found
+    Syntax(Vec<SyntaxError>),
+    /// Evaluation raised an error nothing caught
+    Eval(InterpreterError),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Syntax(errors) => write!(
+                f,
+                "{}",
+                errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n")
+            ),
+            Error::Eval(err) => write!(f, "{}", err.kind),
+        }
+    }
+}
+
+impl Error {
+    /// The status the program asked to exit with, if it stopped by calling `exit`
+    pub fn exit_status(&self) -> Option<i32> {
+        match self {
+            Error::Eval(InterpreterError {
+                kind: InterpreterErrorKind::Exit(status),
+                ..
+            }) => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<InterpreterError> for Error {
+    fn from(err: InterpreterError) -> Self {
+        Error::Eval(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A Scheme object held from Rust. The object stays alive as long as the `Value` does
+#[derive(Clone)]
+pub struct Value {
+    pointer: ObjectPointer,
+    context: Arc<InterpreterContext>,
+}
+
+impl Value {
+    pub fn pointer(&self) -> &ObjectPointer {
+        &self.pointer
+    }
+
+    pub fn context(&self) -> &InterpreterContext {
+        &self.context
+    }
+
+    /// Converts the object into a Rust value, `None` if it is of the wrong kind
+    pub fn get<T: FromScheme>(&self) -> Option<T> {
+        T::from_scheme(&self.context, &self.pointer)
+    }
+
+    /// Everything but `#f` counts as true
+    pub fn is_truthy(&self) -> bool {
+        self.pointer
+            .deref(&self.context)
+            .is_ok_and(|obj| obj.is_truthy())
+    }
+}
+
+impl IntoScheme for Value {
+    fn into_scheme(
+        self,
+        interpreter: &InterpreterContext,
+    ) -> crate::InterpreterResult<crate::object::StackObject> {
+        self.pointer.into_scheme(interpreter)
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pointer.interpreter_fmt(&self.context))
+    }
+}
+
+impl std::fmt::Debug for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Value({self})")
+    }
+}
+
+/// An interpreter driven from Rust, returning results and errors rather than printing
+pub struct Interpreter {
+    context: Arc<InterpreterContext>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::from_context(InterpreterContext::new(ErrorWriter::empty()))
+    }
+
+    pub fn from_context(context: InterpreterContext) -> Self {
+        Self {
+            context: Arc::new(context),
+        }
+    }
+
+    pub fn context(&self) -> &Arc<InterpreterContext> {
+        &self.context
+    }
+
+    fn value(&self, pointer: ObjectPointer) -> Value {
+        Value {
+            pointer,
+            context: self.context.clone(),
+        }
+    }
+
+    /// Converts a Rust value into a Scheme object
+    pub fn to_value(&self, value: impl IntoScheme) -> Result<Value> {
+        Ok(self.value(value.into_scheme_pointer(&self.context)?))
+    }
+
+    /// Evaluates every expression in `source`, returning the value of the last one, or
+    /// the empty list if it produced none. An error stops evaluation, leaving the
+    /// definitions made before it in place
+    pub fn eval_str(&self, source: &str) -> Result<Value> {
+        let values = self.eval_all(source)?;
+        Ok(values
+            .into_iter()
+            .last()
+            .flatten()
+            .unwrap_or_else(|| self.value(ObjectPointer::Null)))
+    }
+
+    /// Evaluates every expression in `source`, returning the value of each, or `None`
+    /// for those which produced no value, such as definitions
+    pub fn eval_all(&self, source: &str) -> Result<Vec<Option<Value>>> {
+        let file_id = self
+            .context
+            .error_writer
+            .write()
+            .unwrap()
+            .load_string(source.to_string());
+        let ast = LexerParser::parse(file_id, source).map_err(Error::Syntax)?;
+
+        let mut values = Vec::new();
+        for node in &ast {
+            let data = self.context.stack.data.read().unwrap().len();
+            let evaluated = self.restoring_stack(|| {
+                self.context.interpret(node)?;
+                if self.context.stack.data.read().unwrap().len() > data {
+                    Ok(Some(self.context.stack.pop_data()?.heap_alloc(&self.context)?))
+                } else {
+                    Ok(None)
+                }
+            });
+            values.push(evaluated.map_err(Error::Eval)?.map(|pointer| self.value(pointer)));
+        }
+        Ok(values)
+    }
+
+    /// Applies the procedure bound globally to `name`
+    pub fn call(&self, name: &str, args: impl IntoIterator<Item = Value>) -> Result<Value> {
+        let func = self.global(name)?;
+        self.apply(&self.value(func), args)
+    }
+
+    /// Applies a procedure value, such as one returned by `eval_str`
+    pub fn apply(&self, func: &Value, args: impl IntoIterator<Item = Value>) -> Result<Value> {
+        // Calls from Rust have no source, so their errors are left unspanned
+        let span = Span::zero(usize::MAX);
+        let args = args.into_iter().map(|arg| arg.pointer).collect();
+        let result = self.restoring_stack(|| self.context.apply(func.pointer.clone(), args, span));
+        match result {
+            Ok(result) => Ok(self.value(result)),
+            Err(mut err) => {
+                if err.span == Some(span) {
+                    err.span = None;
+                }
+                Err(Error::Eval(err))
+            }
+        }
+    }
+
+    /// Runs `f`, then drops whatever it left on the stacks, as a failed evaluation may
+    fn restoring_stack<T>(&self, f: impl FnOnce() -> T) -> T {
+        let data = self.context.stack.data.read().unwrap().len();
+        let frames = self.context.stack.frame.read().unwrap().len();
+        let result = f();
+        self.context.stack.data.write().unwrap().truncate(data);
+        self.context.stack.frame.write().unwrap().truncate(frames);
+        result
+    }
+
+    fn global(&self, name: &str) -> std::result::Result<ObjectPointer, InterpreterError> {
+        self.context
+            .ident_mapping
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| {
+                InterpreterError::new(InterpreterErrorKind::CantResolveIdentifier(
+                    name.to_string(),
+                ))
+            })
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.global(name).ok().map(|pointer| self.value(pointer))
+    }
+
+    /// Binds `name` globally, replacing any previous binding
+    pub fn set_global(&self, name: &str, value: impl IntoScheme) -> Result<()> {
+        value
+            .into_scheme(&self.context)?
+            .heap_alloc_named(name, &self.context)?;
+        Ok(())
+    }
+
+    /// Binds `name` globally to a Rust function, see [`InterpreterContext::register_typed`]
+    pub fn register_typed<Args>(&self, name: &str, f: impl IntoNative<Args>) {
+        self.context.register_typed(name, f);
+    }
+
+    /// Prints an error as the command line interpreter would, with its source lines
+    pub fn report(&self, err: &Error) {
+        let error_writer = self.context.error_writer.read().unwrap();
+        let _ = match err {
+            Error::Syntax(errors) => error_writer.report_errors(errors.clone()),
+            Error::Eval(err) => error_writer.report_errors(vec![err.clone()]),
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Error;
+    use crate::{error::InterpreterErrorKind, testing::interpreter};
+
+    #[test]
+    fn eval_str_returns_the_last_value() {
+        let interpreter = interpreter();
+        assert_eq!(interpreter.eval_str("(+ 1 2) (* 2 3)").unwrap().get::<i64>(), Some(6));
+        assert_eq!(interpreter.eval_str("(define x 1)").unwrap().to_string(), "()");
+        assert!(!interpreter.eval_str("#f").unwrap().is_truthy());
+
+        let values = interpreter.eval_all("(define y 2) y").unwrap();
+        assert!(values[0].is_none());
+        assert_eq!(values[1].as_ref().and_then(|v| v.get::<i64>()), Some(2));
+    }
+
+    #[test]
+    fn errors_keep_earlier_definitions() {
+        let interpreter = interpreter();
+        let Err(Error::Eval(err)) = interpreter.eval_str("(define x 1) (car 5) (define x 2)") else {
+            panic!("expected an evaluation error");
+        };
+        assert!(err.kind.is_catchable());
+        assert_eq!(interpreter.eval_str("x").unwrap().get::<i64>(), Some(1));
+        assert!(matches!(interpreter.eval_str("(+ 1"), Err(Error::Syntax(_))));
+        assert_eq!(interpreter.eval_str("(+ x 1)").unwrap().get::<i64>(), Some(2));
+    }
+
+    #[test]
+    fn call_applies_globals() {
+        let interpreter = interpreter();
+        interpreter.eval_str("(define (add a b) (+ a b))").unwrap();
+        let args = [interpreter.to_value(2i64).unwrap(), interpreter.to_value(3i64).unwrap()];
+        assert_eq!(interpreter.call("add", args).unwrap().get::<i64>(), Some(5));
+
+        let Err(Error::Eval(err)) = interpreter.call("missing", []) else {
+            panic!("expected an evaluation error");
+        };
+        assert_eq!(err.kind, InterpreterErrorKind::CantResolveIdentifier("missing".to_string()));
+        assert_eq!(err.span, None);
+
+        let double = interpreter.eval_str("(lambda (x) (* x 2))").unwrap();
+        let arg = interpreter.to_value(4i64).unwrap();
+        assert_eq!(interpreter.apply(&double, [arg]).unwrap().get::<i64>(), Some(8));
+    }
+
+    #[test]
+    fn globals_are_shared_with_scheme() {
+        let interpreter = interpreter();
+        interpreter.set_global("greeting", "hello").unwrap();
+        assert_eq!(
+            interpreter.eval_str("greeting").unwrap().get::<String>(),
+            Some("hello".to_string())
+        );
+        interpreter.eval_str("(define answer 42)").unwrap();
+        assert_eq!(interpreter.get_global("answer").and_then(|v| v.get::<i64>()), Some(42));
+        assert!(interpreter.get_global("unbound").is_none());
+    }
+
+    #[test]
+    fn typed_natives_check_their_arguments() {
+        let interpreter = interpreter();
+        interpreter.register_typed("halve", |n: i64| {
+            if n % 2 == 0 {
+                Ok(n / 2)
+            } else {
+                Err(format!("{n} is odd"))
+            }
+        });
+        assert_eq!(interpreter.eval_str("(halve 10)").unwrap().get::<i64>(), Some(5));
+        assert!(interpreter.eval_str("(halve 3)").is_err());
+        assert!(interpreter.eval_str("(halve \"ten\")").is_err());
+        assert!(interpreter.eval_str("(halve 1 2)").is_err());
+        assert_eq!(
+            interpreter.eval_str("(guard (e (#t 0)) (halve 3))").unwrap().get::<i64>(),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn exit_gives_its_status() {
+        let interpreter = interpreter();
+        assert_eq!(interpreter.eval_str("(exit 3)").unwrap_err().exit_status(), Some(3));
+        assert_eq!(interpreter.eval_str("(car 5)").unwrap_err().exit_status(), None);
+        assert_eq!(
+            interpreter.eval_str("(guard (e (#t 0)) (exit 4))").unwrap_err().exit_status(),
+            Some(4)
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use core::error::LispError;
 use std::ops::RangeFrom;
 
+use crate::{continuation::Continuation, limits::Limit, object::ObjectPointer};
+
 pub type InterpreterError = LispError<InterpreterErrorKind>;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -9,18 +11,55 @@ pub enum InterpreterErrorKind {
     CantResolveIdentifier(String),
     IsNotParamName(String),
     CannotCall(String),
+    CannotApplySyntax(String),
     ExpectedNOrMoreParams(RangeFrom<usize>, usize),
     ExpectedNParams(usize, usize),
+    ArityMismatch(String, usize),
 
     // Failed Operation
     ExpectedList,
-    ExpectedString,
     NullDeref,
     CannotAllocateNull, // TODO:
     PointerDoesNotExist, // TODO:
     CannotCompare(String, String),
-    CannotPerformOperation(String, String, String),
     CannotConvertType(String, String),
+    WrongType {
+        procedure: String,
+        arg_index: usize,
+        expected: &'static str,
+        got: String,
+    },
+    IndexOutOfRange(String, usize),
+    DivisionByZero(&'static str),
+
+    WrongValueCount(&'static str, String, usize),
+    InvalidFormals(&'static str),
+
+    InvalidParameterizeForm,
+    InvalidDoForm,
+    InvalidCaseLambdaClause,
+    InvalidMatchClause,
+    InvalidMatchPattern(String),
+    NoMatchingClause(String),
+
+    // Exceptions
+    Raised(ObjectPointer, String),
+    HandlerReturned(String),
+    InvalidGuardForm,
+    InvalidGuardClause,
+    Resume {
+        evaluation: usize,
+        continuation: Continuation,
+        values: Vec<ObjectPointer>,
+    },
+    ContinuationExpired,
+
+    // Limits
+    LimitExceeded(Limit),
+    Cancelled,
+    /// `exit` or `emergency-exit` was called with this status
+    Exit(i32),
+    CannotRunProcess(String, String),
 
     // Stack Related
     EmptyStack,
@@ -30,19 +69,52 @@ pub enum InterpreterErrorKind {
     // Import Errors
     EmptyImport,
     InvalidInImport,
-    ImportNotFound(String),
+    ImportNotFound(String, Vec<String>),
     ErrorInParsingImport,
+    InvalidImportSet,
+    InvalidLibraryForm,
+    ImportCycle(String),
+    LibraryNotDefined(String, String),
+    NotExported(String),
+    UndefinedExport(String, String),
+    ImportDisabled(String),
 
     // Definition Syntax
     InvalidFuncParamNames,
+    /// A dotted tail outside formals, patterns and quoted lists
+    MisplacedRest(String),
 
     // Let Errors
     InvalidLetStatement,
     InvalidLetBindingForm,
     InvalidLetBindingName,
 
+    InvalidJson(String),
+
+    /// An error returned by a function the host registered
+    Host(String),
+
     // File IO Errors
     CannotOpenFile(String),
+    CannotWriteOutput(String),
+    /// Imports outside any file are relative to the current directory, which could not
+    /// be found
+    NoCurrentDirectory(String),
+    AccessDenied(&'static str, String),
+}
+
+impl InterpreterErrorKind {
+    /// Whether a handler or `guard` may catch the error. Resuming a continuation,
+    /// running out of a limit and exiting all unwind the whole evaluation
+    pub fn is_catchable(&self) -> bool {
+        !matches!(
+            self,
+            InterpreterErrorKind::Resume { .. }
+                | InterpreterErrorKind::LimitExceeded(_)
+                | InterpreterErrorKind::Cancelled
+                | InterpreterErrorKind::Exit(_)
+        )
+    }
 }
 
 impl std::fmt::Display for InterpreterErrorKind {
@@ -54,6 +126,10 @@ impl std::fmt::Display for InterpreterErrorKind {
                 temp = format!("Cannot call '{s}', it is not a function");
                 &temp
             }
+            InterpreterErrorKind::CannotApplySyntax(s) => {
+                temp = format!("Cannot apply '{s}' to evaluated arguments, it is syntax");
+                &temp
+            }
             InterpreterErrorKind::IsNotParamName(s) => {
                 temp = format!("'{s}' is not an ident");
                 &temp
@@ -71,6 +147,10 @@ impl std::fmt::Display for InterpreterErrorKind {
             }
             InterpreterErrorKind::ExpectedList => "Operation expected a List",
             InterpreterErrorKind::InvalidFuncParamNames => "Invalid Param names",
+            InterpreterErrorKind::MisplacedRest(form) => {
+                temp = format!("'{form}' cannot have a dotted tail");
+                &temp
+            }
             InterpreterErrorKind::ExpectedNOrMoreParams(expected, received) => {
                 temp = format!("Operation expected {expected:?} parameters received {received}");
                 &temp
@@ -79,13 +159,39 @@ impl std::fmt::Display for InterpreterErrorKind {
                 temp = format!("Operation expected {expected:?} parameters received {received}");
                 &temp
             }
+            InterpreterErrorKind::ArityMismatch(arities, received) => {
+                temp = format!("Procedure accepts {arities} parameters, received {received}");
+                &temp
+            }
             InterpreterErrorKind::EmptyImport => "Import is empty",
             InterpreterErrorKind::InvalidInImport => "Invalid in import",
-            InterpreterErrorKind::ImportNotFound(s) => {
-                temp = format!("Import '{s}' cannot be found");
+            InterpreterErrorKind::ImportNotFound(s, tried) => {
+                temp = format!("Import '{s}' cannot be found, tried:\n    {}", tried.join("\n    "));
                 &temp
             }
             InterpreterErrorKind::ErrorInParsingImport => "Parse error in import",
+            InterpreterErrorKind::InvalidImportSet => "import set must be a library name or `(only|except|prefix|rename set ..)`",
+            InterpreterErrorKind::InvalidLibraryForm => "define-library must be in the form `define-library (name ..) (export ..) (import ..) (begin ..) ..`",
+            InterpreterErrorKind::ImportCycle(cycle) => {
+                temp = format!("Import cycle between libraries {cycle}");
+                &temp
+            }
+            InterpreterErrorKind::LibraryNotDefined(path, name) => {
+                temp = format!("'{path}' does not define library '{name}'");
+                &temp
+            }
+            InterpreterErrorKind::NotExported(name) => {
+                temp = format!("'{name}' is not in the import set");
+                &temp
+            }
+            InterpreterErrorKind::UndefinedExport(library, name) => {
+                temp = format!("Library '{library}' exports '{name}' but does not define it");
+                &temp
+            }
+            InterpreterErrorKind::ImportDisabled(name) => {
+                temp = format!("Cannot import '{name}', importing files is disabled");
+                &temp
+            }
             InterpreterErrorKind::CannotCompare(l, r) => {
                 temp = format!("Cannot compare '{}' and '{}'", l, r);
                 &temp
@@ -93,19 +199,90 @@ impl std::fmt::Display for InterpreterErrorKind {
             InterpreterErrorKind::InvalidLetStatement => "let statement must be in the form `let ((ident value) ..) (block)`",
             InterpreterErrorKind::InvalidLetBindingForm => "let binding must be in the form `(ident value)`",
             InterpreterErrorKind::InvalidLetBindingName => "Invalid identifier name in let binding",
-            InterpreterErrorKind::CannotPerformOperation(op, l, r) => {
-                temp = format!("Cannot perform '{op}' between '{l}' and '{r}'");
+            InterpreterErrorKind::InvalidJson(message) => {
+                temp = format!("Invalid JSON, {message}");
                 &temp
             },
-            InterpreterErrorKind::ExpectedString => "Operations expected a String",
+            InterpreterErrorKind::Host(message) => message,
             InterpreterErrorKind::CannotOpenFile(file_name) => {
                 temp = format!("Cannot open file '{file_name}'");
                 &temp
             },
+            InterpreterErrorKind::AccessDenied(action, path) => {
+                temp = format!("Access denied {action} '{path}'");
+                &temp
+            },
+            InterpreterErrorKind::CannotWriteOutput(err) => {
+                temp = format!("Cannot write output, {err}");
+                &temp
+            },
+            InterpreterErrorKind::NoCurrentDirectory(err) => {
+                temp = format!("Cannot find the current directory, {err}");
+                &temp
+            },
             InterpreterErrorKind::CannotConvertType(from, to) => {
                 temp = format!("Cannot convert from '{from}' to '{to}'");
                 &temp
             },
+            InterpreterErrorKind::WrongType { procedure, arg_index, expected, got } => {
+                temp = format!("'{procedure}' expected {expected} as argument {arg_index}, received '{got}'");
+                &temp
+            },
+            InterpreterErrorKind::WrongValueCount(form, expected, received) => {
+                temp = format!("'{form}' expected {expected} values, received {received}");
+                &temp
+            },
+            InterpreterErrorKind::InvalidFormals(form) => {
+                temp = format!("'{form}' formals must be an identifier or a list of identifiers");
+                &temp
+            },
+            InterpreterErrorKind::InvalidParameterizeForm => "parameterize must be in the form `parameterize ((param value) ..) body ..`",
+            InterpreterErrorKind::InvalidDoForm => "do must be in the form `do ((var init step) ..) (test expr ..) command ..`",
+            InterpreterErrorKind::InvalidCaseLambdaClause => "case-lambda clause must be in the form `(formals body)`",
+            InterpreterErrorKind::InvalidMatchClause => "match clause must be in the form `(pattern body ..)`",
+            InterpreterErrorKind::InvalidMatchPattern(pattern) => {
+                temp = format!("Invalid pattern '{pattern}' in match");
+                &temp
+            },
+            InterpreterErrorKind::NoMatchingClause(value) => {
+                temp = format!("No match clause matches '{value}'");
+                &temp
+            },
+            InterpreterErrorKind::Raised(_, s) => {
+                temp = format!("Uncaught exception: {s}");
+                &temp
+            },
+            InterpreterErrorKind::HandlerReturned(s) => {
+                temp = format!("Exception handler returned from non-continuable exception: {s}");
+                &temp
+            },
+            InterpreterErrorKind::InvalidGuardForm => "guard must be in the form `guard (ident clause ..) body ..`",
+            InterpreterErrorKind::InvalidGuardClause => "guard clause must be in the form `(test expr ..)`, `(test => receiver)` or `(else expr ..)`",
+            InterpreterErrorKind::LimitExceeded(limit) => {
+                temp = format!("Evaluation exceeded its limit of {limit}");
+                &temp
+            },
+            InterpreterErrorKind::Cancelled => "Evaluation was cancelled",
+            InterpreterErrorKind::Exit(status) => {
+                temp = format!("Exited with status {status}");
+                &temp
+            }
+            InterpreterErrorKind::CannotRunProcess(program, err) => {
+                temp = format!("Cannot run '{program}': {err}");
+                &temp
+            }
+            InterpreterErrorKind::Resume { .. } => "Continuation invoked outside of any evaluation",
+            InterpreterErrorKind::ContinuationExpired => {
+                "Continuation cannot be resumed once the expression it was captured in has returned"
+            }
+            InterpreterErrorKind::DivisionByZero(procedure) => {
+                temp = format!("'{procedure}' cannot divide by zero");
+                &temp
+            },
+            InterpreterErrorKind::IndexOutOfRange(procedure, index) => {
+                temp = format!("'{procedure}' index {index} is out of range");
+                &temp
+            },
         };
         write!(f, "{s}")
     }
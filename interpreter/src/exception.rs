@@ -0,0 +1,106 @@
+use core::parser::ast::AST;
+use std::sync::Arc;
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    error::{InterpreterError, InterpreterErrorKind},
+    object::{ErrorObject, HeapObject, ObjectPointer},
+    print::InterpreterPrint,
+    InterpreterContext, InterpreterResult,
+};
+
+/// An entry on the exception handler stack
+#[derive(Debug, Clone, PartialEq)]
+pub enum Handler {
+    /// Installed by `with-exception-handler`, called with the condition
+    Procedure(ObjectPointer),
+    /// Installed by `guard`, which only catches by unwinding
+    Guard,
+}
+
+/// The clauses of a `guard`, evaluated with the condition bound to `ident` when its
+/// body raises
+#[derive(Debug, Clone)]
+pub struct Guard {
+    pub ident: String,
+    pub clauses: Arc<Vec<AST>>,
+}
+
+/// The depths of the interpreter stacks, restored when an exception is caught
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DynamicState {
+    pub frames: usize,
+    pub data: usize,
+    pub handlers: usize,
+    pub winders: usize,
+    pub parameters: usize,
+}
+
+impl DynamicState {
+    pub fn capture(interpreter: &InterpreterContext) -> Self {
+        Self {
+            frames: interpreter.stack.frame.read().unwrap().len(),
+            data: interpreter.stack.data.read().unwrap().len(),
+            handlers: interpreter.handlers.read().unwrap().len(),
+            winders: interpreter.winders.read().unwrap().len(),
+            parameters: interpreter.parameters.read().unwrap().len(),
+        }
+    }
+
+    /// Truncates the stacks back to this state, returning the after thunks of the
+    /// `dynamic-wind`s left, innermost first, for the caller to run
+    pub fn restore(&self, interpreter: &InterpreterContext) -> Vec<ObjectPointer> {
+        interpreter.stack.frame.write().unwrap().truncate(self.frames);
+        interpreter.stack.data.write().unwrap().truncate(self.data);
+        interpreter.handlers.write().unwrap().truncate(self.handlers);
+        interpreter.parameters.write().unwrap().truncate(self.parameters);
+
+        let mut winders = interpreter.winders.write().unwrap();
+        let depth = self.winders.min(winders.len());
+        let left = winders.split_off(depth);
+        left.iter().rev().map(|w| w.after.clone()).collect()
+    }
+
+    /// Whether `handler`, installed on top of this state, is still installed and not running
+    pub fn is_handling(&self, interpreter: &InterpreterContext, handler: &ObjectPointer) -> bool {
+        matches!(
+            interpreter.handlers.read().unwrap().get(self.handlers),
+            Some(Handler::Procedure(p)) if p == handler
+        )
+    }
+
+    /// Whether the `guard` installed on top of this state is still installed
+    pub fn is_guarding(&self, interpreter: &InterpreterContext) -> bool {
+        matches!(
+            interpreter.handlers.read().unwrap().get(self.handlers),
+            Some(Handler::Guard)
+        )
+    }
+}
+
+/// Builds the error carrying a raised object up to the nearest handler
+pub fn raised(interpreter: &InterpreterContext, obj: ObjectPointer) -> InterpreterError {
+    let printed = obj.interpreter_fmt(interpreter);
+    InterpreterError::new(InterpreterErrorKind::Raised(obj, printed))
+}
+
+pub fn handler_returned(interpreter: &InterpreterContext, condition: &ObjectPointer) -> InterpreterError {
+    InterpreterError::new(InterpreterErrorKind::HandlerReturned(
+        condition.interpreter_fmt(interpreter),
+    ))
+}
+
+/// The object an error is caught as, native errors becoming error objects without irritants
+pub fn condition_of(
+    interpreter: &InterpreterContext,
+    err: &InterpreterError,
+) -> InterpreterResult<ObjectPointer> {
+    match &err.kind {
+        InterpreterErrorKind::Raised(obj, _) => Ok(obj.clone()),
+        kind => HeapObject::Error(ErrorObject {
+            message: kind.to_string(),
+            irritants: ObjectPointer::Null,
+        })
+        .heap_alloc(interpreter),
+    }
+}
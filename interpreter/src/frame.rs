@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use crate::{func::Func, ObjectPointer};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Frame {
     pub name: String,
     pub func_hash: u64,
@@ -22,6 +22,17 @@ impl Frame {
         }
     }
 
+    /// A frame for bindings made by syntax rather than a function call
+    pub fn named(stack_index: usize, name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            func_hash: 0,
+            stack_index,
+            ident_mapping: HashMap::new(),
+            locals: Vec::new(),
+        }
+    }
+
     pub fn get_local_by_index(&self, index: usize) -> Option<ObjectPointer> {
         self.locals.get(index).and_then(|p| p.clone())
     }
@@ -33,6 +44,12 @@ impl Frame {
     }
 
     pub fn insert_local(&mut self, ident: &str, pointer: ObjectPointer) -> ObjectPointer{
+        // Rebinding a name, as loops and self recursive calls do, reuses its slot
+        if let Some(&id) = self.ident_mapping.get(ident) {
+            self.locals[id] = Some(pointer);
+            return ObjectPointer::Stack(self.stack_index, id);
+        }
+
         let id = self
             .locals
             .iter()
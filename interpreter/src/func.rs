@@ -1,22 +1,169 @@
 use core::parser::ast::AST;
-use std::hash::{DefaultHasher, Hash, Hasher};
+use std::{
+    any::Any,
+    cmp::Ordering,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
 
-use crate::{InterpreterContext, InterpreterResult};
+use crate::{
+    continuation::Continuation, object::StackObject, parameter::Parameter, InterpreterContext,
+    InterpreterResult,
+};
 
-pub type NativeFunc = fn(&InterpreterContext, usize) -> InterpreterResult<()>;
+pub type NativeFn = dyn Fn(&InterpreterContext, usize) -> InterpreterResult<()> + Send + Sync;
 pub type TokenNativeFunc = fn(&InterpreterContext, Vec<&AST>) -> InterpreterResult<()>;
-pub type MacroFunc = fn(&InterpreterContext, Vec<&AST>) -> InterpreterResult<usize>;
+pub type MacroFunc =
+    for<'a> fn(&InterpreterContext, Vec<&'a AST>, Vec<StackObject>) -> InterpreterResult<MacroStep<'a>>;
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+/// What a macro does with its form, given the values of the expressions it has asked for
+#[derive(Debug)]
+pub enum MacroStep<'a> {
+    /// Evaluates these expressions, then calls the macro again with their values added
+    Evaluate(Vec<&'a AST>),
+    /// Evaluates this expression in place of the form
+    Expand(&'a AST),
+}
+
+#[derive(Debug, Clone)]
 pub enum Func {
     Native(String, NativeFunc),
     TokenNative(String, TokenNativeFunc),
     Macro(String, MacroFunc),
     Defined(Option<String>, Vec<String>, AST),
+    Continuation(Continuation),
+    Parameter(Parameter),
+    CaseLambda(Vec<Case>),
+}
+
+/// The body of a native procedure, which may capture Rust state. Two natives are only
+/// equal when they share the same body
+#[derive(Clone)]
+pub struct NativeFunc {
+    call: Arc<NativeFn>,
+    data: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl NativeFunc {
+    pub fn new(
+        f: impl Fn(&InterpreterContext, usize) -> InterpreterResult<()> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            call: Arc::new(f),
+            data: None,
+        }
+    }
+
+    /// A native holding `data`, which it is passed on each call and which the host
+    /// can get back from the procedure with [`NativeFunc::data`]
+    pub fn with_data<T: Any + Send + Sync>(
+        data: T,
+        f: impl Fn(&InterpreterContext, &T, usize) -> InterpreterResult<()> + Send + Sync + 'static,
+    ) -> Self {
+        let data = Arc::new(data);
+        let held = data.clone();
+        Self {
+            call: Arc::new(move |interpreter, n| f(interpreter, &held, n)),
+            data: Some(data),
+        }
+    }
+
+    pub fn call(&self, interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+        (self.call)(interpreter, n)
+    }
+
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.as_ref()?.downcast_ref()
+    }
 }
 
+impl std::fmt::Debug for NativeFunc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NativeFunc")
+    }
+}
+
+impl NativeFunc {
+    /// The address of the body, which identifies it
+    fn address(&self) -> usize {
+        Arc::as_ptr(&self.call) as *const () as usize
+    }
+}
+
+impl PartialEq for NativeFunc {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.call, &other.call)
+    }
+}
+
+impl Eq for NativeFunc {}
+
+impl PartialOrd for NativeFunc {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NativeFunc {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.address().cmp(&other.address())
+    }
+}
+
+impl Hash for NativeFunc {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.address().hash(state);
+    }
+}
+
+/// One clause of a `case-lambda`, taking exactly `params` or, when `rest` is given,
+/// any number of arguments as a list
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct Case {
+    pub params: Vec<String>,
+    pub rest: Option<String>,
+    pub body: AST,
+}
+
+/// The accepted arities of some clauses, as in `1, 2 or 3+`
+pub fn arities(cases: &[Case]) -> String {
+    let arities = cases.iter().map(Case::arity).collect::<Vec<_>>();
+    match arities.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {last}", rest.join(", ")),
+        _ => arities.join(""),
+    }
+}
+
+impl Case {
+    pub fn accepts(&self, n: usize) -> bool {
+        match self.rest {
+            Some(_) => n >= self.params.len(),
+            None => n == self.params.len(),
+        }
+    }
+
+    pub fn arity(&self) -> String {
+        match self.rest {
+            Some(_) => format!("{}+", self.params.len()),
+            None => self.params.len().to_string(),
+        }
+    }
+}
 
 impl Func {
+    /// The position of the variant, ordering functions of different kinds
+    fn kind(&self) -> usize {
+        match self {
+            Func::Native(..) => 0,
+            Func::TokenNative(..) => 1,
+            Func::Macro(..) => 2,
+            Func::Defined(..) => 3,
+            Func::Continuation(_) => 4,
+            Func::Parameter(_) => 5,
+            Func::CaseLambda(_) => 6,
+        }
+    }
+
     pub fn calc_hash(&self) -> u64 {
         let mut s = DefaultHasher::new();
         self.hash(&mut s);
@@ -24,14 +171,126 @@ impl Func {
     }
 }
 
+// Special forms are plain function pointers, which are not guaranteed to be unique,
+// so they are told apart by name
+impl PartialEq for Func {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Func {}
+
+impl PartialOrd for Func {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Func {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Func::Native(l, lf), Func::Native(r, rf)) => l.cmp(r).then_with(|| lf.cmp(rf)),
+            (Func::TokenNative(l, _), Func::TokenNative(r, _))
+            | (Func::Macro(l, _), Func::Macro(r, _)) => l.cmp(r),
+            (Func::Defined(ln, la, lb), Func::Defined(rn, ra, rb)) => {
+                (ln, la, lb).cmp(&(rn, ra, rb))
+            }
+            (Func::Continuation(l), Func::Continuation(r)) => l.cmp(r),
+            (Func::Parameter(l), Func::Parameter(r)) => l.cmp(r),
+            (Func::CaseLambda(l), Func::CaseLambda(r)) => l.cmp(r),
+            _ => self.kind().cmp(&other.kind()),
+        }
+    }
+}
+
+impl Hash for Func {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind().hash(state);
+        match self {
+            Func::Native(name, native) => {
+                name.hash(state);
+                native.hash(state);
+            }
+            Func::TokenNative(name, _) | Func::Macro(name, _) => name.hash(state),
+            Func::Defined(name, args, body) => {
+                name.hash(state);
+                args.hash(state);
+                body.hash(state);
+            }
+            Func::Continuation(continuation) => continuation.hash(state),
+            Func::Parameter(parameter) => parameter.hash(state),
+            Func::CaseLambda(cases) => cases.hash(state),
+        }
+    }
+}
+
 impl std::fmt::Display for Func {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Func::Native(name, n) => write!(f, "{name} {n:?}"),
+            Func::Native(name, _) => write!(f, "{name}"),
             Func::TokenNative(name, n) => write!(f, "{name} {n:?}"),
             Func::Macro(name, n) => write!(f, "{name} {n:?}"),
             Func::Defined(Some(name), args, _body) => write!(f, "{name}({args:?})"),
             Func::Defined(None, args, _body) => write!(f, "Lambda({args:?})"),
+            Func::Continuation(_) => write!(f, "Continuation"),
+            Func::Parameter(_) => write!(f, "Parameter"),
+            Func::CaseLambda(cases) => write!(
+                f,
+                "CaseLambda({})",
+                arities(cases)
+            ),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use core::literal::{Literal, Numeric};
+    use std::sync::atomic::{AtomicI32, Ordering};
+
+    use super::{Func, NativeFunc};
+    use crate::{
+        alloc::InterpreterStackAlloc,
+        object::{HeapObject, StackObject},
+        testing::interpreter,
+    };
+
+    /// A native named `counter` counting its own calls
+    fn counter() -> Func {
+        let count = NativeFunc::with_data(AtomicI32::new(0), |interpreter, count, _| {
+            let n = count.fetch_add(1, Ordering::Relaxed) + 1;
+            interpreter.stack.push_data(StackObject::Value(Literal::Numeric(Numeric::Int(n))));
+            Ok(())
+        });
+        Func::Native("counter".to_string(), count)
+    }
+
+    // Natives are identified by their body as well as their name, rather than by name
+    // alone, so that natives a host makes under one name keep their own state
+    #[test]
+    fn natives_are_identified_by_their_body() {
+        let (a, b) = (counter(), counter());
+        assert_ne!(a, b);
+        assert_ne!(a.calc_hash(), b.calc_hash());
+        assert_eq!(a, a.clone());
+        assert_eq!(a.calc_hash(), a.clone().calc_hash());
+    }
+
+    #[test]
+    fn natives_with_the_same_name_keep_their_own_state() {
+        let interpreter = interpreter();
+        interpreter.context().register_fn("make-counter", |interpreter, _| {
+            let counter = HeapObject::Func(counter()).stack_alloc(interpreter)?;
+            interpreter.stack.push_data(counter);
+            Ok(())
+        });
+        let value = interpreter
+            .eval_str(
+                "(define a (make-counter)) (define b (make-counter))
+                 (a) (a) (list (a) (b) (eq? a b) (eq? a a))",
+            )
+            .unwrap();
+        assert_eq!(value.to_string(), "3:1:false:true:()");
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     ops::Deref,
-    sync::{Arc, RwLock, RwLockReadGuard},
+    sync::{Arc, Condvar, Mutex, RwLock, RwLockReadGuard},
     thread::JoinHandle,
     time::Duration,
 };
@@ -51,30 +51,106 @@ impl InterpreterHeap {
         }
     }
 
-    pub fn alloc_heap_object(&self, obj: HeapObject) -> ObjectPointer {
-        let store_len = self.store.read().unwrap().len();
-        let id = if self.free_slots.read().unwrap().len() > 0 {
-            self.free_slots.write().unwrap().pop().unwrap()
-        } else {
-            store_len
+    pub fn live_objects(&self) -> usize {
+        let free = self.free_slots.read().unwrap().len();
+        self.store.read().unwrap().len() - free
+    }
+
+    /// Frees every object nothing refers to any more
+    pub fn collect(&self) {
+        let mut store = self.store.write().unwrap();
+        let mut free_slots = self.free_slots.write().unwrap();
+
+        // An object only the store refers to is garbage. Freeing it drops its own
+        // references, which may leave what it referred to as garbage in turn. The pairs
+        // of a freed list are checked straight away, and the whole store again until a
+        // pass frees nothing
+        let unreferenced = |entry: &Option<(HeapObject, Arc<usize>)>| {
+            entry
+                .as_ref()
+                .is_some_and(|(_, arc)| Arc::strong_count(arc) <= 1)
         };
+        loop {
+            let mut queue = (0..store.len())
+                .filter(|i| unreferenced(&store[*i]))
+                .collect::<Vec<_>>();
+            if queue.is_empty() {
+                break;
+            }
+
+            while let Some(index) = queue.pop() {
+                let Some((object, _)) = store[index].take() else {
+                    continue;
+                };
+                free_slots.push(index);
+
+                let children = match &object {
+                    HeapObject::List(head, tail) => [head, tail]
+                        .into_iter()
+                        .filter_map(|child| match child {
+                            ObjectPointer::Heap(child) => Some(*child.deref()),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                drop(object);
+                queue.extend(children.into_iter().filter(|i| unreferenced(&store[*i])));
+            }
+        }
 
-        let mut store_write = self.store.write().unwrap();
-        if id >= store_len {
-            let extend = ((store_len)..=id).map(|_| None);
-            store_write.extend(extend);
+        if let Some(last) = store.iter().rposition(Option::is_some) {
+            store.truncate(last + 1);
+            free_slots.retain(|i| *i <= last);
         }
+    }
+
+    pub fn alloc_heap_object(&self, obj: HeapObject) -> ObjectPointer {
+        // The store is locked first, as the collector does, so a slot cannot be freed or
+        // truncated away while it is being taken
+        let mut store = self.store.write().unwrap();
+        let id = match self.free_slots.write().unwrap().pop() {
+            Some(id) => id,
+            None => {
+                store.push(None);
+                store.len() - 1
+            }
+        };
 
-        let mut heap_index = store_write.get_mut(id);
         let arc = Arc::new(id);
-        let temp = ObjectPointer::Heap(arc.clone());
-        let _ = heap_index.as_mut().unwrap().insert((obj, arc));
-        temp
+        let pointer = ObjectPointer::Heap(arc.clone());
+        store[id] = Some((obj, arc));
+        pointer
     }
 }
+/// Tells a collector thread to stop, waking it if it is waiting for its next run
+#[derive(Debug, Default)]
+pub struct GcStop {
+    stopped: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl GcStop {
+    pub fn stop(&self) {
+        *self.stopped.lock().unwrap() = true;
+        self.wake.notify_all();
+    }
+
+    /// Waits up to `delay`, returning whether the collector should stop
+    fn wait(&self, delay: Duration) -> bool {
+        let stopped = self.stopped.lock().unwrap();
+        *self
+            .wake
+            .wait_timeout_while(stopped, delay, |stopped| !*stopped)
+            .unwrap()
+            .0
+    }
+}
+
 pub struct GarbageCollector {
     delay: Duration,
     heap: Arc<InterpreterHeap>,
+    stop: Arc<GcStop>,
 }
 
 impl GarbageCollector {
@@ -82,79 +158,28 @@ impl GarbageCollector {
         Self {
             heap,
             delay: Duration::from_secs_f32(5.0),
+            stop: Arc::new(GcStop::default()),
         }
     }
 
+    /// Sets how long the collector waits between runs
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// The signal which stops this collector's thread
+    pub fn stop_signal(&self) -> Arc<GcStop> {
+        self.stop.clone()
+    }
+
     pub fn spawn_thread(self) -> JoinHandle<()> {
         std::thread::spawn(|| self.run())
     }
 
     pub fn run(self) {
-        loop {
-            std::thread::sleep(self.delay);
-
-            let to_free = {
-                let mut queue = {
-                    let heap_ref = self.heap.store.read().unwrap();
-                    let get_strong_count = |i: usize| -> Option<usize> {
-                        heap_ref
-                            .get(i)
-                            .and_then(|x| x.as_ref())
-                            .map(|(_, arc)| Arc::strong_count(&arc))
-                    };
-
-                    let queue = (0..heap_ref.len())
-                        .filter(|i| get_strong_count(*i).filter(|c| *c <= 1).is_some())
-                        .collect::<Vec<_>>();
-
-                    queue
-                };
-
-                let mut to_free = Vec::new();
-
-                while !queue.is_empty() {
-                    let popped = queue.pop().unwrap();
-                    match self.heap.get_heap_ref(popped).unwrap() {
-                        ObjectRef::Object(o) => match &*o {
-                            HeapObject::List(ObjectPointer::Heap(h), ObjectPointer::Heap(t)) => {
-                                queue.push(*h.deref());
-                                queue.push(*t.deref());
-                            }
-                            HeapObject::List(ObjectPointer::Heap(h), ObjectPointer::Null) => {
-                                queue.push(*h.deref());
-                            }
-                            _ => (),
-                        },
-                        _ => (),
-                    }
-
-                    to_free.push(popped)
-                }
-                to_free
-            };
-
-            if !to_free.is_empty() {
-                let mut write_lock = self.heap.store.write().unwrap();
-                let mut free_vec = self.heap.free_slots.write().unwrap();
-
-                for index in to_free.iter() {
-                    write_lock[*index] = None;
-                    free_vec.push(*index);
-                }
-
-                let last = write_lock.iter().enumerate().rev().find_map(|(i, o)| {
-                    if o.is_some() {
-                        Some(i)
-                    } else {
-                        None
-                    }
-                });
-
-                if let Some(last) = last {
-                    write_lock.truncate(last + 1);
-                    *free_vec = free_vec.drain(..).filter(|i| *i < (last + 1)).collect()
-                }
-            }
+        while !self.stop.wait(self.delay) {
+            self.heap.collect();
         }
     }
 }
@@ -0,0 +1,673 @@
+use core::literal::{Literal, Numeric};
+use std::{iter::Peekable, ops::Deref, str::CharIndices};
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    convert::IntoScheme,
+    deref::InterpreterDeref,
+    list::{InterpreterList, InterpreterListAlloc},
+    object::{HeapObject, ObjectPointer, ObjectRef},
+    print::InterpreterPrint,
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+/// A JSON document, the form Scheme values take on their way to and from JSON.
+/// Objects keep their members in order
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// How deeply arrays and objects may nest, as each level is read and written on the
+/// Rust stack
+const MAX_DEPTH: usize = 128;
+
+fn invalid_json(message: impl Into<String>) -> InterpreterError {
+    InterpreterError::new(InterpreterErrorKind::InvalidJson(message.into()))
+}
+
+fn too_deep() -> InterpreterError {
+    invalid_json(format!("nested deeper than {MAX_DEPTH} levels"))
+}
+
+struct JsonParser<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    /// Arrays and objects open around the current position
+    depth: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn position(&mut self) -> usize {
+        self.chars.peek().map_or(self.source.len(), |(i, _)| *i)
+    }
+
+    fn error(&mut self, expected: &str) -> InterpreterError {
+        let position = self.position();
+        match self.chars.peek() {
+            Some((_, c)) => invalid_json(format!("expected {expected} at {position}, found '{c}'")),
+            None => invalid_json(format!("expected {expected} at {position}, found end of input")),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| matches!(c, ' ' | '\t' | '\n' | '\r')).is_some() {}
+    }
+
+    fn expect(&mut self, c: char) -> InterpreterResult<()> {
+        self.skip_whitespace();
+        match self.chars.next_if(|(_, next)| *next == c) {
+            Some(_) => Ok(()),
+            None => Err(self.error(&format!("'{c}'"))),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> InterpreterResult<Json> {
+        for c in word.chars() {
+            if self.chars.next_if(|(_, next)| *next == c).is_none() {
+                return Err(self.error(&format!("'{word}'")));
+            }
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> InterpreterResult<Json> {
+        self.skip_whitespace();
+        match self.chars.peek().map(|(_, c)| *c) {
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(self.error("a value")),
+        }
+    }
+
+    fn digits(&mut self) -> usize {
+        let mut count = 0;
+        while self.chars.next_if(|(_, c)| c.is_ascii_digit()).is_some() {
+            count += 1;
+        }
+        count
+    }
+
+    fn number(&mut self) -> InterpreterResult<Json> {
+        let start = self.position();
+        self.chars.next_if(|(_, c)| *c == '-');
+        let leading_zero = self.chars.peek().is_some_and(|(_, c)| *c == '0');
+        match self.digits() {
+            0 => return Err(self.error("a digit")),
+            1 => (),
+            _ if leading_zero => {
+                return Err(invalid_json(format!("leading zero in number at {start}")))
+            }
+            _ => (),
+        }
+
+        let mut integer = true;
+        if self.chars.next_if(|(_, c)| *c == '.').is_some() {
+            integer = false;
+            if self.digits() == 0 {
+                return Err(self.error("a digit"));
+            }
+        }
+        if self.chars.next_if(|(_, c)| matches!(c, 'e' | 'E')).is_some() {
+            integer = false;
+            self.chars.next_if(|(_, c)| matches!(c, '+' | '-'));
+            if self.digits() == 0 {
+                return Err(self.error("a digit"));
+            }
+        }
+
+        let text = &self.source[start..self.position()];
+        match text.parse::<i64>() {
+            Ok(i) if integer => Ok(Json::Int(i)),
+            _ => match text.parse::<f64>() {
+                Ok(f) if f.is_finite() => Ok(Json::Float(f)),
+                _ => Err(invalid_json(format!("invalid number '{text}' at {start}"))),
+            },
+        }
+    }
+
+    fn hex_escape(&mut self) -> InterpreterResult<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.chars.peek().and_then(|(_, c)| c.to_digit(16)) {
+                Some(digit) => {
+                    self.chars.next();
+                    code = code * 16 + digit;
+                }
+                None => return Err(self.error("a hex digit")),
+            }
+        }
+        Ok(code)
+    }
+
+    fn string(&mut self) -> InterpreterResult<String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next().map(|(_, c)| c) {
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let escaped = match self.chars.next().map(|(_, c)| c) {
+                        Some(c @ ('"' | '\\' | '/')) => c,
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let mut code = self.hex_escape()?;
+                            // Characters outside the basic plane are written as a
+                            // surrogate pair
+                            if (0xD800..0xDC00).contains(&code) {
+                                if self.chars.next_if(|(_, c)| *c == '\\').is_none()
+                                    || self.chars.next_if(|(_, c)| *c == 'u').is_none()
+                                {
+                                    return Err(self.error("a low surrogate"));
+                                }
+                                let low = self.hex_escape()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(invalid_json(format!("invalid low surrogate '{low:x}'")));
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            char::from_u32(code).ok_or_else(|| {
+                                invalid_json(format!("invalid character escape '{code:x}'"))
+                            })?
+                        }
+                        _ => return Err(invalid_json("invalid escape in string")),
+                    };
+                    s.push(escaped);
+                }
+                Some(c) if (c as u32) < 0x20 => {
+                    return Err(invalid_json("unescaped control character in string"))
+                }
+                Some(c) => s.push(c),
+                None => return Err(invalid_json("unterminated string")),
+            }
+        }
+    }
+
+    /// Parses the elements between `open` and `close`, separated by commas
+    fn sequence<T>(
+        &mut self,
+        open: char,
+        close: char,
+        mut element: impl FnMut(&mut Self) -> InterpreterResult<T>,
+    ) -> InterpreterResult<Vec<T>> {
+        self.expect(open)?;
+        if self.depth == MAX_DEPTH {
+            return Err(too_deep());
+        }
+        self.depth += 1;
+        let elements = self.elements(close, &mut element);
+        self.depth -= 1;
+        elements
+    }
+
+    fn elements<T>(
+        &mut self,
+        close: char,
+        element: &mut impl FnMut(&mut Self) -> InterpreterResult<T>,
+    ) -> InterpreterResult<Vec<T>> {
+        self.skip_whitespace();
+        let mut elements = Vec::new();
+        if self.chars.next_if(|(_, c)| *c == close).is_some() {
+            return Ok(elements);
+        }
+        loop {
+            elements.push(element(self)?);
+            self.skip_whitespace();
+            match self.chars.next_if(|(_, c)| *c == ',' || *c == close) {
+                Some((_, ',')) => (),
+                Some(_) => return Ok(elements),
+                None => return Err(self.error(&format!("',' or '{close}'"))),
+            }
+        }
+    }
+
+    fn array(&mut self) -> InterpreterResult<Json> {
+        self.sequence('[', ']', |parser| parser.value()).map(Json::Array)
+    }
+
+    fn object(&mut self) -> InterpreterResult<Json> {
+        self.sequence('{', '}', |parser| {
+            parser.skip_whitespace();
+            let key = parser.string()?;
+            parser.expect(':')?;
+            Ok((key, parser.value()?))
+        })
+        .map(Json::Object)
+    }
+}
+
+impl Json {
+    pub fn parse(source: &str) -> InterpreterResult<Json> {
+        let mut parser = JsonParser {
+            source,
+            chars: source.char_indices().peekable(),
+            depth: 0,
+        };
+        let json = parser.value()?;
+        parser.skip_whitespace();
+        if parser.chars.peek().is_some() {
+            return Err(parser.error("end of input"));
+        }
+        Ok(json)
+    }
+
+    /// Reads a Scheme value. A list whose every element is a pair with a string head
+    /// is taken as an object, any other list as an array, and the empty list as an
+    /// empty array. An empty object therefore does not round trip, coming back as `[]`
+    pub fn from_scheme(
+        interpreter: &InterpreterContext,
+        obj: &ObjectPointer,
+    ) -> InterpreterResult<Json> {
+        Json::from_scheme_nested(interpreter, obj, 0)
+    }
+
+    fn from_scheme_nested(
+        interpreter: &InterpreterContext,
+        obj: &ObjectPointer,
+        depth: usize,
+    ) -> InterpreterResult<Json> {
+        let cannot_convert = || {
+            InterpreterError::new(InterpreterErrorKind::CannotConvertType(
+                obj.interpreter_fmt(interpreter),
+                "JSON".to_string(),
+            ))
+        };
+
+        let list = match obj.deref(interpreter)? {
+            ObjectRef::Null => return Ok(Json::Array(Vec::new())),
+            ObjectRef::Value(v) => return Json::from_literal(v).ok_or_else(cannot_convert),
+            ObjectRef::Object(o) => match o.deref() {
+                HeapObject::Value(v) => return Json::from_literal(*v).ok_or_else(cannot_convert),
+                HeapObject::String(s) => return Ok(Json::String(s.clone())),
+                HeapObject::List(..) => obj.list_to_vec(interpreter).map_err(|_| cannot_convert())?,
+                _ => return Err(cannot_convert()),
+            },
+        };
+
+        if depth == MAX_DEPTH {
+            return Err(too_deep());
+        }
+
+        let members = list
+            .iter()
+            .map(|element| match element.deref(interpreter).ok()? {
+                ObjectRef::Object(o) => match o.deref() {
+                    HeapObject::List(key, value) => match key.deref(interpreter).ok()? {
+                        ObjectRef::Object(k) => match k.deref() {
+                            HeapObject::String(k) => Some((k.clone(), value.clone())),
+                            _ => None,
+                        },
+                        _ => None,
+                    },
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>();
+
+        match members {
+            Some(members) => members
+                .into_iter()
+                .map(|(key, value)| {
+                    Ok((key, Json::from_scheme_nested(interpreter, &value, depth + 1)?))
+                })
+                .collect::<InterpreterResult<_>>()
+                .map(Json::Object),
+            None => list
+                .iter()
+                .map(|element| Json::from_scheme_nested(interpreter, element, depth + 1))
+                .collect::<InterpreterResult<_>>()
+                .map(Json::Array),
+        }
+    }
+
+    fn from_literal(lit: Literal) -> Option<Json> {
+        match lit {
+            Literal::Boolean(b) => Some(Json::Bool(b)),
+            Literal::Numeric(Numeric::Int(i)) => Some(Json::Int(i as i64)),
+            // Widened through its shortest decimal form, so 0.1 stays 0.1
+            Literal::Numeric(Numeric::Float(f)) if f.is_finite() => {
+                f.to_string().parse().ok().map(Json::Float)
+            }
+            Literal::Numeric(Numeric::Float(_)) => None,
+            Literal::Character(c) => Some(Json::String(c.to_string())),
+        }
+    }
+
+    /// Builds the Scheme value, objects becoming association lists of `(key . value)`
+    /// pairs and each JSON `null` becoming the object given as `null`. Integers must
+    /// fit in 32 bits, as a float would not hold them exactly, and numbers with a
+    /// fraction or exponent must be finite and in the range of a single precision float
+    pub fn to_scheme(
+        &self,
+        interpreter: &InterpreterContext,
+        null: &ObjectPointer,
+    ) -> InterpreterResult<ObjectPointer> {
+        let literal = |lit| HeapObject::Value(lit).heap_alloc(interpreter);
+        match self {
+            Json::Null => Ok(null.clone()),
+            Json::Bool(b) => literal(Literal::Boolean(*b)),
+            Json::Int(i) => {
+                let i = i32::try_from(*i).map_err(|_| {
+                    InterpreterError::new(InterpreterErrorKind::CannotConvertType(
+                        i.to_string(),
+                        "integer".to_string(),
+                    ))
+                })?;
+                literal(Literal::Numeric(Numeric::Int(i)))
+            }
+            Json::Float(f) if f.is_finite() => f.into_scheme(interpreter)?.heap_alloc(interpreter),
+            Json::Float(f) => Err(InterpreterError::new(InterpreterErrorKind::CannotConvertType(
+                f.to_string(),
+                "float".to_string(),
+            ))),
+            Json::String(s) => HeapObject::String(s.clone()).heap_alloc(interpreter),
+            Json::Array(elements) => elements
+                .iter()
+                .map(|element| element.to_scheme(interpreter, null))
+                .collect::<InterpreterResult<Vec<_>>>()?
+                .to_list(interpreter),
+            Json::Object(members) => members
+                .iter()
+                .map(|(key, value)| {
+                    HeapObject::List(
+                        HeapObject::String(key.clone()).heap_alloc(interpreter)?,
+                        value.to_scheme(interpreter, null)?,
+                    )
+                    .heap_alloc(interpreter)
+                })
+                .collect::<InterpreterResult<Vec<_>>>()?
+                .to_list(interpreter),
+        }
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// Writes compact JSON
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Int(i) => write!(f, "{i}"),
+            Json::Float(n) => write!(f, "{n:?}"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use serde::{
+        de::{self, MapAccess, SeqAccess, Visitor},
+        ser::{SerializeMap, SerializeSeq},
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    use super::Json;
+    use crate::{
+        embed::{Interpreter, Value},
+        object::ObjectPointer,
+    };
+
+    impl Serialize for Json {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self {
+                Json::Null => serializer.serialize_unit(),
+                Json::Bool(b) => serializer.serialize_bool(*b),
+                Json::Int(i) => serializer.serialize_i64(*i),
+                Json::Float(f) => serializer.serialize_f64(*f),
+                Json::String(s) => serializer.serialize_str(s),
+                Json::Array(elements) => {
+                    let mut seq = serializer.serialize_seq(Some(elements.len()))?;
+                    for element in elements {
+                        seq.serialize_element(element)?;
+                    }
+                    seq.end()
+                }
+                Json::Object(members) => {
+                    let mut map = serializer.serialize_map(Some(members.len()))?;
+                    for (key, value) in members {
+                        map.serialize_entry(key, value)?;
+                    }
+                    map.end()
+                }
+            }
+        }
+    }
+
+    struct JsonVisitor;
+
+    impl<'de> Visitor<'de> for JsonVisitor {
+        type Value = Json;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "a boolean, number, string, sequence, map or unit")
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Json, E> {
+            Ok(Json::Null)
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Json, E> {
+            Ok(Json::Null)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Json, D::Error> {
+            Json::deserialize(deserializer)
+        }
+
+        fn visit_bool<E: de::Error>(self, b: bool) -> Result<Json, E> {
+            Ok(Json::Bool(b))
+        }
+
+        fn visit_i64<E: de::Error>(self, i: i64) -> Result<Json, E> {
+            Ok(Json::Int(i))
+        }
+
+        fn visit_u64<E: de::Error>(self, u: u64) -> Result<Json, E> {
+            Ok(i64::try_from(u).map_or(Json::Float(u as f64), Json::Int))
+        }
+
+        fn visit_f64<E: de::Error>(self, f: f64) -> Result<Json, E> {
+            Ok(Json::Float(f))
+        }
+
+        fn visit_char<E: de::Error>(self, c: char) -> Result<Json, E> {
+            Ok(Json::String(c.to_string()))
+        }
+
+        fn visit_str<E: de::Error>(self, s: &str) -> Result<Json, E> {
+            Ok(Json::String(s.to_string()))
+        }
+
+        fn visit_string<E: de::Error>(self, s: String) -> Result<Json, E> {
+            Ok(Json::String(s))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Json, A::Error> {
+            let mut elements = Vec::new();
+            while let Some(element) = seq.next_element()? {
+                elements.push(element);
+            }
+            Ok(Json::Array(elements))
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Json, A::Error> {
+            let mut members = Vec::new();
+            while let Some(member) = map.next_entry()? {
+                members.push(member);
+            }
+            Ok(Json::Object(members))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Json {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Json, D::Error> {
+            deserializer.deserialize_any(JsonVisitor)
+        }
+    }
+
+    /// Serializes as [`Json::from_scheme`] reads the value
+    impl Serialize for Value {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Json::from_scheme(self.context(), self.pointer())
+                .map_err(|err| serde::ser::Error::custom(err.kind))?
+                .serialize(serializer)
+        }
+    }
+
+    impl Interpreter {
+        /// Builds a Scheme value from any serde format, as [`Json::to_scheme`] does with
+        /// `null` becoming the empty list
+        pub fn deserialize_value<'de, D: Deserializer<'de>>(
+            &self,
+            deserializer: D,
+        ) -> Result<Value, D::Error> {
+            let json = Json::deserialize(deserializer)?;
+            let pointer = json
+                .to_scheme(self.context(), &ObjectPointer::Null)
+                .map_err(|err| de::Error::custom(err.kind))?;
+            self.to_value(pointer).map_err(de::Error::custom)
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use crate::testing::interpreter;
+
+        #[test]
+        fn values_serialize_as_json() {
+            let interpreter = interpreter();
+            let value = interpreter
+                .eval_str("(list (cons \"a\" (list 1 2.5 #t)) (cons \"b\" \"c\"))")
+                .unwrap();
+            assert_eq!(
+                serde_json::to_string(&value).unwrap(),
+                r#"{"a":[1,2.5,true],"b":"c"}"#
+            );
+        }
+
+        #[test]
+        fn values_deserialize_from_json() {
+            let interpreter = interpreter();
+            let mut deserializer = serde_json::Deserializer::from_str(r#"[1, "x", null, 0.5]"#);
+            let value = interpreter.deserialize_value(&mut deserializer).unwrap();
+            assert_eq!(value.to_string(), r#"1:"x":():0.5:()"#);
+
+            let mut deserializer = serde_json::Deserializer::from_str("1e300");
+            assert!(interpreter.deserialize_value(&mut deserializer).is_err());
+            let mut deserializer = serde_json::Deserializer::from_str("3000000000");
+            assert!(interpreter.deserialize_value(&mut deserializer).is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Json, MAX_DEPTH};
+
+    fn parse(source: &str) -> Json {
+        Json::parse(source).unwrap()
+    }
+
+    fn parse_error(source: &str) -> String {
+        Json::parse(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn documents_are_parsed() {
+        assert_eq!(
+            parse(r#" {"a": [1, -2.5, 1e2], "b": null, "c": "é😀"} "#),
+            Json::Object(vec![
+                (
+                    "a".to_string(),
+                    Json::Array(vec![Json::Int(1), Json::Float(-2.5), Json::Float(100.0)])
+                ),
+                ("b".to_string(), Json::Null),
+                ("c".to_string(), Json::String("é😀".to_string())),
+            ])
+        );
+        assert!(parse_error("[1, 2").contains("expected ',' or ']'"));
+        assert!(parse_error("[1] 2").contains("expected end of input"));
+    }
+
+    #[test]
+    fn numbers_follow_the_grammar() {
+        assert_eq!(parse("0"), Json::Int(0));
+        assert_eq!(parse("-0.5"), Json::Float(-0.5));
+        assert!(parse_error("01").contains("leading zero"));
+        assert!(parse_error("-01.5").contains("leading zero"));
+        assert!(parse_error("1.").contains("expected a digit"));
+        assert!(parse_error("1e400").contains("invalid number '1e400'"));
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(parse_error(&nested(MAX_DEPTH + 1)).contains("nested deeper than"));
+        assert!(parse_error(&"[".repeat(100_000)).contains("nested deeper than"));
+    }
+
+    #[test]
+    fn documents_are_written_compactly() {
+        let json = Json::Object(vec![
+            ("a".to_string(), Json::Array(vec![Json::Int(1), Json::Float(2.0)])),
+            ("b\n".to_string(), Json::String("\"\u{1}".to_string())),
+        ]);
+        assert_eq!(json.to_string(), r#"{"a":[1,2.0],"b\n":"\"\u0001"}"#);
+        assert_eq!(parse(&json.to_string()), json);
+    }
+}
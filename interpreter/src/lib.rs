@@ -4,317 +4,1139 @@
 use std::{
     collections::HashMap,
     ops::Deref,
-    sync::{Arc, RwLock},
+    path::PathBuf,
+    io::Write,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
     thread::JoinHandle,
+    time::Instant,
 };
 
 use alloc::{InterpreterHeapAlloc, InterpreterStackAlloc};
+use capabilities::Capabilities;
 use core::{error::{AddIfNotSpannedExt, ErrorWriter}, parser::ast::AST, token::span::Span};
+use continuation::{own, Base, Continuation, Snapshot, Winder};
+use convert::IntoNative;
+use deferred::Deferred;
 use deref::InterpreterDeref;
 use error::{InterpreterError, InterpreterErrorKind};
+use exception::{DynamicState, Guard, Handler};
 use frame::Frame;
-use func::Func;
-use heap::InterpreterHeap;
+use func::{arities, Func, MacroFunc, MacroStep, NativeFunc};
+use heap::{GarbageCollector, GcStop, InterpreterHeap};
+use library::Library;
+use limits::{Budget, Limits};
+use list::InterpreterListAlloc;
 use object::{HeapObject, ObjectPointer, ObjectRef, StackObject};
+use parameter::Parameter;
 use stack::InterpreterStack;
 
 pub mod alloc;
+pub mod builder;
+pub mod capabilities;
 pub mod comparison;
+pub mod continuation;
+pub mod convert;
+pub mod deferred;
 pub mod deref;
+pub mod embed;
 pub mod error;
+pub mod exception;
 pub mod frame;
 pub mod func;
 pub mod heap;
+pub mod json;
+pub mod library;
+pub mod limits;
+pub mod list;
 pub mod object;
+pub mod parameter;
 pub mod print;
+pub mod promise;
 pub mod stack;
 pub mod std_lib;
+#[cfg(test)]
+mod testing;
+
+pub use builder::InterpreterBuilder;
+pub use embed::{Interpreter, Value};
+pub use std_lib::StdModule;
 
 pub type InterpreterResult<T> = Result<T, InterpreterError>;
 
+#[derive(Debug, Clone)]
+pub(crate) enum QueueOp<'a> {
+    Eval(&'a AST),
+    BuildList,
+
+    EvalLiteral(AST),
+    PopRefStack,
+
+    PopFuncOp(Span, Vec<&'a AST>),
+    PopFuncApply(Span, usize),
+    CountedParams(usize),
+    NamedParams(Vec<String>),
+    CaseParams(usize, usize),
+    MacroParams(Vec<&'a AST>),
+    /// The form of a macro, and how many of the values it asked for are on the data stack
+    MacroValues(Vec<&'a AST>, usize),
+
+    PushFrame(Frame),
+    PopFrame,
+    ApplyFunc(u64, Span),
+
+    Deferred(Deferred, Span),
+    EnsureResult(usize),
+    Catch(DynamicState, ObjectPointer, Span),
+    Guard(DynamicState, Guard, Span),
+    /// Keeps the code a resumed continuation refers into alive
+    Hold(Continuation),
+}
+
+impl QueueOp<'_> {
+    /// Copies any code this refers to into `roots`, so it can outlive its evaluation
+    fn detach(&self, roots: &mut Vec<Arc<AST>>) -> QueueOp<'static> {
+        match self {
+            QueueOp::Eval(ast) => QueueOp::Eval(own(roots, ast)),
+            QueueOp::PopFuncOp(span, params) => {
+                QueueOp::PopFuncOp(*span, params.iter().map(|p| own(roots, p)).collect())
+            }
+            QueueOp::MacroParams(params) => {
+                QueueOp::MacroParams(params.iter().map(|p| own(roots, p)).collect())
+            }
+            QueueOp::MacroValues(params, n) => {
+                QueueOp::MacroValues(params.iter().map(|p| own(roots, p)).collect(), *n)
+            }
+            QueueOp::BuildList => QueueOp::BuildList,
+            QueueOp::EvalLiteral(ast) => QueueOp::EvalLiteral(ast.clone()),
+            QueueOp::PopRefStack => QueueOp::PopRefStack,
+            QueueOp::PopFuncApply(span, n) => QueueOp::PopFuncApply(*span, *n),
+            QueueOp::CountedParams(n) => QueueOp::CountedParams(*n),
+            QueueOp::NamedParams(names) => QueueOp::NamedParams(names.clone()),
+            QueueOp::CaseParams(case, n) => QueueOp::CaseParams(*case, *n),
+            QueueOp::PushFrame(frame) => QueueOp::PushFrame(frame.clone()),
+            QueueOp::PopFrame => QueueOp::PopFrame,
+            QueueOp::ApplyFunc(hash, span) => QueueOp::ApplyFunc(*hash, *span),
+            QueueOp::Deferred(deferred, span) => QueueOp::Deferred(deferred.clone(), *span),
+            QueueOp::EnsureResult(height) => QueueOp::EnsureResult(*height),
+            QueueOp::Catch(state, handler, span) => QueueOp::Catch(*state, handler.clone(), *span),
+            QueueOp::Guard(state, guard, span) => QueueOp::Guard(*state, guard.clone(), *span),
+            QueueOp::Hold(k) => QueueOp::Hold(k.clone()),
+        }
+    }
+
+    /// Moves any stack heights recorded against `from` to be relative to `to`
+    fn rebase(&mut self, from: &Base, to: &Base) {
+        match self {
+            QueueOp::EnsureResult(height) => *height = from.rebase_data(to, *height),
+            QueueOp::PushFrame(frame) => {
+                frame.stack_index = from.rebase_frames(to, frame.stack_index)
+            }
+            QueueOp::Catch(state, _, _) | QueueOp::Guard(state, _, _) => {
+                state.data = from.rebase_data(to, state.data);
+                state.frames = from.rebase_frames(to, state.frames);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// The queued work of a single call to `interpret`
+struct Evaluation<'a> {
+    id: usize,
+    base: Base,
+    op_stack: Vec<QueueOp<'a>>,
+    ref_stack: Vec<AST>,
+    func_cache: HashMap<u64, Func>,
+}
+
 pub struct InterpreterContext {
     pub error_writer: RwLock<ErrorWriter>,
 
     pub ident_mapping: RwLock<HashMap<String, ObjectPointer>>,
+    /// Every library defined so far, latest last
+    pub libraries: RwLock<Vec<Arc<Library>>>,
+    /// Names of the libraries being loaded, for detecting import cycles
+    pub loading: RwLock<Vec<Vec<String>>>,
+    /// Directories given with `-I`, searched for imports after the importing directory
+    pub search_path: RwLock<Vec<PathBuf>>,
 
     pub stack: Arc<InterpreterStack>,
     pub heap: Arc<InterpreterHeap>,
 
-    pub gc_thread: JoinHandle<()>,
+    pub deferred: RwLock<Vec<Deferred>>,
+    pub handlers: RwLock<Vec<Handler>>,
+    pub winders: RwLock<Vec<Arc<Winder>>>,
+    /// Bindings made by `parameterize`, innermost last
+    pub parameters: RwLock<Vec<(Parameter, ObjectPointer)>>,
+
+    /// Ids of the evaluations currently running, innermost last
+    pub evaluations: RwLock<Vec<usize>>,
+    next_evaluation: AtomicUsize,
+
+    pub limits: Limits,
+    budget: Budget,
+    /// What scripts may read, write and import
+    pub capabilities: Capabilities,
+    /// What `command-line` returns, the script's name followed by its arguments
+    pub command_line: Vec<String>,
+    /// When the interpreter was created, what `current-jiffy` counts from
+    pub started: Instant,
+
+    /// Where `write` prints to
+    pub output: Mutex<Box<dyn Write + Send>>,
+
+    /// The collector thread, `None` when collection only happens on request
+    pub gc_thread: Option<JoinHandle<()>>,
+    gc_stop: Arc<GcStop>,
+}
+
+impl Drop for InterpreterContext {
+    fn drop(&mut self) {
+        self.gc_stop.stop();
+        if let Some(thread) = self.gc_thread.take() {
+            let _ = thread.join();
+        }
+    }
 }
 
 impl InterpreterContext {
+    /// An interpreter with the whole standard library, see [`InterpreterBuilder`] to
+    /// choose otherwise
     pub fn new(error_writer: ErrorWriter) -> Self {
-        let (heap, gc) = InterpreterHeap::new();
+        InterpreterBuilder::new().error_writer(error_writer).build()
+    }
 
-        let mut s = Self {
+    /// An interpreter with nothing installed, whose heap is collected by `gc` if given
+    pub(crate) fn bare(
+        error_writer: ErrorWriter,
+        heap: Arc<InterpreterHeap>,
+        gc: Option<GarbageCollector>,
+        output: Box<dyn Write + Send>,
+    ) -> Self {
+        let gc_stop = gc.as_ref().map_or_else(Default::default, |gc| gc.stop_signal());
+        Self {
             error_writer: RwLock::new(error_writer),
             ident_mapping: RwLock::new(HashMap::new()),
+            libraries: RwLock::new(Vec::new()),
+            loading: RwLock::new(Vec::new()),
+            search_path: RwLock::new(Vec::new()),
             heap,
             stack: Arc::new(InterpreterStack::new()),
-            gc_thread: gc.spawn_thread(),
-        };
-        s.with_std();
-        s
+            deferred: RwLock::new(Vec::new()),
+            handlers: RwLock::new(Vec::new()),
+            winders: RwLock::new(Vec::new()),
+            parameters: RwLock::new(Vec::new()),
+            evaluations: RwLock::new(Vec::new()),
+            next_evaluation: AtomicUsize::new(0),
+            limits: Limits::default(),
+            budget: Budget::default(),
+            capabilities: Capabilities::default(),
+            command_line: Vec::new(),
+            started: Instant::now(),
+            output: Mutex::new(output),
+            gc_thread: gc.map(|gc| gc.spawn_thread()),
+            gc_stop,
+        }
+    }
+
+    /// Frees every heap object nothing refers to any more, as the collector thread
+    /// does periodically
+    pub fn collect_garbage(&self) {
+        self.heap.collect();
     }
 
+    /// Evaluates the procedures of the standard library written in Scheme
+    #[cfg(feature = "prelude")]
+    pub(crate) fn load_prelude(&self) {
+        const PRELUDE: &str = include_str!("prelude.scm");
+
+        let id = self.error_writer.write().unwrap().load_string(PRELUDE.to_string());
+        let ast = core::LexerParser::from_string(id, PRELUDE.to_string(), &self.error_writer.read().unwrap())
+            .expect("prelude should parse");
+        for node in ast {
+            if let Err(err) = self.interpret(&node) {
+                let _ = self.error_writer.read().unwrap().report_errors(vec![err]);
+                panic!("prelude should evaluate");
+            }
+        }
+    }
+
+    /// Binds `name` globally to a native procedure running `f`, which is passed the
+    /// number of arguments to pop off the data stack
+    pub fn register_fn(
+        &self,
+        name: &str,
+        f: impl Fn(&InterpreterContext, usize) -> InterpreterResult<()> + Send + Sync + 'static,
+    ) {
+        self.register_native(name, NativeFunc::new(f));
+    }
+
+    /// Binds `name` globally to a Rust function taking and returning values which
+    /// convert to and from Scheme, see [`convert`]. The native checks the number and
+    /// types of its arguments, and raises any error the function returns
+    pub fn register_typed<Args>(&self, name: &str, f: impl IntoNative<Args>) {
+        self.register_native(name, f.into_native(name));
+    }
+
+    /// Binds `name` globally to an already built native, such as one holding user data
+    pub fn register_native(&self, name: &str, native: NativeFunc) {
+        HeapObject::Func(Func::Native(name.to_string(), native))
+            .heap_alloc_named(name, self)
+            .unwrap();
+    }
+
+    /// Adds a directory to search for imported libraries and files
+    pub fn add_search_path(&self, dir: impl Into<PathBuf>) {
+        self.search_path.write().unwrap().push(dir.into());
+    }
+
+    /// Installs the special forms and every module of the standard library
     pub fn with_std(&mut self) {
+        self.with_syntax();
+        for module in StdModule::ALL {
+            self.with_module(*module);
+        }
+    }
+
+    /// Installs the special forms, such as `define` and `lambda`, which every
+    /// interpreter has
+    pub fn with_syntax(&self) {
         fn alloc_func(int: &InterpreterContext, f: Func) {
             let name = match &f {
                 Func::TokenNative(s, _)
                 | Func::Macro(s, _)
-                | Func::Defined(Some(s), _, _)
-                | Func::Native(s, _) => s,
+                | Func::Defined(Some(s), _, _) => s,
                 _ => panic!(),
             }
             .clone();
             HeapObject::Func(f).heap_alloc_named(&name, int).unwrap();
         }
 
-        alloc_func(self, Func::TokenNative("import".into(), std_lib::import));
-
-        alloc_func(
-            self,
-            Func::Native("stack-trace".into(), std_lib::stack_trace),
-        );
-        alloc_func(self, Func::Native("heap-dump".into(), std_lib::heap_dump));
-
+        alloc_func(self, Func::TokenNative("import".into(), std_lib::library::import));
+        alloc_func(self, Func::TokenNative("define-library".into(), std_lib::library::define_library));
         alloc_func(self, Func::TokenNative("define".into(), std_lib::define));
         alloc_func(self, Func::TokenNative("lambda".into(), std_lib::lambda));
-
         alloc_func(self, Func::Macro("if".into(), std_lib::if_macro));
         alloc_func(self, Func::Macro("let".into(), std_lib::let_));
+        alloc_func(self, Func::TokenNative("guard".into(), std_lib::exception::guard));
+        alloc_func(self, Func::TokenNative("let-values".into(), std_lib::values::let_values));
+        alloc_func(self, Func::TokenNative("let*-values".into(), std_lib::values::let_star_values));
+        alloc_func(self, Func::TokenNative("receive".into(), std_lib::values::receive));
+        alloc_func(self, Func::TokenNative("define-values".into(), std_lib::values::define_values));
+        alloc_func(self, Func::TokenNative("delay".into(), std_lib::promise::delay));
+        alloc_func(self, Func::TokenNative("delay-force".into(), std_lib::promise::delay_force));
+        alloc_func(self, Func::TokenNative("stream-cons".into(), std_lib::stream::stream_cons));
+        alloc_func(self, Func::TokenNative("do".into(), std_lib::iteration::do_loop));
+        alloc_func(self, Func::TokenNative("case-lambda".into(), std_lib::iteration::case_lambda));
+        alloc_func(self, Func::TokenNative("match".into(), std_lib::matching::match_));
+        alloc_func(self, Func::TokenNative("parameterize".into(), std_lib::parameter::parameterize));
+    }
 
-        alloc_func(self, Func::Native("car".into(), std_lib::car));
-        alloc_func(self, Func::Native("cdr".into(), std_lib::cdr));
-        alloc_func(self, Func::Native("cons".into(), std_lib::cons));
-        alloc_func(self, Func::Native("empty?".into(), std_lib::empty));
-
-        alloc_func(self, Func::Native("write".into(), std_lib::write));
-
-        alloc_func(self, Func::Native("file->string".into(), std_lib::file_to_string));
-        alloc_func(self, Func::Native("string->chars".into(), std_lib::string_to_chars));
-        alloc_func(self, Func::Native("string->int".into(), std_lib::string_to_int));
-        alloc_func(self, Func::Native("string->float".into(), std_lib::string_to_float));
-
-        alloc_func(self, Func::Native("+".into(), std_lib::add));
-        alloc_func(self, Func::Native("-".into(), std_lib::sub));
-        alloc_func(self, Func::Native("*".into(), std_lib::mul));
-        alloc_func(self, Func::Native("/".into(), std_lib::div));
-
-        alloc_func(self, Func::Native("eq?".into(), std_lib::eq));
-        alloc_func(self, Func::Native("==".into(), std_lib::eq));
-        alloc_func(self, Func::Native("<".into(), std_lib::lt));
-        alloc_func(self, Func::Native("<=".into(), std_lib::lteq));
-        alloc_func(self, Func::Native(">".into(), std_lib::gt));
-        alloc_func(self, Func::Native(">=".into(), std_lib::gteq));
+    /// Installs the native procedures of one module of the standard library
+    pub fn with_module(&self, module: StdModule) {
+        match module {
+            StdModule::List => {
+                self.register_fn("car", std_lib::car);
+                self.register_fn("cdr", std_lib::cdr);
+                self.register_fn("cons", std_lib::cons);
+                self.register_fn("empty?", std_lib::empty);
+                self.register_fn("list", std_lib::list::list);
+                self.register_fn("list?", std_lib::list::is_list);
+                self.register_fn("pair?", std_lib::list::is_pair);
+                self.register_fn("null?", std_lib::list::is_null);
+                self.register_fn("length", std_lib::list::length);
+                self.register_fn("append", std_lib::list::append);
+                self.register_fn("reverse", std_lib::list::reverse);
+                self.register_fn("list-tail", std_lib::list::list_tail);
+                self.register_fn("list-ref", std_lib::list::list_ref);
+                self.register_fn("list-copy", std_lib::list::list_copy);
+                self.register_fn("last-pair", std_lib::list::last_pair);
+                self.register_fn("memq", std_lib::list::memq);
+                self.register_fn("memv", std_lib::list::memv);
+                self.register_fn("member", std_lib::list::member);
+                self.register_fn("assq", std_lib::list::assq);
+                self.register_fn("assv", std_lib::list::assv);
+                self.register_fn("assoc", std_lib::list::assoc);
+                self.register_fn("caar", std_lib::list::caar);
+                self.register_fn("cadr", std_lib::list::cadr);
+                self.register_fn("cdar", std_lib::list::cdar);
+                self.register_fn("cddr", std_lib::list::cddr);
+                self.register_fn("caaar", std_lib::list::caaar);
+                self.register_fn("caadr", std_lib::list::caadr);
+                self.register_fn("cadar", std_lib::list::cadar);
+                self.register_fn("caddr", std_lib::list::caddr);
+                self.register_fn("cdaar", std_lib::list::cdaar);
+                self.register_fn("cdadr", std_lib::list::cdadr);
+                self.register_fn("cddar", std_lib::list::cddar);
+                self.register_fn("cdddr", std_lib::list::cdddr);
+            }
+            StdModule::HigherOrder => {
+                self.register_fn("apply", std_lib::higher_order::apply);
+                self.register_fn("map", std_lib::higher_order::map);
+                self.register_fn("for-each", std_lib::higher_order::for_each);
+                self.register_fn("filter", std_lib::higher_order::filter);
+                self.register_fn("remove", std_lib::higher_order::remove);
+                self.register_fn("partition", std_lib::higher_order::partition);
+                self.register_fn("fold", std_lib::higher_order::fold);
+                self.register_fn("fold-left", std_lib::higher_order::fold_left);
+                self.register_fn("fold-right", std_lib::higher_order::fold_right);
+                self.register_fn("reduce", std_lib::higher_order::reduce);
+                self.register_fn("find", std_lib::higher_order::find);
+                self.register_fn("any", std_lib::higher_order::any);
+                self.register_fn("every", std_lib::higher_order::every);
+                self.register_fn("count", std_lib::higher_order::count);
+                self.register_fn("delete", std_lib::higher_order::delete);
+                self.register_fn("iota", std_lib::higher_order::iota);
+                self.register_fn("list-index", std_lib::higher_order::list_index);
+            }
+            StdModule::Numeric => {
+                self.register_fn("+", std_lib::add);
+                self.register_fn("-", std_lib::sub);
+                self.register_fn("*", std_lib::mul);
+                self.register_fn("/", std_lib::div);
+                self.register_fn("=", std_lib::num_eq);
+                self.register_fn("<", std_lib::lt);
+                self.register_fn("<=", std_lib::lteq);
+                self.register_fn(">", std_lib::gt);
+                self.register_fn(">=", std_lib::gteq);
+                self.register_fn("floor/", std_lib::numeric::floor_div);
+                self.register_fn("truncate/", std_lib::numeric::truncate_div);
+                self.register_fn("exact-integer-sqrt", std_lib::numeric::exact_integer_sqrt);
+            }
+            StdModule::Types => {
+                self.register_fn("number?", std_lib::types::is_number);
+                self.register_fn("integer?", std_lib::types::is_integer);
+                self.register_fn("string?", std_lib::types::is_string);
+                self.register_fn("boolean?", std_lib::types::is_boolean);
+                self.register_fn("char?", std_lib::types::is_char);
+                self.register_fn("procedure?", std_lib::types::is_procedure);
+                self.register_fn("string->chars", std_lib::string_to_chars);
+                self.register_fn("string->int", std_lib::string_to_int);
+                self.register_fn("string->float", std_lib::string_to_float);
+            }
+            StdModule::Ordering => {
+                self.register_fn("eq?", std_lib::eq);
+                self.register_fn("eqv?", std_lib::eqv);
+                self.register_fn("equal?", std_lib::equal);
+                self.register_fn("==", std_lib::equal);
+                self.register_fn("string=?", std_lib::ordering::string_eq);
+                self.register_fn("string<?", std_lib::ordering::string_lt);
+                self.register_fn("string>?", std_lib::ordering::string_gt);
+                self.register_fn("string<=?", std_lib::ordering::string_lteq);
+                self.register_fn("string>=?", std_lib::ordering::string_gteq);
+                self.register_fn("string-ci=?", std_lib::ordering::string_ci_eq);
+                self.register_fn("string-ci<?", std_lib::ordering::string_ci_lt);
+                self.register_fn("string-ci>?", std_lib::ordering::string_ci_gt);
+                self.register_fn("string-ci<=?", std_lib::ordering::string_ci_lteq);
+                self.register_fn("string-ci>=?", std_lib::ordering::string_ci_gteq);
+                self.register_fn("char=?", std_lib::ordering::char_eq);
+                self.register_fn("char<?", std_lib::ordering::char_lt);
+                self.register_fn("char>?", std_lib::ordering::char_gt);
+                self.register_fn("char<=?", std_lib::ordering::char_lteq);
+                self.register_fn("char>=?", std_lib::ordering::char_gteq);
+                self.register_fn("char-ci=?", std_lib::ordering::char_ci_eq);
+                self.register_fn("char-ci<?", std_lib::ordering::char_ci_lt);
+                self.register_fn("char-ci>?", std_lib::ordering::char_ci_gt);
+                self.register_fn("char-ci<=?", std_lib::ordering::char_ci_lteq);
+                self.register_fn("char-ci>=?", std_lib::ordering::char_ci_gteq);
+                self.register_fn("compare", std_lib::ordering::compare);
+                self.register_fn("sort", std_lib::ordering::sort);
+                self.register_fn("list-sort", std_lib::ordering::list_sort);
+            }
+            StdModule::Exception => {
+                self.register_fn("error", std_lib::exception::error);
+                self.register_fn("raise", std_lib::exception::raise);
+                self.register_fn("raise-continuable", std_lib::exception::raise_continuable);
+                self.register_fn("with-exception-handler", std_lib::exception::with_exception_handler);
+                self.register_fn("error-object?", std_lib::exception::is_error_object);
+                self.register_fn("error-object-message", std_lib::exception::error_object_message);
+                self.register_fn("error-object-irritants", std_lib::exception::error_object_irritants);
+            }
+            StdModule::Control => {
+                self.register_fn("call-with-current-continuation", std_lib::control::call_cc);
+                self.register_fn("call/cc", std_lib::control::call_cc);
+                self.register_fn("dynamic-wind", std_lib::control::dynamic_wind);
+                self.register_fn("values", std_lib::values::values);
+                self.register_fn("call-with-values", std_lib::values::call_with_values);
+                self.register_fn("make-parameter", std_lib::parameter::make_parameter);
+            }
+            StdModule::Lazy => {
+                self.register_fn("make-promise", std_lib::promise::make_promise);
+                self.register_fn("promise?", std_lib::promise::is_promise);
+                self.register_fn("force", std_lib::promise::force);
+                self.register_fn("stream-null?", std_lib::stream::is_stream_null);
+                self.register_fn("stream-pair?", std_lib::stream::is_stream_pair);
+                self.register_fn("stream-car", std_lib::stream::stream_car);
+                self.register_fn("stream-cdr", std_lib::stream::stream_cdr);
+                self.register_fn("stream-take", std_lib::stream::stream_take);
+                self.register_fn("stream-map", std_lib::stream::stream_map);
+                self.register_fn("stream-filter", std_lib::stream::stream_filter);
+                self.register_fn("list->stream", std_lib::stream::list_to_stream);
+                self.register_fn("stream->list", std_lib::stream::stream_to_list);
+                std_lib::stream::empty_stream(self)
+                    .and_then(|s| StackObject::Ref(s).heap_alloc_named("stream-null", self))
+                    .unwrap();
+            }
+            StdModule::Json => {
+                self.register_fn("json-read", std_lib::json::json_read);
+                self.register_fn("json-write", std_lib::json::json_write);
+            }
+            StdModule::Io => {
+                self.register_fn("write", std_lib::write);
+                self.register_fn("file->string", std_lib::file_to_string);
+            }
+            StdModule::Process => {
+                self.register_fn("command-line", std_lib::process::command_line);
+                self.register_fn("exit", std_lib::process::exit);
+                self.register_fn("emergency-exit", std_lib::process::emergency_exit);
+                self.register_fn(
+                    "get-environment-variable",
+                    std_lib::process::get_environment_variable,
+                );
+                self.register_fn(
+                    "get-environment-variables",
+                    std_lib::process::get_environment_variables,
+                );
+                self.register_fn("process-run", std_lib::process::process_run);
+            }
+            StdModule::Time => {
+                self.register_fn("current-second", std_lib::time::current_second);
+                self.register_fn("current-jiffy", std_lib::time::current_jiffy);
+                self.register_fn("jiffies-per-second", std_lib::time::jiffies_per_second);
+            }
+            StdModule::Debug => {
+                self.register_fn("stack-trace", std_lib::stack_trace);
+                self.register_fn("heap-dump", std_lib::heap_dump);
+            }
+        }
     }
 
-    pub fn start(&self, ast: Vec<AST>) {
+    /// Evaluates each node in turn, reporting the first error and stopping there.
+    /// Returns the status to exit with if evaluation stopped early, 1 after an error
+    /// or the status given to `exit`
+    pub fn start(&self, ast: Vec<AST>) -> Result<(), i32> {
         for node in ast {
             if let Err(err) = self.interpret(&node) {
+                if let InterpreterErrorKind::Exit(status) = err.kind {
+                    return Err(status);
+                }
                 let _ = self.error_writer.read().unwrap().report_errors(vec![err]);
                 // self.stack_trace();
                 // self.heap.dump(self);
-                break;
+                return Err(1);
             }
         }
+        Ok(())
     }
 
     pub fn interpret(&self, ast: &AST) -> InterpreterResult<()> {
-        #[derive(Debug)]
-        enum QueueOp<'a> {
-            Eval(&'a AST),
-            BuildList,
-
-            EvalLiteral(AST),
-            PopRefStack,
-
-            PopFuncOp(Span, Vec<&'a AST>),
-            CountedParams(usize),
-            NamedParams(Vec<String>),
-            MacroParams(Vec<&'a AST>),
-
-            PushFrame(Frame),
-            PopFrame,
-            ApplyFunc(u64, Span),
+        self.run(QueueOp::Eval(ast))
+    }
+
+    /// Applies a procedure to already evaluated arguments, returning its result
+    pub fn apply(
+        &self,
+        func: ObjectPointer,
+        args: Vec<ObjectPointer>,
+        span: Span,
+    ) -> InterpreterResult<ObjectPointer> {
+        self.run(QueueOp::Deferred(Deferred::Apply(func, args), span))?;
+        self.stack.pop_data()?.heap_alloc(self)
+    }
+
+    fn run(&self, op: QueueOp) -> InterpreterResult<()> {
+        let mut eval = Evaluation {
+            id: self.next_evaluation.fetch_add(1, Ordering::Relaxed),
+            base: Base {
+                data: self.stack.data.read().unwrap().len(),
+                frames: self.stack.frame.read().unwrap().len(),
+            },
+            op_stack: vec![op],
+            ref_stack: Vec::new(),
+            func_cache: HashMap::new(),
+        };
+        let outermost = self.evaluations.read().unwrap().is_empty();
+        if outermost {
+            self.start_budget();
         }
+        let state = DynamicState::capture(self);
+        self.evaluations.write().unwrap().push(eval.id);
 
-        let head = ast;
-        let mut op_stack = vec![QueueOp::Eval(head)];
-        let mut ref_stack = Vec::new();
-        let mut func_cache = HashMap::<u64, Func>::new();
-        while !op_stack.is_empty() {
-            let next = op_stack.pop().unwrap();
-            match next {
-                QueueOp::EvalLiteral(ast) => {
-                    ref_stack.push(ast);
-                    op_stack.push(QueueOp::PopRefStack);
-                    op_stack.push(QueueOp::Eval(unsafe {
-                        {
-                            (&*ref_stack.last().unwrap() as *const AST)
-                                .as_ref()
-                                .unwrap()
-                        }
-                    }))
-                }
-                QueueOp::PopRefStack => {
-                    ref_stack.pop();
+        let mut result = Ok(());
+        while let Some(next) = eval.op_stack.pop() {
+            if let Err(err) = self.charge_step().and_then(|_| self.step(next, &mut eval)) {
+                if let Err(err) = self.unwind(err, &mut eval) {
+                    result = Err(err);
+                    break;
                 }
-                QueueOp::Eval(ast) => match ast {
-                    AST::Identifier(ident, span) => {
-                        let p = self.resolve_identifier(ident, *span)?;
-                        self.stack.push_data(StackObject::Ref(p));
-                    }
-                    AST::Literal(lit, _) => self.stack.push_data(StackObject::Value(*lit)),
-                    AST::EmptyList(_) => {
-                        self.stack.push_data(StackObject::Ref(ObjectPointer::Null))
-                    }
-                    AST::StringLiteral(s, _) => {
-                        let p = HeapObject::String(s.clone()).stack_alloc(self)?;
-                        self.stack.push_data(p)
+            }
+        }
+
+        self.evaluations.write().unwrap().pop();
+        if outermost {
+            self.finish_budget();
+        }
+        // An error escaping to the top leaves the interpreter as it was before, so it can
+        // go on evaluating
+        if outermost && result.is_err() {
+            state.restore(self);
+        }
+        result
+    }
+
+    fn step<'a>(&self, next: QueueOp<'a>, eval: &mut Evaluation<'a>) -> InterpreterResult<()> {
+        let Evaluation {
+            op_stack,
+            ref_stack,
+            func_cache,
+            ..
+        } = eval;
+
+        match next {
+            QueueOp::EvalLiteral(ast) => {
+                ref_stack.push(ast);
+                op_stack.push(QueueOp::PopRefStack);
+                op_stack.push(QueueOp::Eval(unsafe {
+                    {
+                        (ref_stack.last().unwrap() as *const AST)
+                            .as_ref()
+                            .unwrap()
                     }
-                    AST::List(head, tail, _) => {
-                        op_stack.extend([
-                            QueueOp::BuildList,
-                            QueueOp::Eval(&head),
-                            QueueOp::Eval(&tail),
-                        ]);
+                }))
+            }
+            QueueOp::PopRefStack => {
+                ref_stack.pop();
+            }
+            QueueOp::Eval(ast) => match ast {
+                AST::Identifier(ident, span) => {
+                    let p = self.resolve_identifier(ident, *span)?;
+                    self.stack.push_data(StackObject::Ref(p));
+                }
+                AST::Literal(lit, _) => self.stack.push_data(StackObject::Value(*lit)),
+                AST::EmptyList(_) => {
+                    self.stack.push_data(StackObject::Ref(ObjectPointer::Null))
+                }
+                AST::StringLiteral(s, _) => {
+                    let p = HeapObject::String(s.clone()).stack_alloc(self)?;
+                    self.stack.push_data(p)
+                }
+                AST::List(head, tail, _) => {
+                    op_stack.extend([
+                        QueueOp::BuildList,
+                        QueueOp::Eval(head),
+                        QueueOp::Eval(tail),
+                    ]);
+                }
+                AST::Operation(_, params, span) if matches!(params.last(), Some(AST::Rest(..))) => {
+                    return Err(InterpreterError::spanned(
+                        InterpreterErrorKind::MisplacedRest(ast.to_string()),
+                        *span,
+                    ));
+                }
+                AST::Rest(..) => {
+                    return Err(InterpreterError::spanned(
+                        InterpreterErrorKind::MisplacedRest(ast.to_string()),
+                        ast.span(),
+                    ));
+                }
+                AST::Operation(op, params, _) => {
+                    op_stack.extend([
+                        QueueOp::PopFuncOp(op.span(), params.iter().collect()),
+                        QueueOp::Eval(op),
+                    ]);
+                }
+            },
+            QueueOp::BuildList => {
+                let head = self.stack.pop_data()?.heap_alloc(self)?;
+                let tail = self.stack.pop_data()?.heap_alloc(self)?;
+                let pointer = HeapObject::List(head, tail).stack_alloc(self)?;
+                self.stack.push_data(pointer);
+            }
+
+            op @ (QueueOp::PopFuncOp(..) | QueueOp::PopFuncApply(..)) => {
+                let (span, params, param_len) = match op {
+                    QueueOp::PopFuncOp(span, params) => {
+                        let param_len = params.len();
+                        (span, Some(params), param_len)
                     }
-                    AST::Operation(op, params, _) => {
-                        op_stack.extend([
-                            QueueOp::PopFuncOp(op.span(), params.iter().collect()),
-                            QueueOp::Eval(op),
-                        ]);
+                    QueueOp::PopFuncApply(span, n) => (span, None, n),
+                    _ => unreachable!(),
+                };
+
+                let pointer = match self.stack.pop_data()? {
+                    StackObject::Ref(r) => r,
+                    StackObject::Value(v) => {
+                        return Err(InterpreterError::spanned(
+                            InterpreterErrorKind::CannotCall(v.to_string()),
+                            span,
+                        ))
                     }
-                },
-                QueueOp::BuildList => {
-                    let head = self.stack.pop_data()?.heap_alloc(self)?;
-                    let tail = self.stack.pop_data()?.heap_alloc(self)?;
-                    let pointer = HeapObject::List(head, tail).stack_alloc(self)?;
-                    self.stack.push_data(pointer);
-                }
+                };
+
+                let ObjectRef::Object(lock) = pointer.deref(self)? else {
+                    return Err(InterpreterError::spanned(
+                        InterpreterErrorKind::CannotCall(
+                            pointer.deref(self).unwrap().to_string(),
+                        ),
+                        span,
+                    ));
+                };
+                let HeapObject::Func(func) = lock.deref() else {
+                    return Err(InterpreterError::spanned(
+                        InterpreterErrorKind::CannotCall(lock.deref().to_string()),
+                        span,
+                    ));
+                };
 
-                QueueOp::PopFuncOp(span, mut params) => {
-                    let pointer = match self.stack.pop_data()? {
-                        StackObject::Ref(r) => r,
-                        StackObject::Value(v) => {
+                let func_hash = func.calc_hash();
+                func_cache.entry(func_hash).or_insert_with(|| func.clone());
+                let mut new_frame = !self
+                    .stack
+                    .top_frame()
+                    .is_ok_and(|f| f.func_hash == func.calc_hash());
+
+                let mut new_ops = Vec::new();
+                match func {
+                    Func::Defined(_, p, _) => {
+                        if p.len() != param_len {
                             return Err(InterpreterError::spanned(
-                                InterpreterErrorKind::CannotCall(v.to_string()),
+                                InterpreterErrorKind::ExpectedNParams(p.len(), param_len),
                                 span,
-                            ))
+                            ));
                         }
-                    };
-
-                    let ObjectRef::Object(lock) = pointer.deref(self)? else {
-                        return Err(InterpreterError::spanned(
-                            InterpreterErrorKind::CannotCall(
-                                pointer.deref(self).unwrap().to_string(),
-                            ),
-                            span,
-                        ));
-                    };
-                    let HeapObject::Func(func) = lock.deref() else {
+                        new_ops.push(QueueOp::NamedParams(p.clone()));
+                        new_ops.push(QueueOp::ApplyFunc(func_hash, span));
+                        new_ops.extend(params.into_iter().flatten().map(QueueOp::Eval).rev());
+                    }
+                    Func::CaseLambda(cases) => {
+                        let Some(case) = cases.iter().position(|c| c.accepts(param_len)) else {
+                            return Err(InterpreterError::spanned(
+                                InterpreterErrorKind::ArityMismatch(arities(cases), param_len),
+                                span,
+                            ));
+                        };
+                        new_ops.push(QueueOp::CaseParams(case, param_len));
+                        new_ops.push(QueueOp::ApplyFunc(func_hash, span));
+                        new_ops.extend(params.into_iter().flatten().map(QueueOp::Eval).rev());
+                    }
+                    Func::Native(_, _) | Func::Continuation(_) | Func::Parameter(_) => {
+                        new_ops.push(QueueOp::CountedParams(param_len));
+                        new_ops.push(QueueOp::ApplyFunc(func_hash, span));
+                        new_ops.extend(params.into_iter().flatten().map(QueueOp::Eval).rev());
+                    }
+                    Func::TokenNative(name, _) | Func::Macro(name, _) if params.is_none() => {
                         return Err(InterpreterError::spanned(
-                            InterpreterErrorKind::CannotCall(lock.deref().to_string()),
+                            InterpreterErrorKind::CannotApplySyntax(name.clone()),
                             span,
                         ));
-                    };
-
-                    let func_hash = func.calc_hash();
-                    func_cache.entry(func_hash).or_insert_with(|| func.clone());
-                    let mut new_frame = !self
-                        .stack
-                        .top_frame()
-                        .is_ok_and(|f| f.func_hash == func.calc_hash());
-
-                    let mut new_ops = Vec::new();
-                    let param_len = params.len();
-                    match func {
-                        Func::Defined(_, p, _) => {
-                            if p.len() != param_len {
-                                return Err(InterpreterError::spanned(
-                                    InterpreterErrorKind::ExpectedNParams(p.len(), param_len),
-                                    span,
-                                ));
-                            }
-                            new_ops.push(QueueOp::NamedParams(p.clone()));
-                            new_ops.push(QueueOp::ApplyFunc(func_hash, span));
-                            new_ops.extend(params.drain(..).map(|p| QueueOp::Eval(p)).rev());
-                        }
-                        Func::Native(_, _) => {
-                            new_ops.push(QueueOp::CountedParams(param_len));
-                            new_ops.push(QueueOp::ApplyFunc(func_hash, span));
-                            new_ops.extend(params.drain(..).map(|p| QueueOp::Eval(p)).rev());
-                        }
-                        Func::TokenNative(_, _) => {
-                            new_ops.push(QueueOp::MacroParams(params));
-                            new_ops.push(QueueOp::ApplyFunc(func_hash, span));
-                        }
-                        Func::Macro(_, _) => {
-                            new_ops.push(QueueOp::MacroParams(params));
-                            new_ops.push(QueueOp::ApplyFunc(func_hash, span));
-                            new_frame = false;
-                        }
                     }
-
-                    if new_frame {
-                        op_stack.push(QueueOp::PopFrame);
+                    Func::TokenNative(_, _) => {
+                        new_ops.push(QueueOp::MacroParams(params.unwrap()));
+                        new_ops.push(QueueOp::ApplyFunc(func_hash, span));
                     }
-                    op_stack.extend(new_ops);
-                    if new_frame {
-                        op_stack.push(QueueOp::PushFrame(Frame::new(
-                            self.stack.frame.read().unwrap().len(),
-                            func,
-                        )));
+                    Func::Macro(_, _) => {
+                        new_ops.push(QueueOp::MacroParams(params.unwrap()));
+                        new_ops.push(QueueOp::ApplyFunc(func_hash, span));
+                        new_frame = false;
                     }
                 }
 
-                QueueOp::PushFrame(frame) => {
-                    self.stack.push_frame(frame);
+                if new_frame {
+                    op_stack.push(QueueOp::PopFrame);
                 }
-                QueueOp::PopFrame => {
-                    self.stack.pop_frame()?;
+                op_stack.extend(new_ops);
+                if new_frame {
+                    op_stack.push(QueueOp::PushFrame(Frame::new(
+                        self.stack.frame.read().unwrap().len(),
+                        func,
+                    )));
                 }
+            }
 
-                QueueOp::ApplyFunc(func_hash, span) => {
-                    let func = func_cache.get(&func_hash).unwrap();
-
-                    let params = op_stack.last().unwrap();
-                    match (params, func) {
-                        (QueueOp::NamedParams(param_names), Func::Defined(_, _, ast)) => {
-                            let mut params = Vec::new();
-                            for _ in 0..param_names.len() {
-                                params.push(self.stack.pop_data()?.heap_alloc(self)?);
-                            }
-                            params.reverse();
+            QueueOp::PushFrame(frame) => {
+                self.stack.push_frame(frame);
+            }
+            QueueOp::PopFrame => {
+                self.stack.pop_frame()?;
+            }
 
-                            let mut top_frame = self.stack.top_frame()?;
-                            param_names.iter().zip(params).for_each(|(name, obj)| {
-                                top_frame.insert_local(name, obj);
-                            });
+            QueueOp::ApplyFunc(func_hash, span) => {
+                let func = func_cache.get(&func_hash).unwrap();
 
-                            op_stack.push(QueueOp::EvalLiteral(ast.clone()))
-                        }
-                        (QueueOp::CountedParams(n), Func::Native(_, native_func)) => {
-                            native_func(self, *n).map_not_spanned(span)?
+                let params = op_stack.last().unwrap();
+                match (params, func) {
+                    (QueueOp::NamedParams(param_names), Func::Defined(_, _, ast)) => {
+                        let mut params = Vec::new();
+                        for _ in 0..param_names.len() {
+                            params.push(self.stack.pop_data()?.heap_alloc(self)?);
                         }
-                        (QueueOp::MacroParams(params), Func::TokenNative(_, token_native)) => {
-                            token_native(self, params.to_vec()).map_not_spanned(span)?;
+                        params.reverse();
+
+                        let mut top_frame = self.stack.top_frame()?;
+                        param_names.iter().zip(params).for_each(|(name, obj)| {
+                            top_frame.insert_local(name, obj);
+                        });
+
+                        op_stack.push(QueueOp::EvalLiteral(ast.clone()))
+                    }
+                    (QueueOp::CountedParams(n), Func::Native(_, native_func)) => {
+                        let n = *n;
+                        self.with_deferred(span, op_stack, || native_func.call(self, n))?;
+                    }
+                    (QueueOp::CaseParams(case, n), Func::CaseLambda(cases)) => {
+                        let case = &cases[*case];
+                        let mut params = std_lib::pop_params(self, *n)?;
+                        let rest = params.split_off(case.params.len()).to_list(self)?;
+
+                        let mut top_frame = self.stack.top_frame()?;
+                        case.params.iter().zip(params).for_each(|(name, obj)| {
+                            top_frame.insert_local(name, obj);
+                        });
+                        if let Some(name) = &case.rest {
+                            top_frame.insert_local(name, rest);
                         }
-                        (QueueOp::MacroParams(params), Func::Macro(_, macro_f)) => {
-                            let out = macro_f(self, params.to_vec()).map_not_spanned(span)?;
-                            op_stack.push(QueueOp::Eval(params[out]));
+
+                        op_stack.push(QueueOp::EvalLiteral(case.body.clone()))
+                    }
+                    (QueueOp::CountedParams(n), Func::Parameter(parameter)) => {
+                        if *n != 0 {
+                            return Err(InterpreterError::spanned(
+                                InterpreterErrorKind::ExpectedNParams(0, *n),
+                                span,
+                            ));
                         }
-                        e => panic!("{e:?}"),
-                    };
+                        self.stack.push_data(StackObject::Ref(parameter.get(self)));
+                    }
+                    (QueueOp::CountedParams(n), Func::Continuation(k)) => {
+                        let values = std_lib::pop_params(self, *n)?;
+                        return Err(self.invoke(k.clone(), values, span));
+                    }
+                    (QueueOp::MacroParams(params), Func::TokenNative(_, token_native)) => {
+                        let params = params.to_vec();
+                        self.with_deferred(span, op_stack, || token_native(self, params))?;
+                    }
+                    (QueueOp::MacroParams(params), Func::Macro(_, macro_f)) => {
+                        let params = params.to_vec();
+                        self.expand_macro(*macro_f, func_hash, span, params, 0, op_stack)?;
+                    }
+                    (QueueOp::MacroValues(params, n), Func::Macro(_, macro_f)) => {
+                        let (params, n) = (params.to_vec(), *n);
+                        self.expand_macro(*macro_f, func_hash, span, params, n, op_stack)?;
+                    }
+                    e => panic!("{e:?}"),
+                };
+            }
+            QueueOp::Deferred(Deferred::Apply(func, args), span) => {
+                let height = self.stack.data.read().unwrap().len();
+                let argc = args.len();
+                for arg in args {
+                    self.stack.push_data(StackObject::Ref(arg));
+                }
+                self.stack.push_data(StackObject::Ref(func));
+                op_stack.push(QueueOp::EnsureResult(height));
+                op_stack.push(QueueOp::PopFuncApply(span, argc));
+            }
+            QueueOp::Deferred(Deferred::Then(continuation), span) => {
+                self.with_deferred(span, op_stack, || continuation(self))?;
+            }
+            QueueOp::Deferred(Deferred::WithHandler(handler, thunk), span) => {
+                let state = DynamicState::capture(self);
+                self.handlers
+                    .write()
+                    .unwrap()
+                    .push(Handler::Procedure(handler.clone()));
+                op_stack.push(QueueOp::Catch(state, handler, span));
+                op_stack.push(QueueOp::Deferred(Deferred::Apply(thunk, Vec::new()), span));
+            }
+            QueueOp::Deferred(Deferred::CallWithContinuation(func), span) => {
+                let k = HeapObject::Func(Func::Continuation(self.capture(eval))).heap_alloc(self)?;
+                eval.op_stack.push(QueueOp::Deferred(Deferred::Apply(func, vec![k]), span));
+            }
+            QueueOp::Deferred(Deferred::Guard(guard, thunk), span) => {
+                let state = DynamicState::capture(self);
+                self.handlers.write().unwrap().push(Handler::Guard);
+                op_stack.push(QueueOp::Guard(state, guard, span));
+                op_stack.push(QueueOp::Deferred(Deferred::Apply(thunk, Vec::new()), span));
+            }
+            QueueOp::Catch(state, _, _) | QueueOp::Guard(state, _, _) => {
+                self.handlers.write().unwrap().truncate(state.handlers);
+            }
+            QueueOp::EnsureResult(height) => {
+                if self.stack.data.read().unwrap().len() <= height {
+                    self.stack.push_data(StackObject::Ref(ObjectPointer::Null));
+                }
+            }
+            QueueOp::CountedParams(_)
+            | QueueOp::CaseParams(..)
+            | QueueOp::MacroParams(_)
+            | QueueOp::MacroValues(..)
+            | QueueOp::NamedParams(_)
+            | QueueOp::Hold(_) => (),
+        }
+        Ok(())
+    }
+
+    /// Runs `f`, queueing any work it deferred to run straight after it
+    fn with_deferred<'a, T>(
+        &self,
+        span: Span,
+        op_stack: &mut Vec<QueueOp<'a>>,
+        f: impl FnOnce() -> InterpreterResult<T>,
+    ) -> InterpreterResult<T> {
+        let mark = self.deferred.read().unwrap().len();
+        let result = f().map_not_spanned(span);
+        let deferred = self.deferred.write().unwrap().split_off(mark);
+        let out = result?;
+        op_stack.extend(deferred.into_iter().rev().map(|d| QueueOp::Deferred(d, span)));
+        Ok(out)
+    }
+
+    /// Calls a macro with its form and the `n` values it has asked for so far, which are
+    /// on top of the data stack, and queues the evaluation of what it asks for next
+    fn expand_macro<'a>(
+        &self,
+        macro_f: MacroFunc,
+        func_hash: u64,
+        span: Span,
+        params: Vec<&'a AST>,
+        n: usize,
+        op_stack: &mut Vec<QueueOp<'a>>,
+    ) -> InterpreterResult<()> {
+        let values = {
+            let data = self.stack.data.read().unwrap();
+            data[data.len() - n..].to_vec()
+        };
+        match macro_f(self, params.clone(), values).map_not_spanned(span)? {
+            MacroStep::Expand(ast) => {
+                for _ in 0..n {
+                    self.stack.pop_data()?;
+                }
+                op_stack.push(QueueOp::Eval(ast));
+            }
+            MacroStep::Evaluate(exprs) => {
+                let height = self.stack.data.read().unwrap().len();
+                op_stack.push(QueueOp::MacroValues(params, n + exprs.len()));
+                op_stack.push(QueueOp::ApplyFunc(func_hash, span));
+                for (i, expr) in exprs.into_iter().enumerate().rev() {
+                    op_stack.push(QueueOp::EnsureResult(height + i));
+                    op_stack.push(QueueOp::Eval(expr));
                 }
-                QueueOp::CountedParams(_) | QueueOp::MacroParams(_) | QueueOp::NamedParams(_) => (),
             }
         }
         Ok(())
     }
 
+    /// Discards queued work up to the innermost active exception handler and calls it
+    /// with the condition, returning the error if there is none. A continuation being
+    /// invoked is never caught, and replaces the work of the evaluation it targets
+    fn unwind<'a>(&self, err: InterpreterError, eval: &mut Evaluation<'a>) -> InterpreterResult<()> {
+        if let InterpreterErrorKind::Resume {
+            evaluation,
+            continuation,
+            values,
+        } = &err.kind
+        {
+            if *evaluation == eval.id {
+                let span = err.span.unwrap_or(Span::zero(0));
+                self.resume(continuation, values.clone(), span, eval);
+                return Ok(());
+            }
+        }
+        let catchable = err.kind.is_catchable();
+
+        while let Some(op) = eval.op_stack.pop() {
+            match op {
+                QueueOp::PopRefStack => {
+                    eval.ref_stack.pop();
+                }
+                QueueOp::Catch(state, handler, span)
+                    if catchable && state.is_handling(self, &handler) =>
+                {
+                    let afters = state.restore(self);
+                    let condition = exception::condition_of(self, &err)?;
+                    let returned = condition.clone();
+                    eval.op_stack.push(QueueOp::Deferred(
+                        Deferred::then(move |interpreter| {
+                            interpreter.stack.pop_data()?;
+                            Err(exception::handler_returned(interpreter, &returned))
+                        }),
+                        span,
+                    ));
+                    eval.op_stack.push(QueueOp::Deferred(
+                        Deferred::Apply(handler, vec![condition]),
+                        span,
+                    ));
+                    Self::queue_afters(afters, span, &mut eval.op_stack);
+                    return Ok(());
+                }
+                // The clauses run in the dynamic environment of the `guard`, with the
+                // condition bound in a frame of their own
+                QueueOp::Guard(state, guard, span) if catchable && state.is_guarding(self) => {
+                    let afters = state.restore(self);
+                    let condition = exception::condition_of(self, &err)?;
+                    let mut frame = Frame::named(state.frames, "guard");
+                    frame.insert_local(&guard.ident, condition);
+
+                    eval.op_stack.push(QueueOp::PopFrame);
+                    eval.op_stack.push(QueueOp::Deferred(
+                        Deferred::then(move |interpreter| {
+                            std_lib::exception::guard_clauses(interpreter, &guard, 0, &err)
+                        }),
+                        span,
+                    ));
+                    eval.op_stack.push(QueueOp::PushFrame(frame));
+                    Self::queue_afters(afters, span, &mut eval.op_stack);
+                    return Ok(());
+                }
+                _ => (),
+            }
+        }
+        Err(err)
+    }
+
+    /// Queues the after thunks of the `dynamic-wind`s an exception left, innermost first
+    fn queue_afters(afters: Vec<ObjectPointer>, span: Span, op_stack: &mut Vec<QueueOp>) {
+        for after in afters.into_iter().rev() {
+            op_stack.push(QueueOp::Deferred(Deferred::discard(), span));
+            op_stack.push(QueueOp::Deferred(Deferred::Apply(after, Vec::new()), span));
+        }
+    }
+
+    /// Snapshots the rest of the evaluation, as it will carry on once the
+    /// currently running native function returns
+    fn capture(&self, eval: &Evaluation) -> Continuation {
+        let mut roots = Vec::new();
+        let op_stack = eval
+            .op_stack
+            .iter()
+            .map(|op| op.detach(&mut roots))
+            .collect::<Vec<_>>();
+        let func_cache = op_stack
+            .iter()
+            .filter_map(|op| match op {
+                QueueOp::ApplyFunc(hash, _) => Some((*hash, eval.func_cache[hash].clone())),
+                _ => None,
+            })
+            .collect();
+
+        Continuation(Arc::new(Snapshot {
+            evaluation: eval.id,
+            outermost: self.evaluations.read().unwrap().first() == Some(&eval.id),
+            base: eval.base,
+            op_stack,
+            func_cache,
+            data: self.stack.data.read().unwrap()[eval.base.data..].to_vec(),
+            frames: self.stack.frame.read().unwrap()[eval.base.frames..].to_vec(),
+            handlers: self.handlers.read().unwrap().clone(),
+            winders: self.winders.read().unwrap().clone(),
+            parameters: self.parameters.read().unwrap().clone(),
+            roots,
+        }))
+    }
+
+    /// Builds the error which carries `values` to a continuation. If the evaluation it
+    /// was captured in has finished, a top level one has its work resumed by the current
+    /// top level evaluation instead. A nested one returned its result to a native which
+    /// has since finished, so cannot be resumed at all
+    fn invoke(&self, k: Continuation, values: Vec<ObjectPointer>, span: Span) -> InterpreterError {
+        let evaluations = self.evaluations.read().unwrap();
+        let snapshot = k.snapshot();
+        let evaluation = if evaluations.contains(&snapshot.evaluation) {
+            snapshot.evaluation
+        } else if snapshot.outermost {
+            evaluations[0]
+        } else {
+            return InterpreterError::spanned(InterpreterErrorKind::ContinuationExpired, span);
+        };
+        InterpreterError::spanned(
+            InterpreterErrorKind::Resume {
+                evaluation,
+                continuation: k,
+                values,
+            },
+            span,
+        )
+    }
+
+    /// Replaces the work of `eval` with that of the continuation, queueing the
+    /// `dynamic-wind` thunks between the two dynamic extents to run first
+    fn resume<'a>(
+        &self,
+        k: &Continuation,
+        values: Vec<ObjectPointer>,
+        span: Span,
+        eval: &mut Evaluation<'a>,
+    ) {
+        let snapshot = k.snapshot();
+        let (from, to) = (snapshot.base, eval.base);
+
+        eval.op_stack = std::iter::once(QueueOp::Hold(k.clone()))
+            .chain(snapshot.op_stack.iter().map(|op| {
+                let mut op: QueueOp<'a> = op.clone();
+                op.rebase(&from, &to);
+                op
+            }))
+            .collect();
+        eval.func_cache.extend(snapshot.func_cache.clone());
+
+        {
+            let mut data = self.stack.data.write().unwrap();
+            data.truncate(to.data);
+            data.extend(snapshot.data.iter().cloned());
+        }
+        {
+            let mut frames = self.stack.frame.write().unwrap();
+            frames.truncate(to.frames);
+            frames.extend(snapshot.frames.iter().cloned().map(|mut frame| {
+                frame.stack_index = from.rebase_frames(&to, frame.stack_index);
+                frame
+            }));
+        }
+        *self.handlers.write().unwrap() = snapshot.handlers.clone();
+        *self.parameters.write().unwrap() = snapshot.parameters.clone();
+
+        let current = self.winders.read().unwrap().clone();
+        let common = current
+            .iter()
+            .zip(&snapshot.winders)
+            .take_while(|(l, r)| Arc::ptr_eq(l, r))
+            .count();
+
+        let mut steps = Vec::new();
+        for i in (common..current.len()).rev() {
+            let outer = current[..i].to_vec();
+            steps.push(Deferred::then(move |interpreter| {
+                *interpreter.winders.write().unwrap() = outer.clone();
+                Ok(())
+            }));
+            steps.push(Deferred::Apply(current[i].after.clone(), Vec::new()));
+            steps.push(Deferred::discard());
+        }
+        for i in common..snapshot.winders.len() {
+            let inner = snapshot.winders[..=i].to_vec();
+            steps.push(Deferred::Apply(snapshot.winders[i].before.clone(), Vec::new()));
+            steps.push(Deferred::then(move |interpreter| {
+                interpreter.stack.pop_data()?;
+                *interpreter.winders.write().unwrap() = inner.clone();
+                Ok(())
+            }));
+        }
+        steps.push(Deferred::then(move |interpreter| {
+            std_lib::values::push_values(interpreter, values.clone())
+        }));
+
+        eval.op_stack
+            .extend(steps.into_iter().rev().map(|d| QueueOp::Deferred(d, span)));
+    }
+
+    /// Queues work to run once the current native function returns
+    pub fn defer(&self, op: Deferred) {
+        self.deferred.write().unwrap().push(op);
+    }
+
     pub fn resolve_identifier(&self, ident: &str, span: Span) -> InterpreterResult<ObjectPointer> {
         if let Some(ptr) = self
             .stack
@@ -328,6 +1150,12 @@ impl InterpreterContext {
             return Ok(ptr);
         }
 
+        if let Some(library) = self.library_at(&span) {
+            if let Some(ptr) = library.bindings.read().unwrap().get(ident) {
+                return Ok(ptr.clone());
+            }
+        }
+
         if let Some(ptr) = self.ident_mapping.read().unwrap().get(ident) {
             return Ok(ptr.clone());
         }
@@ -337,4 +1165,23 @@ impl InterpreterContext {
             span,
         ))
     }
+
+    /// The innermost library whose form contains `span`
+    pub fn library_at(&self, span: &Span) -> Option<Arc<Library>> {
+        self.libraries
+            .read()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|library| library.contains(span))
+            .cloned()
+    }
+
+    /// Binds a top level name written at `span`, in its library if it is inside one
+    pub fn define_global(&self, ident: &str, span: Span, ptr: ObjectPointer) {
+        match self.library_at(&span) {
+            Some(library) => library.bindings.write().unwrap().insert(ident.to_string(), ptr),
+            None => self.ident_mapping.write().unwrap().insert(ident.to_string(), ptr),
+        };
+    }
 }
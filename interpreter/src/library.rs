@@ -0,0 +1,128 @@
+use core::token::span::Span;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use crate::{object::ObjectPointer, InterpreterContext};
+
+/// A library made by `define-library`. Identifiers written inside its form resolve
+/// in its own bindings before the globals, and only its exports can be imported
+#[derive(Debug)]
+pub struct Library {
+    pub name: Vec<String>,
+    pub span: Span,
+    pub bindings: RwLock<HashMap<String, ObjectPointer>>,
+    /// Pairs of the internal name and the name it is exported as
+    pub exports: RwLock<Vec<(String, String)>>,
+}
+
+impl Library {
+    pub fn new(name: Vec<String>, span: Span) -> Arc<Self> {
+        Arc::new(Self {
+            name,
+            span,
+            bindings: RwLock::new(HashMap::new()),
+            exports: RwLock::new(Vec::new()),
+        })
+    }
+
+    /// Whether `span` lies within the library's form
+    pub fn contains(&self, span: &Span) -> bool {
+        self.span.file_id == span.file_id && self.span.start <= span.start && span.end <= self.span.end
+    }
+}
+
+/// Formats a library name as it is written, as in `(scheme base)`
+pub fn library_name(name: &[String]) -> String {
+    format!("({})", name.join(" "))
+}
+
+/// The standard libraries, each exporting those of its names the interpreter defines
+const BUILTIN_LIBRARIES: &[(&[&str], &[&str])] = &[
+    (
+        &["scheme", "base"],
+        &[
+            "define", "lambda", "if", "let", "import", "define-library", "car", "cdr",
+            "cons", "list", "list?", "pair?", "null?", "length", "append", "reverse",
+            "list-tail", "list-ref", "list-copy", "memq", "memv", "member", "assq", "assv",
+            "assoc", "caar", "cadr", "cdar", "cddr", "apply", "map", "for-each", "+", "-",
+            "*", "/", "=", "<", "<=", ">", ">=", "eq?", "eqv?", "equal?", "number?",
+            "integer?", "string?", "boolean?", "char?", "procedure?", "string=?",
+            "string<?", "string>?", "string<=?", "string>=?", "char=?", "char<?", "char>?",
+            "char<=?", "char>=?", "error", "raise", "raise-continuable",
+            "with-exception-handler", "guard", "error-object?", "error-object-message",
+            "error-object-irritants", "call/cc", "call-with-current-continuation",
+            "dynamic-wind", "values", "call-with-values", "let-values", "let*-values",
+            "define-values", "floor/", "truncate/", "exact-integer-sqrt", "make-parameter",
+            "parameterize", "do", "not", "boolean=?", "zero?", "positive?", "negative?",
+            "quotient", "remainder", "modulo", "even?", "odd?", "abs", "square", "gcd",
+            "lcm", "max", "min", "make-list",
+        ],
+    ),
+    (
+        &["scheme", "cxr"],
+        &["caaar", "caadr", "cadar", "caddr", "cdaar", "cdadr", "cddar", "cdddr"],
+    ),
+    (
+        &["scheme", "char"],
+        &[
+            "string-ci=?", "string-ci<?", "string-ci>?", "string-ci<=?", "string-ci>=?",
+            "char-ci=?", "char-ci<?", "char-ci>?", "char-ci<=?", "char-ci>=?",
+        ],
+    ),
+    (
+        &["scheme", "lazy"],
+        &["delay", "delay-force", "force", "make-promise", "promise?"],
+    ),
+    (&["scheme", "case-lambda"], &["case-lambda"]),
+    (&["scheme", "write"], &["write"]),
+    (
+        &["scheme", "process-context"],
+        &[
+            "command-line", "exit", "emergency-exit", "get-environment-variable",
+            "get-environment-variables",
+        ],
+    ),
+    (
+        &["scheme", "time"],
+        &["current-second", "current-jiffy", "jiffies-per-second"],
+    ),
+    (
+        &["srfi", "1"],
+        &[
+            "filter", "remove", "partition", "fold", "fold-left", "fold-right", "reduce",
+            "find", "any", "every", "count", "delete", "iota", "list-index", "last-pair",
+            "first", "second", "third", "last", "take", "drop", "list-tabulate",
+            "append-map", "filter-map", "delete-duplicates",
+        ],
+    ),
+    (
+        &["srfi", "41"],
+        &[
+            "stream-cons", "stream-null", "stream-null?", "stream-pair?", "stream-car",
+            "stream-cdr", "stream-take", "stream-map", "stream-filter", "list->stream",
+            "stream->list",
+        ],
+    ),
+    (&["srfi", "132"], &["sort", "list-sort"]),
+];
+
+/// Defines the standard libraries over the globals, so that `(import (scheme base))`
+/// and the like bind the built in procedures
+pub fn register_builtin_libraries(interpreter: &InterpreterContext) {
+    let globals = interpreter.ident_mapping.read().unwrap();
+    let mut libraries = interpreter.libraries.write().unwrap();
+    for (name, exports) in BUILTIN_LIBRARIES {
+        let library = Library::new(
+            name.iter().map(|s| s.to_string()).collect(),
+            Span::zero(usize::MAX),
+        );
+        *library.exports.write().unwrap() = exports
+            .iter()
+            .filter(|export| globals.contains_key(**export))
+            .map(|export| (export.to_string(), export.to_string()))
+            .collect();
+        libraries.push(library);
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult};
+
+/// Bounds on a single top level evaluation, `None` leaving that resource unbounded
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    /// Operations the evaluator may run
+    pub max_steps: Option<u64>,
+    /// Heap objects live at once, beyond those already live when the evaluation starts
+    pub max_heap_objects: Option<usize>,
+    /// Stack frames and nested evaluations, each call pushing a frame and each native
+    /// which evaluates code itself, such as `eval`, starting an evaluation
+    pub max_depth: Option<usize>,
+    /// Wall clock time
+    pub timeout: Option<Duration>,
+}
+
+/// The limit an evaluation ran into
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Steps(u64),
+    HeapObjects(usize),
+    Depth(usize),
+    Time(Duration),
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Steps(n) => write!(f, "{n} steps"),
+            Limit::HeapObjects(n) => write!(f, "{n} heap objects"),
+            Limit::Depth(n) => write!(f, "a depth of {n} frames"),
+            Limit::Time(t) => write!(f, "{t:?}"),
+        }
+    }
+}
+
+/// Interrupts whatever the interpreter it came from is evaluating, from any thread. A
+/// cancel made while nothing is evaluating stops the next evaluation instead
+#[derive(Debug, Clone)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// What the running top level evaluation has used so far
+#[derive(Debug, Default)]
+pub struct Budget {
+    steps: AtomicU64,
+    /// Live heap objects when the evaluation started
+    heap_base: AtomicUsize,
+    deadline: Mutex<Option<Instant>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+fn exceeded(limit: Limit) -> InterpreterError {
+    InterpreterError::new(InterpreterErrorKind::LimitExceeded(limit))
+}
+
+impl InterpreterContext {
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle(self.budget.cancelled.clone())
+    }
+
+    /// Starts the budget of a top level evaluation
+    pub(crate) fn start_budget(&self) {
+        self.budget.steps.store(0, Ordering::Relaxed);
+        // Garbage left by earlier evaluations would otherwise count towards the base
+        if self.limits.max_heap_objects.is_some() {
+            self.heap.collect();
+            self.budget
+                .heap_base
+                .store(self.heap.live_objects(), Ordering::Relaxed);
+        }
+        *self.budget.deadline.lock().unwrap() =
+            self.limits.timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    /// Ends the budget of a top level evaluation, so a cancel it was stopped by does not
+    /// stop the next one too
+    pub(crate) fn finish_budget(&self) {
+        self.budget.cancelled.store(false, Ordering::Relaxed);
+    }
+
+    /// Counts one evaluation step against the limits
+    pub(crate) fn charge_step(&self) -> InterpreterResult<()> {
+        if self.budget.cancelled.load(Ordering::Relaxed) {
+            return Err(InterpreterError::new(InterpreterErrorKind::Cancelled));
+        }
+
+        let Limits {
+            max_steps,
+            max_depth,
+            timeout,
+            ..
+        } = self.limits;
+        if let Some(max) = max_steps {
+            if self.budget.steps.fetch_add(1, Ordering::Relaxed) >= max {
+                return Err(exceeded(Limit::Steps(max)));
+            }
+        }
+        if let Some(max) = max_depth {
+            let depth = self.stack.frame.read().unwrap().len()
+                + self.evaluations.read().unwrap().len();
+            if depth > max {
+                return Err(exceeded(Limit::Depth(max)));
+            }
+        }
+        if let Some(timeout) = timeout {
+            if self.budget.deadline.lock().unwrap().is_some_and(|d| Instant::now() >= d) {
+                return Err(exceeded(Limit::Time(timeout)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks there is room for another heap object, collecting garbage first if the
+    /// heap is full
+    pub(crate) fn reserve_heap_object(&self) -> InterpreterResult<()> {
+        let Some(max) = self.limits.max_heap_objects else {
+            return Ok(());
+        };
+        let limit = self.budget.heap_base.load(Ordering::Relaxed) + max;
+        if self.heap.live_objects() >= limit {
+            self.heap.collect();
+            if self.heap.live_objects() >= limit {
+                return Err(exceeded(Limit::HeapObjects(max)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{Limit, Limits};
+    use crate::{embed::Error, testing::builder, Interpreter, InterpreterErrorKind};
+
+    fn interpreter(limits: Limits) -> Interpreter {
+        Interpreter::from_context(builder().limits(limits).build())
+    }
+
+    fn error_kind(result: crate::embed::Result<crate::Value>) -> InterpreterErrorKind {
+        match result {
+            Err(Error::Eval(err)) => err.kind,
+            other => panic!("expected an evaluation error, got {other:?}"),
+        }
+    }
+
+    const LOOP: &str = "(define (loop) (loop)) (loop)";
+
+    #[test]
+    fn steps_are_limited() {
+        let interpreter = interpreter(Limits {
+            max_steps: Some(1000),
+            ..Limits::default()
+        });
+        assert_eq!(
+            error_kind(interpreter.eval_str(LOOP)),
+            InterpreterErrorKind::LimitExceeded(Limit::Steps(1000))
+        );
+        // Each evaluation starts with a fresh budget
+        assert_eq!(interpreter.eval_str("(+ 1 2)").unwrap().get::<i64>(), Some(3));
+    }
+
+    #[test]
+    fn heap_growth_is_limited() {
+        let interpreter = interpreter(Limits {
+            max_heap_objects: Some(50),
+            ..Limits::default()
+        });
+        assert!(interpreter.eval_str("(list 1 2)").is_ok());
+        assert_eq!(
+            error_kind(interpreter.eval_str("(iota 1000)")),
+            InterpreterErrorKind::LimitExceeded(Limit::HeapObjects(50))
+        );
+        assert!(interpreter.eval_str("(length (iota 20))").is_ok());
+    }
+
+    #[test]
+    fn depth_is_limited() {
+        let interpreter = interpreter(Limits {
+            max_depth: Some(50),
+            ..Limits::default()
+        });
+        let deep = "(define (deep n) (if (= n 0) 0 (+ 1 (deep (- n 1)))))";
+        assert!(interpreter.eval_str(&format!("{deep} (deep 10)")).is_ok());
+        assert_eq!(
+            error_kind(interpreter.eval_str("(deep 1000)")),
+            InterpreterErrorKind::LimitExceeded(Limit::Depth(50))
+        );
+        assert_eq!(interpreter.eval_str("(deep 10)").unwrap().get::<i64>(), Some(10));
+    }
+
+    /// Evaluates `source` on a stack small enough that recursing through the Rust stack
+    /// for each level would overflow it
+    fn eval_deep(limits: Limits, source: &str) -> crate::embed::Result<crate::Value> {
+        let source = source.to_string();
+        std::thread::Builder::new()
+            .stack_size(512 * 1024)
+            .spawn(move || interpreter(limits).eval_str(&source))
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    #[test]
+    fn conditions_and_bindings_do_not_escape_the_depth_limit() {
+        let limits = Limits {
+            max_depth: Some(200),
+            ..Limits::default()
+        };
+        // A procedure calling itself reuses its frame, so these run to the end without
+        // growing the Rust stack
+        let nested_if = "(define (f n) (if (if (= n 0) #t (f (- n 1))) 1 0)) (f 100000)";
+        assert_eq!(eval_deep(limits, nested_if).unwrap().get::<i64>(), Some(1));
+        let nested_let = "(define (f n) (let ((x (if (= n 0) 0 (f (- n 1))))) x)) (f 20000)";
+        assert_eq!(eval_deep(limits, nested_let).unwrap().get::<i64>(), Some(0));
+
+        let deep = "(define (f n) (if (if (= n 0) #t (+ 1 (f (- n 1)))) 1 0)) (f 100000)";
+        assert_eq!(
+            error_kind(eval_deep(limits, deep)),
+            InterpreterErrorKind::LimitExceeded(Limit::Depth(200))
+        );
+    }
+
+    #[test]
+    fn time_is_limited() {
+        let timeout = Duration::from_millis(50);
+        let interpreter = interpreter(Limits {
+            timeout: Some(timeout),
+            ..Limits::default()
+        });
+        assert_eq!(
+            error_kind(interpreter.eval_str(LOOP)),
+            InterpreterErrorKind::LimitExceeded(Limit::Time(timeout))
+        );
+    }
+
+    #[test]
+    fn limits_cannot_be_caught() {
+        let interpreter = interpreter(Limits {
+            max_steps: Some(1000),
+            ..Limits::default()
+        });
+        assert_eq!(
+            error_kind(interpreter.eval_str(&format!("(guard (e (#t 0)) {LOOP})"))),
+            InterpreterErrorKind::LimitExceeded(Limit::Steps(1000))
+        );
+    }
+
+    #[test]
+    fn cancel_stops_a_running_evaluation() {
+        let interpreter = interpreter(Limits::default());
+        let cancel = interpreter.context().cancel_handle();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            cancel.cancel();
+        });
+        assert_eq!(error_kind(interpreter.eval_str(LOOP)), InterpreterErrorKind::Cancelled);
+        canceller.join().unwrap();
+
+        // The interpreter recovers, and the cancel does not carry over
+        assert_eq!(interpreter.eval_str("(+ 1 2)").unwrap().get::<i64>(), Some(3));
+    }
+
+    #[test]
+    fn cancel_before_evaluating_stops_the_next_evaluation() {
+        let interpreter = interpreter(Limits::default());
+        interpreter.context().cancel_handle().cancel();
+        assert_eq!(
+            error_kind(interpreter.eval_str("(+ 1 2)")),
+            InterpreterErrorKind::Cancelled
+        );
+        assert!(interpreter.eval_str("(+ 1 2)").is_ok());
+    }
+}
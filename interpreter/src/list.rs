@@ -0,0 +1,78 @@
+use std::ops::Deref;
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    deref::InterpreterDeref,
+    error::{InterpreterError, InterpreterErrorKind},
+    object::{HeapObject, ObjectPointer, ObjectRef},
+    InterpreterContext, InterpreterResult,
+};
+
+pub trait InterpreterList {
+    /// Follows the chain of pairs, returning each pair with its head and the final tail
+    fn list_spine(
+        &self,
+        interpreter: &InterpreterContext,
+    ) -> InterpreterResult<(Vec<(ObjectPointer, ObjectPointer)>, ObjectPointer)>;
+
+    /// Collects the elements of a proper list
+    fn list_to_vec(&self, interpreter: &InterpreterContext) -> InterpreterResult<Vec<ObjectPointer>> {
+        let (spine, tail) = self.list_spine(interpreter)?;
+        if tail != ObjectPointer::Null {
+            return Err(InterpreterError::new(InterpreterErrorKind::ExpectedList));
+        }
+        Ok(spine.into_iter().map(|(_, head)| head).collect())
+    }
+}
+
+impl InterpreterList for ObjectPointer {
+    fn list_spine(
+        &self,
+        interpreter: &InterpreterContext,
+    ) -> InterpreterResult<(Vec<(ObjectPointer, ObjectPointer)>, ObjectPointer)> {
+        let mut spine = Vec::new();
+        let mut cur = self.clone();
+        loop {
+            let next = match cur.deref(interpreter)? {
+                ObjectRef::Object(o) => match o.deref() {
+                    HeapObject::List(h, t) => {
+                        spine.push((cur.clone(), h.clone()));
+                        t.clone()
+                    }
+                    _ => return Ok((spine, cur)),
+                },
+                _ => return Ok((spine, cur)),
+            };
+            cur = next;
+        }
+    }
+}
+
+pub trait InterpreterListAlloc {
+    /// Allocates a list holding each of the elements, ending in `tail`
+    fn to_list_with_tail(
+        self,
+        tail: ObjectPointer,
+        interpreter: &InterpreterContext,
+    ) -> InterpreterResult<ObjectPointer>;
+
+    /// Allocates a proper list holding each of the elements
+    fn to_list(self, interpreter: &InterpreterContext) -> InterpreterResult<ObjectPointer>
+    where
+        Self: Sized,
+    {
+        self.to_list_with_tail(ObjectPointer::Null, interpreter)
+    }
+}
+
+impl InterpreterListAlloc for Vec<ObjectPointer> {
+    fn to_list_with_tail(
+        self,
+        tail: ObjectPointer,
+        interpreter: &InterpreterContext,
+    ) -> InterpreterResult<ObjectPointer> {
+        self.into_iter()
+            .rev()
+            .try_fold(tail, |tail, head| HeapObject::List(head, tail).heap_alloc(interpreter))
+    }
+}
@@ -1,28 +1,189 @@
-use std::{fs::File, io::Read};
+use std::{io::Read, path::PathBuf, process::ExitCode};
 
-use anyhow::Result;
-use core::{
-    error::ErrorWriter,
-    LexerParser,
-};
-use interpreter::InterpreterContext;
+use core::{error::ErrorWriter, lexer::Lexer, LexerParser};
+use interpreter::InterpreterBuilder;
 
-pub fn main() -> Result<()> {
-    let file_names = std::env::args().skip(1).collect::<Vec<_>>();
-    if file_names.is_empty() {
-        println!("Error: File not specified");
-        return Ok(());
+const USAGE: &str = "\
+Usage: interpreter [options] [file | -]... [-- arg...]
+       interpreter [options] -s script [arg...]
+
+Evaluates each file, `-` reading standard input, in order
+
+Options:
+  -e expr     Evaluate expr, in order with the files
+  -s script   Evaluate script, passing every argument after it to (command-line)
+  -I dir      Search dir for imported libraries and files
+  --tokens    Print the tokens of each program with their spans instead of evaluating
+  --ast       Print the syntax tree of each program instead of evaluating
+  --check     Only report syntax errors, without evaluating
+  --          Pass every argument after it to (command-line)
+  -h, --help  Print this message";
+
+/// Exit code of a program which did not parse, as for one which raised an error
+const FAILURE: u8 = 1;
+/// Exit code of bad arguments or an input which could not be read
+const USAGE_ERROR: u8 = 2;
+
+/// A program to evaluate
+enum Source {
+    Expr(String),
+    Stdin,
+    File(String),
+}
+
+/// What is done with the programs once they are read
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    #[default]
+    Run,
+    Tokens,
+    Ast,
+    Check,
+}
+
+#[derive(Default)]
+struct Options {
+    mode: Mode,
+    search_path: Vec<String>,
+    sources: Vec<Source>,
+    /// The name `(command-line)` gives the script, given with `-s`
+    script: Option<String>,
+    /// Arguments to the script
+    args: Vec<String>,
+    help: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => options.help = true,
+            "--tokens" => options.mode = Mode::Tokens,
+            "--ast" => options.mode = Mode::Ast,
+            "--check" => options.mode = Mode::Check,
+            "-e" => {
+                let expr = args.next().ok_or("-e expects an expression")?;
+                options.sources.push(Source::Expr(expr));
+            }
+            "-s" => {
+                let script = args.next().ok_or("-s expects a script")?;
+                options.sources.push(Source::File(script.clone()));
+                options.script = Some(script);
+                options.args.extend(args.by_ref());
+            }
+            "--" => options.args.extend(args.by_ref()),
+            "-" => options.sources.push(Source::Stdin),
+            "-I" => options
+                .search_path
+                .push(args.next().ok_or("-I expects a directory")?),
+            _ => match arg.strip_prefix("-I") {
+                Some(dir) => options.search_path.push(dir.to_string()),
+                None if arg.starts_with('-') => return Err(format!("Unknown option '{arg}'")),
+                None => options.sources.push(Source::File(arg)),
+            },
+        }
     }
+    Ok(options)
+}
 
-    let mut file = File::open(&file_names[0])?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
+/// Loads the source into `error_writer`, returning its file id and contents
+fn load(error_writer: &mut ErrorWriter, source: &Source) -> Result<(usize, String), String> {
+    match source {
+        Source::Expr(expr) => Ok((error_writer.load_string(expr.clone()), expr.clone())),
+        Source::Stdin => {
+            let mut contents = String::new();
+            std::io::stdin()
+                .read_to_string(&mut contents)
+                .map_err(|err| format!("Cannot read standard input: {err}"))?;
+            Ok((error_writer.add_file("<stdin>".into(), contents.clone()), contents))
+        }
+        Source::File(name) => {
+            let contents = std::fs::read_to_string(name)
+                .map_err(|err| format!("Cannot read '{name}': {err}"))?;
+            Ok((error_writer.add_file(PathBuf::from(name), contents.clone()), contents))
+        }
+    }
+}
+
+pub fn main() -> ExitCode {
+    let mut args = std::env::args();
+    let program = args.next().unwrap_or_else(|| "interpreter".to_string());
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("Error: {err}\n\n{USAGE}");
+            return ExitCode::from(USAGE_ERROR);
+        }
+    };
+    if options.help {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    if options.sources.is_empty() {
+        eprintln!("Error: No file or expression given\n\n{USAGE}");
+        return ExitCode::from(USAGE_ERROR);
+    }
 
-    let error_writer = ErrorWriter::new((&file_names[0]).into(), contents.clone());
-    let Ok(ast) = LexerParser::from_string(0, contents, &error_writer) else { return Ok(()) };
+    // Everything is parsed before anything runs, so a syntax error anywhere stops the
+    // program before it has any effect
+    let mut error_writer = ErrorWriter::empty();
+    let mut programs = Vec::new();
+    let mut parsed = true;
+    for source in &options.sources {
+        let (file_id, contents) = match load(&mut error_writer, source) {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                eprintln!("Error: {err}");
+                return ExitCode::from(USAGE_ERROR);
+            }
+        };
+        if options.mode == Mode::Tokens {
+            let result = Lexer::new(file_id, &contents).lex();
+            for token in &result.tokens {
+                println!("{} {:?}", token.span, token.kind);
+            }
+            parsed &= error_writer.report_errors(result.errors).is_ok();
+            continue;
+        }
+        match LexerParser::from_string(file_id, contents, &error_writer) {
+            Ok(ast) => {
+                if options.mode == Mode::Ast {
+                    ast.iter().for_each(|node| print!("{}", node.tree()));
+                }
+                programs.push(ast);
+            }
+            Err(()) => parsed = false,
+        }
+    }
+    if !parsed {
+        return ExitCode::from(FAILURE);
+    }
+    if options.mode != Mode::Run {
+        return ExitCode::SUCCESS;
+    }
 
-    let context = InterpreterContext::new(error_writer);
-    context.start(ast);
+    let script = options.script.clone().or_else(|| {
+        options.sources.iter().find_map(|source| match source {
+            Source::File(name) => Some(name.clone()),
+            Source::Stdin => Some("-".to_string()),
+            Source::Expr(_) => None,
+        })
+    });
+    let command_line = std::iter::once(script.unwrap_or(program)).chain(options.args);
 
-    Ok(())
+    let context = options
+        .search_path
+        .into_iter()
+        .fold(InterpreterBuilder::new().error_writer(error_writer), |builder, dir| {
+            builder.search_path(dir)
+        })
+        .command_line(command_line)
+        .build();
+    for ast in programs {
+        if let Err(status) = context.start(ast) {
+            // Statuses outside a byte are truncated, as the shell would
+            return ExitCode::from(status as u8);
+        }
+    }
+    ExitCode::SUCCESS
 }
@@ -6,7 +6,7 @@ use std::{
 
 use core::literal::Literal;
 
-use crate::func::Func;
+use crate::{func::Func, promise::Promise};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum StackObject {
@@ -47,6 +47,17 @@ pub enum HeapObject {
     String(String),
     List(ObjectPointer, ObjectPointer),
     Func(Func),
+    Error(ErrorObject),
+    /// Zero or several results returned by `values`, a single value is never wrapped
+    Values(Vec<ObjectPointer>),
+    Promise(Promise),
+}
+
+/// A condition raised by `error` or by a failing native function
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorObject {
+    pub message: String,
+    pub irritants: ObjectPointer,
 }
 
 impl std::fmt::Display for HeapObject {
@@ -56,6 +67,9 @@ impl std::fmt::Display for HeapObject {
             HeapObject::String(s) => write!(f, "\"{s}\""),
             HeapObject::List(h, t) => write!(f, "{h}:{t}"),
             HeapObject::Func(_fn) => write!(f, "{_fn}"),
+            HeapObject::Error(e) => write!(f, "{}: {}", e.message, e.irritants),
+            HeapObject::Values(v) => write!(f, "{v:?}"),
+            HeapObject::Promise(p) => write!(f, "{p}"),
         }
     }
 }
@@ -68,6 +82,23 @@ pub enum ObjectRef<'a> {
 }
 
 impl ObjectRef<'_> {
+    /// The literal held by the object, whether on the stack or the heap
+    pub fn literal(&self) -> Option<Literal> {
+        match self {
+            ObjectRef::Value(v) => Some(*v),
+            ObjectRef::Object(o) => match o.deref() {
+                HeapObject::Value(v) => Some(*v),
+                _ => None,
+            },
+            ObjectRef::Null => None,
+        }
+    }
+
+    /// Everything but `#f` counts as true
+    pub fn is_truthy(&self) -> bool {
+        self.literal().is_none_or(|v| v.is_truthy())
+    }
+
     pub fn clone_to_unallocated(&self) -> UnallocatedObject {
         match self {
             ObjectRef::Value(v) => UnallocatedObject::Value(*v),
@@ -76,6 +107,9 @@ impl ObjectRef<'_> {
                 HeapObject::String(s) => UnallocatedObject::String(s),
                 HeapObject::List(h, t) => UnallocatedObject::List(h, t), //TODO: Perhaps some copy issues here
                 HeapObject::Func(f) => UnallocatedObject::Func(f),
+                HeapObject::Error(e) => UnallocatedObject::Error(e),
+                HeapObject::Values(v) => UnallocatedObject::Values(v),
+                HeapObject::Promise(p) => UnallocatedObject::Promise(p),
             },
             ObjectRef::Null => UnallocatedObject::Null,
         }
@@ -104,5 +138,8 @@ pub enum UnallocatedObject {
     Func(Func),
     String(String),
     List(ObjectPointer, ObjectPointer),
+    Error(ErrorObject),
+    Values(Vec<ObjectPointer>),
+    Promise(Promise),
     Null,
 }
@@ -0,0 +1,68 @@
+use std::{
+    hash::{Hash, Hasher},
+    sync::{Arc, RwLock},
+};
+
+use crate::{object::ObjectPointer, InterpreterContext};
+
+#[derive(Debug)]
+struct ParameterCell {
+    value: RwLock<ObjectPointer>,
+    converter: Option<ObjectPointer>,
+}
+
+/// A parameter object made by `make-parameter`, compared and hashed by identity.
+/// `parameterize` rebinds it on the interpreter's parameter stack rather than in place
+#[derive(Debug, Clone)]
+pub struct Parameter(Arc<ParameterCell>);
+
+impl Parameter {
+    pub fn new(value: ObjectPointer, converter: Option<ObjectPointer>) -> Self {
+        Self(Arc::new(ParameterCell {
+            value: RwLock::new(value),
+            converter,
+        }))
+    }
+
+    pub fn converter(&self) -> Option<&ObjectPointer> {
+        self.0.converter.as_ref()
+    }
+
+    /// The innermost `parameterize` binding, otherwise the value it was made with
+    pub fn get(&self, interpreter: &InterpreterContext) -> ObjectPointer {
+        interpreter
+            .parameters
+            .read()
+            .unwrap()
+            .iter()
+            .rev()
+            .find_map(|(p, value)| (p == self).then(|| value.clone()))
+            .unwrap_or_else(|| self.0.value.read().unwrap().clone())
+    }
+}
+
+impl PartialEq for Parameter {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Parameter {}
+
+impl PartialOrd for Parameter {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Parameter {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        Arc::as_ptr(&self.0).cmp(&Arc::as_ptr(&other.0))
+    }
+}
+
+impl Hash for Parameter {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).hash(state)
+    }
+}
@@ -0,0 +1,72 @@
+;;; Derived procedures, written in Scheme over the native core and loaded at startup.
+;;; Scoping is dynamic, so parameters start with `%` to keep them from shadowing
+;;; the free variables of procedures passed in.
+
+;; Booleans and numbers
+
+(define (not %x) (if %x #f #t))
+
+(define (boolean=? %a %b) (eq? %a %b))
+
+(define (zero? %n) (= %n 0))
+(define (positive? %n) (> %n 0))
+(define (negative? %n) (< %n 0))
+
+(define (quotient %n %d) (receive (%q %r) (truncate/ %n %d) %q))
+(define (remainder %n %d) (receive (%q %r) (truncate/ %n %d) %r))
+(define (modulo %n %d) (receive (%q %r) (floor/ %n %d) %r))
+
+(define (even? %n) (zero? (remainder %n 2)))
+(define (odd? %n) (not (even? %n)))
+
+(define (abs %n) (if (negative? %n) (- 0 %n) %n))
+(define (square %n) (* %n %n))
+
+(define (gcd %a %b)
+  (if (zero? %b) (abs %a) (gcd %b (remainder %a %b))))
+
+(define (lcm %a %b)
+  (if (zero? %b) 0 (abs (quotient (* %a %b) (gcd %a %b)))))
+
+(define max
+  (case-lambda
+    ((%x) %x)
+    (%all (fold (lambda (%x %acc) (if (> %x %acc) %x %acc)) (car %all) (cdr %all)))))
+
+(define min
+  (case-lambda
+    ((%x) %x)
+    (%all (fold (lambda (%x %acc) (if (< %x %acc) %x %acc)) (car %all) (cdr %all)))))
+
+;; Lists
+
+(define (first %l) (car %l))
+(define (second %l) (cadr %l))
+(define (third %l) (caddr %l))
+(define (last %l) (car (last-pair %l)))
+
+(define (take %l %k)
+  (do ((%rest %l (cdr %rest))
+       (%i %k (- %i 1))
+       (%acc '() (cons (car %rest) %acc)))
+      ((zero? %i) (reverse %acc))))
+
+(define (drop %l %k) (list-tail %l %k))
+
+;; The fill is unspecified by R7RS when left out, #f here
+(define make-list
+  (case-lambda
+    ((%k) (make-list %k #f))
+    ((%k %fill)
+     (do ((%i %k (- %i 1))
+          (%acc '() (cons %fill %acc)))
+         ((zero? %i) %acc)))))
+
+(define (list-tabulate %k %f) (map %f (iota %k)))
+
+(define (append-map %f %l) (apply append (map %f %l)))
+
+(define (filter-map %f %l) (filter (lambda (%x) %x) (map %f %l)))
+
+(define (delete-duplicates %l)
+  (fold-right (lambda (%x %acc) (cons %x (delete %x %acc))) '() %l))
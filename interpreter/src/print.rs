@@ -2,7 +2,8 @@ use core::literal::Numeric;
 
 use crate::{
     frame::Frame,
-    object::{HeapObject, ObjectPointer, ObjectRef, StackObject, UnallocatedObject},
+    list::InterpreterList,
+    object::{ErrorObject, HeapObject, ObjectPointer, ObjectRef, StackObject, UnallocatedObject},
     InterpreterContext,
 };
 
@@ -47,6 +48,9 @@ impl InterpreterPrint for HeapObject {
             HeapObject::List(h, t) => {
                 format!("{}:{}", h.interpreter_fmt(i), t.interpreter_fmt(i))
             }
+            HeapObject::Error(e) => e.interpreter_fmt(i),
+            HeapObject::Values(v) => v.interpreter_fmt(i),
+            HeapObject::Promise(p) => format!("{p}"),
         }
     }
 }
@@ -69,11 +73,34 @@ impl InterpreterPrint for UnallocatedObject {
             UnallocatedObject::String(s) => format!("\"{s}\""),
             UnallocatedObject::List(h, t) =>
                 format!("{}:{}", h.interpreter_fmt(i), t.interpreter_fmt(i)),
+            UnallocatedObject::Error(e) => e.interpreter_fmt(i),
+            UnallocatedObject::Values(v) => v.interpreter_fmt(i),
+            UnallocatedObject::Promise(p) => format!("{p}"),
             UnallocatedObject::Null => format!("()"),
         }
     }
 }
 
+impl InterpreterPrint for ErrorObject {
+    fn interpreter_fmt(&self, i: &InterpreterContext) -> String {
+        match self.irritants.list_to_vec(i) {
+            Ok(irritants) => irritants.iter().fold(self.message.clone(), |out, irritant| {
+                format!("{out} {}", irritant.interpreter_fmt(i))
+            }),
+            Err(_) => format!("{} {}", self.message, self.irritants.interpreter_fmt(i)),
+        }
+    }
+}
+
+impl InterpreterPrint for Vec<ObjectPointer> {
+    fn interpreter_fmt(&self, i: &InterpreterContext) -> String {
+        self.iter()
+            .map(|p| p.interpreter_fmt(i))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
 impl InterpreterPrint for Numeric {
     fn interpreter_fmt(&self, _i: &InterpreterContext) -> String {
         format!("{self}")
@@ -0,0 +1,181 @@
+use std::sync::{Arc, RwLock};
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    deferred::{Continuation, Deferred},
+    deref::InterpreterDeref,
+    frame::Frame,
+    object::{HeapObject, ObjectPointer, ObjectRef, StackObject},
+    InterpreterContext, InterpreterResult,
+};
+
+/// How a delayed promise computes its value
+#[derive(Clone)]
+pub enum Thunk {
+    /// A procedure of no arguments, called with the bindings visible where it was delayed
+    Procedure(ObjectPointer, Frame),
+    /// Native work which pushes a single result
+    Native(Continuation),
+}
+
+#[derive(Clone)]
+pub enum PromiseState {
+    Done(ObjectPointer),
+    /// Not yet forced, when `chained` the thunk returns another promise to force in its place
+    Delayed {
+        thunk: Thunk,
+        chained: bool,
+    },
+}
+
+/// A memoized computation. Forcing a `delay-force` promise makes it share its state with
+/// the promise it returned, so long chains are forced in constant space
+#[derive(Clone)]
+pub struct Promise(Arc<RwLock<Arc<RwLock<PromiseState>>>>);
+
+impl Promise {
+    pub fn new(state: PromiseState) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(RwLock::new(state)))))
+    }
+
+    pub fn done(value: ObjectPointer) -> Self {
+        Self::new(PromiseState::Done(value))
+    }
+
+    pub fn delayed(thunk: Thunk, chained: bool) -> Self {
+        Self::new(PromiseState::Delayed { thunk, chained })
+    }
+
+    pub fn state(&self) -> PromiseState {
+        self.0.read().unwrap().read().unwrap().clone()
+    }
+
+    fn set(&self, state: PromiseState) {
+        *self.0.read().unwrap().write().unwrap() = state;
+    }
+
+    /// Takes on the state of `other`, which then shares it with this promise
+    fn merge(&self, other: &Promise) {
+        let shared = self.0.read().unwrap().clone();
+        *shared.write().unwrap() = other.state();
+        *other.0.write().unwrap() = shared;
+    }
+}
+
+impl PartialEq for Promise {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl std::fmt::Debug for Promise {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.state() {
+            PromiseState::Done(value) => write!(f, "Promise({value})"),
+            PromiseState::Delayed { .. } => write!(f, "Promise"),
+        }
+    }
+}
+
+impl std::fmt::Display for Promise {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Promise")
+    }
+}
+
+/// The promise an object holds, if it is one
+pub fn as_promise(
+    interpreter: &InterpreterContext,
+    obj: &ObjectPointer,
+) -> InterpreterResult<Option<Promise>> {
+    if let ObjectRef::Object(o) = obj.deref(interpreter)? {
+        if let HeapObject::Promise(p) = &*o {
+            return Ok(Some(p.clone()));
+        }
+    }
+    Ok(None)
+}
+
+/// Gathers every binding currently visible into a single frame, innermost winning
+pub fn capture_bindings(interpreter: &InterpreterContext) -> Frame {
+    let frames = interpreter.stack.frame.read().unwrap();
+    let mut captured = Frame::named(frames.len(), "delay");
+    for frame in frames.iter() {
+        for (name, i) in frame.ident_mapping.iter() {
+            if let Some(p) = frame.get_local_by_index(*i) {
+                captured.insert_local(name, p);
+            }
+        }
+    }
+    captured
+}
+
+/// Pushes the value of `obj`, forcing it first if it is a promise
+pub fn force(interpreter: &InterpreterContext, obj: ObjectPointer) -> InterpreterResult<()> {
+    match as_promise(interpreter, &obj)? {
+        Some(promise) => force_step(interpreter, promise),
+        None => {
+            interpreter.stack.push_data(StackObject::Ref(obj));
+            Ok(())
+        }
+    }
+}
+
+/// Forces `obj` then hands its value to `f`
+pub fn force_then(
+    interpreter: &InterpreterContext,
+    obj: ObjectPointer,
+    f: impl Fn(&InterpreterContext, ObjectPointer) -> InterpreterResult<()> + Send + Sync + 'static,
+) -> InterpreterResult<()> {
+    force(interpreter, obj)?;
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let value = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+        f(interpreter, value)
+    }));
+    Ok(())
+}
+
+fn run_thunk(interpreter: &InterpreterContext, thunk: Thunk) -> InterpreterResult<()> {
+    match thunk {
+        Thunk::Native(f) => f(interpreter),
+        Thunk::Procedure(func, bindings) => {
+            let mut bindings = bindings;
+            bindings.stack_index = interpreter.stack.frame.read().unwrap().len();
+            interpreter.stack.push_frame(bindings);
+            interpreter.defer(Deferred::Apply(func, Vec::new()));
+            interpreter.defer(Deferred::then(|interpreter| {
+                let result = interpreter.stack.pop_data()?;
+                interpreter.stack.pop_frame()?;
+                interpreter.stack.push_data(result);
+                Ok(())
+            }));
+            Ok(())
+        }
+    }
+}
+
+/// Runs the thunk of a delayed promise, then forces again whatever it left behind,
+/// each round handed back to the interpreter loop rather than recursing
+fn force_step(interpreter: &InterpreterContext, promise: Promise) -> InterpreterResult<()> {
+    let (thunk, chained) = match promise.state() {
+        PromiseState::Done(value) => {
+            interpreter.stack.push_data(StackObject::Ref(value));
+            return Ok(());
+        }
+        PromiseState::Delayed { thunk, chained } => (thunk, chained),
+    };
+
+    run_thunk(interpreter, thunk)?;
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let result = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+        // Forcing the thunk may have forced this promise already, the first value sticks
+        if let PromiseState::Delayed { .. } = promise.state() {
+            match as_promise(interpreter, &result)? {
+                Some(next) if chained => promise.merge(&next),
+                _ => promise.set(PromiseState::Done(result)),
+            }
+        }
+        force_step(interpreter, promise.clone())
+    }));
+    Ok(())
+}
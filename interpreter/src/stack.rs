@@ -68,7 +68,7 @@ impl InterpreterStack {
     }
 
     pub fn top_frame<'a>(&'a self) -> InterpreterResult<FrameRef<'a>> {
-        RwLockWriteGuard::try_map(self.frame.write().unwrap(), |l| l.last_mut())
+        RwLockWriteGuard::filter_map(self.frame.write().unwrap(), |l| l.last_mut())
             .map(|x| x.into())
             .map_err(|_| InterpreterError::new(InterpreterErrorKind::EmptyStack))
     }
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use crate::{continuation::Winder, deferred::Deferred, InterpreterContext, InterpreterResult};
+
+use super::{
+    pop_params,
+    types::{expect_params, expect_procedure},
+};
+
+/// `(call/cc proc)`, applying `proc` to the continuation of the call
+pub fn call_cc(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    expect_procedure(interpreter, "call-with-current-continuation", 1, &params[0])?;
+
+    interpreter.defer(Deferred::CallWithContinuation(params[0].clone()));
+    Ok(())
+}
+
+/// `(dynamic-wind before thunk after)`, calling `before` whenever the extent of `thunk`
+/// is entered and `after` whenever it is left, including by continuations and exceptions
+pub fn dynamic_wind(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 3)?;
+    let params = pop_params(interpreter, n)?;
+    for (i, p) in params.iter().enumerate() {
+        expect_procedure(interpreter, "dynamic-wind", i + 1, p)?;
+    }
+
+    let [before, thunk, after] = <[_; 3]>::try_from(params).unwrap();
+    let winder = Arc::new(Winder {
+        before: before.clone(),
+        after,
+    });
+
+    interpreter.defer(Deferred::Apply(before, Vec::new()));
+    interpreter.defer(Deferred::then(move |interpreter| {
+        interpreter.stack.pop_data()?;
+        interpreter.winders.write().unwrap().push(winder.clone());
+
+        let winder = winder.clone();
+        interpreter.defer(Deferred::Apply(thunk.clone(), Vec::new()));
+        interpreter.defer(Deferred::then(move |interpreter| {
+            let result = interpreter.stack.pop_data()?;
+            interpreter.winders.write().unwrap().pop();
+
+            interpreter.defer(Deferred::Apply(winder.after.clone(), Vec::new()));
+            interpreter.defer(Deferred::then(move |interpreter| {
+                interpreter.stack.pop_data()?;
+                interpreter.stack.push_data(result.clone());
+                Ok(())
+            }));
+            Ok(())
+        }));
+        Ok(())
+    }));
+    Ok(())
+}
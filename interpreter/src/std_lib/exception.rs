@@ -0,0 +1,303 @@
+use core::{literal::Literal, parser::ast::AST};
+use std::{ops::Deref, sync::Arc};
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    deferred::Deferred,
+    deref::InterpreterDeref,
+    exception::{raised, Guard, Handler},
+    func::{Func, NativeFunc},
+    list::InterpreterListAlloc,
+    object::{ErrorObject, HeapObject, ObjectPointer, ObjectRef, StackObject},
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::{
+    pop_params,
+    types::{expect_params, expect_procedure, expect_string, wrong_type},
+};
+
+/// `(error message irritant ...)`
+pub fn error(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if n < 1 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNOrMoreParams(1.., n),
+        ));
+    }
+
+    let mut params = pop_params(interpreter, n)?;
+    let message = expect_string(interpreter, "error", 1, &params[0])?;
+    let irritants = params.split_off(1).to_list(interpreter)?;
+    let obj = HeapObject::Error(ErrorObject { message, irritants }).heap_alloc(interpreter)?;
+    Err(raised(interpreter, obj))
+}
+
+pub fn raise(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    Err(raised(interpreter, params[0].clone()))
+}
+
+/// Calls the innermost handler in the dynamic environment of the raise, with the
+/// handler uninstalled while it runs, returning its result
+pub fn raise_continuable(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+
+    let handler = {
+        let mut handlers = interpreter.handlers.write().unwrap();
+        match handlers.last() {
+            Some(Handler::Procedure(_)) => handlers.pop(),
+            _ => None,
+        }
+    };
+    let Some(Handler::Procedure(handler)) = handler else {
+        return Err(raised(interpreter, params[0].clone()));
+    };
+
+    interpreter.defer(Deferred::Apply(handler.clone(), params));
+    interpreter.defer(Deferred::then(move |interpreter| {
+        interpreter
+            .handlers
+            .write()
+            .unwrap()
+            .push(Handler::Procedure(handler.clone()));
+        Ok(())
+    }));
+    Ok(())
+}
+
+/// `(with-exception-handler handler thunk)`
+pub fn with_exception_handler(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 2)?;
+    let params = pop_params(interpreter, n)?;
+    expect_procedure(interpreter, "with-exception-handler", 1, &params[0])?;
+    expect_procedure(interpreter, "with-exception-handler", 2, &params[1])?;
+
+    interpreter.defer(Deferred::WithHandler(params[0].clone(), params[1].clone()));
+    Ok(())
+}
+
+fn expect_error_object(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    obj: &ObjectPointer,
+) -> InterpreterResult<ErrorObject> {
+    if let ObjectRef::Object(o) = obj.deref(interpreter)? {
+        if let HeapObject::Error(e) = o.deref() {
+            return Ok(e.clone());
+        }
+    }
+    Err(wrong_type(interpreter, procedure, 1, "an error object", obj))
+}
+
+pub fn is_error_object(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    let is_error = expect_error_object(interpreter, "error-object?", &params[0]).is_ok();
+    interpreter
+        .stack
+        .push_data(StackObject::Value(Literal::Boolean(is_error)));
+    Ok(())
+}
+
+pub fn error_object_message(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    let e = expect_error_object(interpreter, "error-object-message", &params[0])?;
+    let message = HeapObject::String(e.message).heap_alloc(interpreter)?;
+    interpreter.stack.push_data(StackObject::Ref(message));
+    Ok(())
+}
+
+pub fn error_object_irritants(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    let e = expect_error_object(interpreter, "error-object-irritants", &params[0])?;
+    interpreter.stack.push_data(StackObject::Ref(e.irritants));
+    Ok(())
+}
+
+/// Evaluates each expression in turn, returning the value of the last if it had one
+pub fn eval_body<'a>(
+    interpreter: &InterpreterContext,
+    body: impl IntoIterator<Item = &'a AST>,
+) -> InterpreterResult<Option<ObjectPointer>> {
+    let mut last = None;
+    for expr in body {
+        let height = interpreter.stack.data.read().unwrap().len();
+        interpreter.interpret(expr)?;
+        last = if interpreter.stack.data.read().unwrap().len() > height {
+            Some(interpreter.stack.pop_data()?.heap_alloc(interpreter)?)
+        } else {
+            None
+        };
+    }
+    Ok(last)
+}
+
+/// A procedure of no arguments evaluating `expr`. Applying it through
+/// [`Deferred::Apply`] evaluates the expression in the interpreter loop, rather than
+/// on the Rust stack as [`eval_body`] does
+pub fn thunk(interpreter: &InterpreterContext, expr: &AST) -> InterpreterResult<ObjectPointer> {
+    HeapObject::Func(Func::Defined(None, Vec::new(), expr.clone())).heap_alloc(interpreter)
+}
+
+/// Defers applying each thunk in turn, leaving only the result of the last
+pub fn defer_thunks(interpreter: &InterpreterContext, thunks: &[ObjectPointer]) {
+    for (i, thunk) in thunks.iter().enumerate() {
+        if i > 0 {
+            interpreter.defer(Deferred::discard());
+        }
+        interpreter.defer(Deferred::Apply(thunk.clone(), Vec::new()));
+    }
+}
+
+/// Defers evaluating each expression in turn, leaving only the value of the last
+pub fn defer_body<'a>(
+    interpreter: &InterpreterContext,
+    body: impl IntoIterator<Item = &'a AST>,
+) -> InterpreterResult<()> {
+    let thunks = body
+        .into_iter()
+        .map(|expr| thunk(interpreter, expr))
+        .collect::<InterpreterResult<Vec<_>>>()?;
+    defer_thunks(interpreter, &thunks);
+    Ok(())
+}
+
+/// A procedure of no arguments evaluating each expression of `body` in turn, returning
+/// the value of the last
+pub fn body_thunk<'a>(
+    interpreter: &InterpreterContext,
+    body: impl IntoIterator<Item = &'a AST>,
+) -> InterpreterResult<ObjectPointer> {
+    let mut thunks = body
+        .into_iter()
+        .map(|expr| thunk(interpreter, expr))
+        .collect::<InterpreterResult<Vec<_>>>()?;
+    if thunks.len() == 1 {
+        return Ok(thunks.pop().unwrap());
+    }
+
+    let body = NativeFunc::new(move |interpreter, _| {
+        defer_thunks(interpreter, &thunks);
+        Ok(())
+    });
+    HeapObject::Func(Func::Native("body".into(), body)).heap_alloc(interpreter)
+}
+
+/// Evaluates the `cond` style clauses of a `guard` from `index`, leaving the result of
+/// the first which applies, and raising `err` again if none do
+pub fn guard_clauses(
+    interpreter: &InterpreterContext,
+    guard: &Guard,
+    index: usize,
+    err: &InterpreterError,
+) -> InterpreterResult<()> {
+    let Some(clause) = guard.clauses.get(index) else {
+        return Err(err.clone());
+    };
+    let AST::Operation(test, body, span) = clause else {
+        return Err(InterpreterError::spanned(
+            InterpreterErrorKind::InvalidGuardClause,
+            clause.span(),
+        ));
+    };
+    if matches!(test.deref(), AST::Identifier(name, _) if name == "else") {
+        return defer_body(interpreter, body);
+    }
+
+    interpreter.defer(Deferred::Apply(thunk(interpreter, test)?, Vec::new()));
+    let (guard, err, span) = (guard.clone(), err.clone(), *span);
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let value = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+        if !value.deref(interpreter)?.is_truthy() {
+            return guard_clauses(interpreter, &guard, index + 1, &err);
+        }
+
+        let AST::Operation(_, body, _) = &guard.clauses[index] else {
+            unreachable!()
+        };
+        match body.as_slice() {
+            [] => interpreter.stack.push_data(StackObject::Ref(value)),
+            [AST::Identifier(arrow, _), receiver] if arrow == "=>" => {
+                interpreter.defer(Deferred::Apply(thunk(interpreter, receiver)?, Vec::new()));
+                interpreter.defer(Deferred::then(move |interpreter| {
+                    let receiver = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+                    interpreter.defer(Deferred::Apply(receiver, vec![value.clone()]));
+                    Ok(())
+                }));
+            }
+            [AST::Identifier(arrow, _), ..] if arrow == "=>" => {
+                return Err(InterpreterError::spanned(
+                    InterpreterErrorKind::InvalidGuardClause,
+                    span,
+                ));
+            }
+            body => defer_body(interpreter, body)?,
+        }
+        Ok(())
+    }));
+    Ok(())
+}
+
+/// `(guard (ident clause ...) body ...)`, evaluating the clauses with the condition
+/// bound to `ident` if the body raises, and raising it again if none apply
+pub fn guard(interpreter: &InterpreterContext, mut ast: Vec<&AST>) -> InterpreterResult<()> {
+    if ast.len() < 2 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNOrMoreParams(2.., ast.len()),
+        ));
+    }
+
+    let spec = ast.remove(0);
+    let AST::Operation(ident, clauses, _) = spec else {
+        return Err(InterpreterError::spanned(
+            InterpreterErrorKind::InvalidGuardForm,
+            spec.span(),
+        ));
+    };
+    let AST::Identifier(ident, _) = ident.deref() else {
+        return Err(InterpreterError::spanned(
+            InterpreterErrorKind::InvalidGuardForm,
+            ident.span(),
+        ));
+    };
+
+    let guard = Guard {
+        ident: ident.clone(),
+        clauses: Arc::new(clauses.clone()),
+    };
+    let body = body_thunk(interpreter, ast)?;
+    interpreter.defer(Deferred::Guard(guard, body));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::{eval, eval_recursion};
+
+    #[test]
+    fn guard_does_not_grow_the_native_stack() {
+        let f = "(define (f n) (guard (e (#t n)) (if (= n 0) (raise 1) (f (- n 1)))))";
+        assert_eq!(eval_recursion(f, 500), Ok("0".to_string()));
+    }
+
+    #[test]
+    fn guard_clauses_select_a_result() {
+        assert_eq!(eval("(guard (e ((= e 1) 10) ((= e 2) 20)) (raise 2))"), Ok("20".to_string()));
+        assert_eq!(eval("(guard (e ((= e 1) 10) (else e)) (raise 3))"), Ok("3".to_string()));
+        assert_eq!(eval("(guard (e ((+ e 1) => (lambda (x) (* x 2)))) (raise 4))"), Ok("10".to_string()));
+        assert_eq!(eval("(guard (e ((= e 1) 10)) (+ 1 2))"), Ok("3".to_string()));
+    }
+
+    #[test]
+    fn unmatched_guard_reraises() {
+        assert_eq!(
+            eval("(guard (outer (#t (+ outer 100))) (guard (e ((= e 1) 10)) (raise 5)))"),
+            Ok("105".to_string())
+        );
+        assert!(eval("(guard (e ((= e 1) 10)) (raise 5))").is_err());
+    }
+}
@@ -0,0 +1,519 @@
+use core::literal::{Literal, Numeric};
+use std::sync::Arc;
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    comparison::InterpreterEquivalence,
+    deferred::Deferred,
+    deref::InterpreterDeref,
+    list::InterpreterListAlloc,
+    object::{HeapObject, ObjectPointer, StackObject},
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::{
+    pop_params,
+    types::{expect_index, expect_list, expect_numeric, expect_procedure},
+    values::push_values,
+};
+
+type Rows = Arc<Vec<Vec<ObjectPointer>>>;
+type CollectFn = fn(&InterpreterContext, Vec<(ObjectPointer, bool)>) -> InterpreterResult<()>;
+
+/// Splits the parameters into the leading function and one row of arguments per
+/// element, stopping at the end of the shortest list
+fn func_and_rows(
+    interpreter: &InterpreterContext,
+    n: usize,
+    procedure: &str,
+    leading: usize,
+) -> InterpreterResult<(Vec<ObjectPointer>, Vec<Vec<ObjectPointer>>)> {
+    if n < leading + 1 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNOrMoreParams((leading + 1).., n),
+        ));
+    }
+
+    let mut params = pop_params(interpreter, n)?;
+    expect_procedure(interpreter, procedure, 1, &params[0])?;
+    let lists = params
+        .split_off(leading)
+        .iter()
+        .enumerate()
+        .map(|(i, l)| expect_list(interpreter, procedure, leading + i + 1, l))
+        .collect::<InterpreterResult<Vec<_>>>()?;
+
+    let len = lists.iter().map(Vec::len).min().unwrap_or(0);
+    let rows = (0..len)
+        .map(|i| lists.iter().map(|l| l[i].clone()).collect())
+        .collect();
+    Ok((params, rows))
+}
+
+fn pop_truthy(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<Vec<bool>> {
+    let mut out = Vec::new();
+    for _ in 0..n {
+        out.push(interpreter.stack.pop_data()?.deref(interpreter)?.is_truthy());
+    }
+    out.reverse();
+    Ok(out)
+}
+
+pub fn apply(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if n < 2 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNOrMoreParams(2.., n),
+        ));
+    }
+
+    let mut params = pop_params(interpreter, n)?;
+    expect_procedure(interpreter, "apply", 1, &params[0])?;
+    let spread = expect_list(interpreter, "apply", n, &params.pop().unwrap())?;
+    let func = params.remove(0);
+    params.extend(spread);
+
+    interpreter.defer(Deferred::Apply(func, params));
+    Ok(())
+}
+
+pub fn map(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    let (func, rows) = func_and_rows(interpreter, n, "map", 1)?;
+    let len = rows.len();
+    for row in rows {
+        interpreter.defer(Deferred::Apply(func[0].clone(), row));
+    }
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let list = pop_params(interpreter, len)?.to_list(interpreter)?;
+        interpreter.stack.push_data(StackObject::Ref(list));
+        Ok(())
+    }));
+    Ok(())
+}
+
+pub fn for_each(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    let (func, rows) = func_and_rows(interpreter, n, "for-each", 1)?;
+    for row in rows {
+        interpreter.defer(Deferred::Apply(func[0].clone(), row));
+        interpreter.defer(Deferred::then(|interpreter| {
+            interpreter.stack.pop_data()?;
+            Ok(())
+        }));
+    }
+    Ok(())
+}
+
+/// Calls `pred` on every element then hands the elements and results to `collect`
+fn partition_by(
+    interpreter: &InterpreterContext,
+    n: usize,
+    procedure: &str,
+    collect: CollectFn,
+) -> InterpreterResult<()> {
+    if n != 2 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, n),
+        ));
+    }
+
+    let params = pop_params(interpreter, n)?;
+    expect_procedure(interpreter, procedure, 1, &params[0])?;
+    let list = expect_list(interpreter, procedure, 2, &params[1])?;
+    for x in list.iter() {
+        interpreter.defer(Deferred::Apply(params[0].clone(), vec![x.clone()]));
+    }
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let results = pop_truthy(interpreter, list.len())?;
+        collect(interpreter, list.iter().cloned().zip(results).collect())
+    }));
+    Ok(())
+}
+
+fn keep_matching(
+    interpreter: &InterpreterContext,
+    results: Vec<(ObjectPointer, bool)>,
+    keep: bool,
+) -> InterpreterResult<ObjectPointer> {
+    results
+        .into_iter()
+        .filter_map(|(x, r)| (r == keep).then_some(x))
+        .collect::<Vec<_>>()
+        .to_list(interpreter)
+}
+
+pub fn filter(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    partition_by(interpreter, n, "filter", |interpreter, results| {
+        let list = keep_matching(interpreter, results, true)?;
+        interpreter.stack.push_data(StackObject::Ref(list));
+        Ok(())
+    })
+}
+
+pub fn remove(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    partition_by(interpreter, n, "remove", |interpreter, results| {
+        let list = keep_matching(interpreter, results, false)?;
+        interpreter.stack.push_data(StackObject::Ref(list));
+        Ok(())
+    })
+}
+
+/// Returns two values, the elements satisfying `pred` and those that do not
+pub fn partition(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    partition_by(interpreter, n, "partition", |interpreter, results| {
+        let matching = keep_matching(interpreter, results.clone(), true)?;
+        let others = keep_matching(interpreter, results, false)?;
+        push_values(interpreter, vec![matching, others])
+    })
+}
+
+/// Applies `func` to the next row and the accumulator on top of the data stack,
+/// leaving the final accumulator once the rows run out
+fn fold_step(
+    interpreter: &InterpreterContext,
+    func: ObjectPointer,
+    rows: Rows,
+    i: usize,
+    acc_first: bool,
+) -> InterpreterResult<()> {
+    let Some(row) = rows.get(i) else {
+        return Ok(());
+    };
+
+    let acc = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+    let mut args = row.clone();
+    if acc_first {
+        args.insert(0, acc);
+    } else {
+        args.push(acc);
+    }
+
+    interpreter.defer(Deferred::Apply(func.clone(), args));
+    interpreter.defer(Deferred::then(move |interpreter| {
+        fold_step(interpreter, func.clone(), rows.clone(), i + 1, acc_first)
+    }));
+    Ok(())
+}
+
+fn fold_by(
+    interpreter: &InterpreterContext,
+    n: usize,
+    procedure: &str,
+    from_right: bool,
+    acc_first: bool,
+) -> InterpreterResult<()> {
+    let (mut params, mut rows) = func_and_rows(interpreter, n, procedure, 2)?;
+    if from_right {
+        rows.reverse();
+    }
+
+    let knil = params.pop().unwrap();
+    let func = params.pop().unwrap();
+    interpreter.stack.push_data(StackObject::Ref(knil));
+    fold_step(interpreter, func, Arc::new(rows), 0, acc_first)
+}
+
+/// SRFI-1 `fold`, calling `(kons elem ... acc)` from the left
+pub fn fold(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    fold_by(interpreter, n, "fold", false, false)
+}
+
+/// Calls `(func acc elem ...)` from the left
+pub fn fold_left(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    fold_by(interpreter, n, "fold-left", false, true)
+}
+
+/// Calls `(kons elem ... acc)` from the right
+pub fn fold_right(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    fold_by(interpreter, n, "fold-right", true, false)
+}
+
+pub fn reduce(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if n != 3 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(3, n),
+        ));
+    }
+
+    let params = pop_params(interpreter, n)?;
+    expect_procedure(interpreter, "reduce", 1, &params[0])?;
+    let mut list = expect_list(interpreter, "reduce", 3, &params[2])?;
+    if list.is_empty() {
+        interpreter.stack.push_data(StackObject::Ref(params[1].clone()));
+        return Ok(());
+    }
+
+    let first = list.remove(0);
+    let rows = list.into_iter().map(|x| vec![x]).collect();
+    interpreter.stack.push_data(StackObject::Ref(first));
+    fold_step(interpreter, params[0].clone(), Arc::new(rows), 0, false)
+}
+
+/// Decides from the result of the predicate whether the search is finished,
+/// given the row index, the row, the result, its truthiness and whether it was the last row
+type SearchFn = fn(usize, &[ObjectPointer], &ObjectPointer, bool, bool) -> Option<StackObject>;
+
+/// Applies `pred` to each row in turn until `found` yields the result
+fn search_step(
+    interpreter: &InterpreterContext,
+    pred: ObjectPointer,
+    rows: Rows,
+    i: usize,
+    found: SearchFn,
+    exhausted: StackObject,
+) -> InterpreterResult<()> {
+    let Some(row) = rows.get(i) else {
+        interpreter.stack.push_data(exhausted);
+        return Ok(());
+    };
+
+    interpreter.defer(Deferred::Apply(pred.clone(), row.clone()));
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let result = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+        let truthy = result.deref(interpreter)?.is_truthy();
+        let last = i + 1 == rows.len();
+
+        match found(i, &rows[i], &result, truthy, last) {
+            Some(out) => {
+                interpreter.stack.push_data(out);
+                Ok(())
+            }
+            None => search_step(
+                interpreter,
+                pred.clone(),
+                rows.clone(),
+                i + 1,
+                found,
+                exhausted.clone(),
+            ),
+        }
+    }));
+    Ok(())
+}
+
+fn search(
+    interpreter: &InterpreterContext,
+    n: usize,
+    procedure: &str,
+    found: SearchFn,
+    exhausted: bool,
+) -> InterpreterResult<()> {
+    let (pred, rows) = func_and_rows(interpreter, n, procedure, 1)?;
+    search_step(
+        interpreter,
+        pred[0].clone(),
+        Arc::new(rows),
+        0,
+        found,
+        StackObject::Value(Literal::Boolean(exhausted)),
+    )
+}
+
+pub fn find(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if n != 2 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, n),
+        ));
+    }
+    search(
+        interpreter,
+        n,
+        "find",
+        |_, row, _, truthy, _| truthy.then(|| StackObject::Ref(row[0].clone())),
+        false,
+    )
+}
+
+pub fn any(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    search(
+        interpreter,
+        n,
+        "any",
+        |_, _, result, truthy, _| truthy.then(|| StackObject::Ref(result.clone())),
+        false,
+    )
+}
+
+pub fn every(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    search(
+        interpreter,
+        n,
+        "every",
+        |_, _, result, truthy, last| match (truthy, last) {
+            (false, _) => Some(StackObject::Value(Literal::Boolean(false))),
+            (true, true) => Some(StackObject::Ref(result.clone())),
+            (true, false) => None,
+        },
+        true,
+    )
+}
+
+pub fn list_index(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    search(
+        interpreter,
+        n,
+        "list-index",
+        |i, _, _, truthy, _| {
+            truthy.then_some(StackObject::Value(Literal::Numeric(Numeric::Int(i as i32))))
+        },
+        false,
+    )
+}
+
+pub fn count(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    let (pred, rows) = func_and_rows(interpreter, n, "count", 1)?;
+    let len = rows.len();
+    for row in rows {
+        interpreter.defer(Deferred::Apply(pred[0].clone(), row));
+    }
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let count = pop_truthy(interpreter, len)?.iter().filter(|r| **r).count();
+        interpreter
+            .stack
+            .push_data(StackObject::Value(Literal::Numeric(Numeric::Int(count as i32))));
+        Ok(())
+    }));
+    Ok(())
+}
+
+/// Removes every element equal to `x`, optionally using a given equality procedure
+pub fn delete(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if !(2..=3).contains(&n) {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, n),
+        ));
+    }
+
+    let params = pop_params(interpreter, n)?;
+    let x = params[0].clone();
+    let list = expect_list(interpreter, "delete", 2, &params[1])?;
+
+    let Some(eq) = params.get(2) else {
+        let mut kept = Vec::new();
+        for elem in list {
+            if !x.is_equal(&elem, interpreter)? {
+                kept.push(elem);
+            }
+        }
+        let list = kept.to_list(interpreter)?;
+        interpreter.stack.push_data(StackObject::Ref(list));
+        return Ok(());
+    };
+
+    for elem in list.iter() {
+        interpreter.defer(Deferred::Apply(eq.clone(), vec![x.clone(), elem.clone()]));
+    }
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let results = pop_truthy(interpreter, list.len())?;
+        let list = keep_matching(interpreter, list.iter().cloned().zip(results).collect(), false)?;
+        interpreter.stack.push_data(StackObject::Ref(list));
+        Ok(())
+    }));
+    Ok(())
+}
+
+/// `(iota count [start [step]])`
+pub fn iota(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if !(1..=3).contains(&n) {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(1, n),
+        ));
+    }
+
+    let params = pop_params(interpreter, n)?;
+    let count = expect_index(interpreter, "iota", 1, &params[0])?;
+    let mut numbers = params
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, p)| expect_numeric(interpreter, "iota", i + 1, p))
+        .collect::<InterpreterResult<Vec<_>>>()?
+        .into_iter();
+    let start = numbers.next().unwrap_or(Numeric::Int(0));
+    let step = numbers.next().unwrap_or(Numeric::Int(1));
+
+    let list = (0..count)
+        .map(|i| {
+            HeapObject::Value(Literal::Numeric(start + Numeric::Int(i as i32) * step))
+                .heap_alloc(interpreter)
+        })
+        .collect::<InterpreterResult<Vec<_>>>()?
+        .to_list(interpreter)?;
+    interpreter.stack.push_data(StackObject::Ref(list));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::{eval, eval_recursion};
+
+    #[test]
+    fn apply_spreads_its_last_argument() {
+        assert_eq!(eval("(apply + 1 2 (list 3 4))"), Ok("10".to_string()));
+        assert_eq!(eval("(apply list (list))"), Ok("()".to_string()));
+        assert!(eval("(apply + 1 2)").unwrap_err().contains("expected a list"));
+    }
+
+    #[test]
+    fn map_and_for_each_stop_at_the_shortest_list() {
+        assert_eq!(eval("(map (lambda (x) (* x x)) (list 1 2 3))"), Ok("1:4:9:()".to_string()));
+        assert_eq!(eval("(map + (list 1 2 3) (list 10 20))"), Ok("11:22:()".to_string()));
+        assert_eq!(
+            eval("(define total 0) (define (add x y) (define total (+ total x y)))
+                  (for-each add (list 1 2 3) (list 10 20)) total"),
+            Ok("33".to_string())
+        );
+        assert!(eval("(map car 5)").unwrap_err().contains("'map' expected a list as argument 2"));
+    }
+
+    #[test]
+    fn filters_keep_or_drop_matching_elements() {
+        let big = "(define (big? x) (> x 1))";
+        assert_eq!(eval(&format!("{big} (filter big? (list 1 2 3))")), Ok("2:3:()".to_string()));
+        assert_eq!(eval(&format!("{big} (remove big? (list 1 2 3))")), Ok("1:()".to_string()));
+        assert_eq!(
+            eval(&format!(
+                "{big} (call-with-values (lambda () (partition big? (list 1 2 3))) list)"
+            )),
+            Ok("2:3:():1:():()".to_string())
+        );
+        assert_eq!(eval("(delete 2 (list 1 2 3 2))"), Ok("1:3:()".to_string()));
+    }
+
+    #[test]
+    fn folds_run_in_their_direction() {
+        assert_eq!(eval("(fold cons (list) (list 1 2 3))"), Ok("3:2:1:()".to_string()));
+        assert_eq!(eval("(fold + 0 (list 1 2) (list 10 20))"), Ok("33".to_string()));
+        assert_eq!(eval("(fold-right cons (list) (list 1 2 3))"), Ok("1:2:3:()".to_string()));
+        assert_eq!(eval("(reduce + 0 (list 1 2 3))"), Ok("6".to_string()));
+        assert_eq!(eval("(reduce + 0 (list))"), Ok("0".to_string()));
+    }
+
+    #[test]
+    fn searches_stop_at_the_first_match() {
+        let big = "(define (big? x) (> x 1))";
+        assert_eq!(eval(&format!("{big} (find big? (list 1 2 3))")), Ok("2".to_string()));
+        assert_eq!(eval("(find (lambda (x) (> x 5)) (list 1 2 3))"), Ok("false".to_string()));
+        assert_eq!(eval(&format!("{big} (list-index big? (list 1 2 3))")), Ok("1".to_string()));
+        assert_eq!(eval("(list-index (lambda (x) (> x 5)) (list 1 2 3))"), Ok("false".to_string()));
+        assert_eq!(eval("(any < (list 1 5) (list 2 4))"), Ok("true".to_string()));
+        assert_eq!(eval("(any (lambda (x) (> x 5)) (list 1 2 3))"), Ok("false".to_string()));
+        assert_eq!(eval("(every (lambda (x) x) (list 1 2 3))"), Ok("3".to_string()));
+        assert_eq!(eval("(every (lambda (x) x) (list))"), Ok("true".to_string()));
+        assert_eq!(eval(&format!("{big} (count big? (list 1 2 3))")), Ok("2".to_string()));
+    }
+
+    #[test]
+    fn iota_counts_from_its_start_by_its_step() {
+        assert_eq!(eval("(iota 3)"), Ok("0:1:2:()".to_string()));
+        assert_eq!(eval("(iota 3 1)"), Ok("1:2:3:()".to_string()));
+        assert_eq!(eval("(iota 3 0 2)"), Ok("0:2:4:()".to_string()));
+        assert_eq!(eval("(iota 0)"), Ok("()".to_string()));
+    }
+
+    #[test]
+    fn higher_order_procedures_do_not_grow_the_native_stack() {
+        let f = "(define (f n) (car (map (lambda (x) (if (= n 0) x (f (- n 1)))) (list 0))))";
+        assert_eq!(eval_recursion(f, 1000), Ok("0".to_string()));
+        let f = "(define (f n) (fold (lambda (x acc) (if (= n 0) acc (f (- n 1)))) 0 (list 1)))";
+        assert_eq!(eval_recursion(f, 1000), Ok("0".to_string()));
+    }
+}
@@ -0,0 +1,217 @@
+use core::parser::ast::AST;
+use std::{ops::Deref, sync::Arc};
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    deferred::Deferred,
+    deref::InterpreterDeref,
+    func::{Case, Func},
+    object::{HeapObject, ObjectPointer, StackObject},
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::exception::{defer_thunks, thunk};
+
+/// A `do` variable with its initial value and optional step
+struct DoVar<'a> {
+    name: &'a str,
+    init: &'a AST,
+    step: Option<&'a AST>,
+}
+
+fn invalid_do(ast: &AST) -> InterpreterError {
+    InterpreterError::spanned(InterpreterErrorKind::InvalidDoForm, ast.span())
+}
+
+fn do_vars(specs: &AST) -> InterpreterResult<Vec<DoVar<'_>>> {
+    let list = match specs {
+        AST::EmptyList(_) => return Ok(Vec::new()),
+        AST::Operation(first, rest, _) => std::iter::once(first.deref()).chain(rest),
+        e => return Err(invalid_do(e)),
+    };
+
+    list.map(|spec| match spec {
+        AST::Operation(name, rest, _) => match (name.deref(), rest.as_slice()) {
+            (AST::Identifier(name, _), [init]) => Ok(DoVar {
+                name,
+                init,
+                step: None,
+            }),
+            (AST::Identifier(name, _), [init, step]) => Ok(DoVar {
+                name,
+                init,
+                step: Some(step),
+            }),
+            _ => Err(invalid_do(spec)),
+        },
+        e => Err(invalid_do(e)),
+    })
+    .collect()
+}
+
+/// A `do` loop with each of its expressions made into a thunk, so that every iteration
+/// is evaluated in the interpreter loop rather than on the Rust stack
+struct Do {
+    /// The variables with a step, rebound after each iteration
+    stepped: Vec<(String, ObjectPointer)>,
+    test: ObjectPointer,
+    result: Vec<ObjectPointer>,
+    commands: Vec<ObjectPointer>,
+}
+
+/// Pops a value for each name, pushed in order, binding them in the frame of the loop
+fn bind_vars<'a>(
+    interpreter: &InterpreterContext,
+    names: impl DoubleEndedIterator<Item = &'a String>,
+) -> InterpreterResult<()> {
+    for name in names.rev() {
+        let value = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+        interpreter.stack.top_frame()?.insert_local(name, value);
+    }
+    Ok(())
+}
+
+/// Defers one iteration, the test then either the result or the commands and steps
+fn do_iteration(interpreter: &InterpreterContext, spec: Arc<Do>) {
+    interpreter.defer(Deferred::Apply(spec.test.clone(), Vec::new()));
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let done = interpreter.stack.pop_data()?.deref(interpreter)?.is_truthy();
+        if done {
+            defer_thunks(interpreter, &spec.result);
+            return Ok(());
+        }
+
+        defer_thunks(interpreter, &spec.commands);
+        if !spec.commands.is_empty() {
+            interpreter.defer(Deferred::discard());
+        }
+        for (_, step) in &spec.stepped {
+            interpreter.defer(Deferred::Apply(step.clone(), Vec::new()));
+        }
+        let spec = spec.clone();
+        interpreter.defer(Deferred::then(move |interpreter| {
+            bind_vars(interpreter, spec.stepped.iter().map(|(name, _)| name))?;
+            do_iteration(interpreter, spec.clone());
+            Ok(())
+        }));
+        Ok(())
+    }));
+}
+
+/// `(do ((var init step) ..) (test expr ..) command ..)`, evaluating the commands then
+/// rebinding every variable to its step until `test` holds, returning the last `expr`
+pub fn do_loop(interpreter: &InterpreterContext, ast: Vec<&AST>) -> InterpreterResult<()> {
+    if ast.len() < 2 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNOrMoreParams(2.., ast.len()),
+        ));
+    }
+
+    let vars = do_vars(ast[0])?;
+    let AST::Operation(test, result, _) = ast[1] else {
+        return Err(invalid_do(ast[1]));
+    };
+
+    let spec = Arc::new(Do {
+        stepped: vars
+            .iter()
+            .filter_map(|var| {
+                let step = var.step?;
+                Some(thunk(interpreter, step).map(|step| (var.name.to_string(), step)))
+            })
+            .collect::<InterpreterResult<Vec<_>>>()?,
+        test: thunk(interpreter, test)?,
+        result: result
+            .iter()
+            .map(|expr| thunk(interpreter, expr))
+            .collect::<InterpreterResult<Vec<_>>>()?,
+        commands: ast[2..]
+            .iter()
+            .map(|expr| thunk(interpreter, expr))
+            .collect::<InterpreterResult<Vec<_>>>()?,
+    });
+
+    // Every init is evaluated before any variable is bound. The variables are bound in
+    // the frame the form runs in, which is popped once it returns
+    for var in &vars {
+        interpreter.defer(Deferred::Apply(thunk(interpreter, var.init)?, Vec::new()));
+    }
+    let names = vars.iter().map(|var| var.name.to_string()).collect::<Vec<_>>();
+    interpreter.defer(Deferred::then(move |interpreter| {
+        bind_vars(interpreter, names.iter())?;
+        do_iteration(interpreter, spec.clone());
+        Ok(())
+    }));
+    Ok(())
+}
+
+fn case_params(formals: &AST) -> InterpreterResult<(Vec<String>, Option<String>)> {
+    match formals {
+        AST::Identifier(rest, _) => Ok((Vec::new(), Some(rest.clone()))),
+        AST::EmptyList(_) => Ok((Vec::new(), None)),
+        AST::Operation(first, rest, _) => super::formals(std::iter::once(first.deref()).chain(rest), |e| {
+            InterpreterError::spanned(
+                InterpreterErrorKind::IsNotParamName(e.to_string()),
+                e.span(),
+            )
+        }),
+        e => Err(InterpreterError::spanned(
+            InterpreterErrorKind::InvalidCaseLambdaClause,
+            e.span(),
+        )),
+    }
+}
+
+/// `(case-lambda (formals body) ..)`, a procedure running the first clause which accepts
+/// the number of arguments it was called with. Formals which are a single identifier
+/// take all the arguments as a list
+pub fn case_lambda(interpreter: &InterpreterContext, ast: Vec<&AST>) -> InterpreterResult<()> {
+    if ast.is_empty() {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNOrMoreParams(1.., 0),
+        ));
+    }
+
+    let cases = ast
+        .iter()
+        .map(|clause| match clause {
+            AST::Operation(formals, body, _) if body.len() == 1 => {
+                let (params, rest) = case_params(formals)?;
+                Ok(Case {
+                    params,
+                    rest,
+                    body: body[0].clone(),
+                })
+            }
+            e => Err(InterpreterError::spanned(
+                InterpreterErrorKind::InvalidCaseLambdaClause,
+                e.span(),
+            )),
+        })
+        .collect::<InterpreterResult<Vec<_>>>()?;
+
+    let obj = HeapObject::Func(Func::CaseLambda(cases)).heap_alloc(interpreter)?;
+    interpreter.stack.push_data(StackObject::Ref(obj));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::{eval, eval_recursion};
+
+    #[test]
+    fn do_steps_until_the_test_holds() {
+        assert_eq!(eval("(do ((i 0 (+ i 1)) (acc 0 (+ acc i))) ((= i 5) acc))"), Ok("10".to_string()));
+        assert_eq!(eval("(do ((i 0 (+ i 1)) (j 10)) ((= i 3) j))"), Ok("10".to_string()));
+        assert_eq!(
+            eval("(define total 0) (do ((i 0 (+ i 1))) ((= i 4) total) (define total (+ total i)))"),
+            Ok("6".to_string())
+        );
+    }
+
+    #[test]
+    fn do_does_not_grow_the_native_stack() {
+        let f = "(define (f n) (do ((i 0 (+ i 1))) ((= i 1) (if (= n 0) 0 (f (- n 1))))))";
+        assert_eq!(eval_recursion(f, 1000), Ok("0".to_string()));
+    }
+}
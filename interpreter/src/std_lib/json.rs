@@ -0,0 +1,105 @@
+use crate::{
+    alloc::InterpreterStackAlloc,
+    json::Json,
+    object::{HeapObject, ObjectPointer, StackObject},
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::{
+    pop_params,
+    types::{expect_params, expect_string},
+};
+
+/// `(json-read string [null])`, parsing a JSON document. Objects become association
+/// lists of `(key . value)` pairs with string keys, arrays become lists, and `null`
+/// becomes the optional second argument, the empty list by default. Integers outside
+/// 32 bits are an error. An empty object becomes the empty list, which `json-write`
+/// writes as `[]`
+pub fn json_read(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if !(1..=2).contains(&n) {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(1, n),
+        ));
+    }
+
+    let params = pop_params(interpreter, n)?;
+    let source = expect_string(interpreter, "json-read", 1, &params[0])?;
+    let null = params.get(1).cloned().unwrap_or(ObjectPointer::Null);
+
+    let value = Json::parse(&source)?.to_scheme(interpreter, &null)?;
+    interpreter.stack.push_data(StackObject::Ref(value));
+    Ok(())
+}
+
+/// `(json-write obj)`, returning `obj` written as compact JSON. A list of pairs which
+/// all have string heads is written as an object, any other list as an array
+pub fn json_write(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+
+    let params = pop_params(interpreter, n)?;
+    let json = Json::from_scheme(interpreter, &params[0])?;
+    let string = HeapObject::String(json.to_string()).stack_alloc(interpreter)?;
+    interpreter.stack.push_data(string);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        testing::{eval, interpreter},
+        Interpreter,
+    };
+
+    /// Calls `name` with a string argument, as a string holding quotes cannot be
+    /// written in the source
+    fn call(interpreter: &Interpreter, name: &str, arg: &str) -> Result<String, String> {
+        let arg = interpreter.to_value(arg.to_string()).unwrap();
+        interpreter
+            .call(name, [arg])
+            .map(|value| value.to_string())
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn json_is_read_into_lists() {
+        let interpreter = interpreter();
+        assert_eq!(
+            call(&interpreter, "json-read", r#"{"a": [1, 2.5, true], "b": null}"#),
+            Ok(r#""a":1:2.5:true:():"b":():()"#.to_string())
+        );
+        assert_eq!(eval("(json-read \"null\" #f)"), Ok("false".to_string()));
+        assert!(eval("(json-read \"[1,\")").unwrap_err().contains("Invalid JSON"));
+        assert!(eval("(json-read \"3000000000\")").is_err());
+        assert!(eval("(json-read \"1e300\")").is_err());
+    }
+
+    #[test]
+    fn json_is_written_from_lists() {
+        let interpreter = interpreter();
+        let written = interpreter
+            .eval_str("(json-write (list (cons \"a\" (list 1 2.5)) (cons \"b\" \"c\")))")
+            .unwrap();
+        assert_eq!(written.get::<String>().as_deref(), Some(r#"{"a":[1,2.5],"b":"c"}"#));
+        assert_eq!(eval("(json-write (list))"), Ok("\"[]\"".to_string()));
+        assert!(eval("(json-write car)").is_err());
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let interpreter = interpreter();
+        let document = r#"{"a":[1,2.5,true,"x"],"b":{"c":false}}"#;
+        let document_value = interpreter.to_value(document.to_string()).unwrap();
+        let read = interpreter.call("json-read", [document_value]).unwrap();
+        let written = interpreter.call("json-write", [read]).unwrap();
+        assert_eq!(written.get::<String>().as_deref(), Some(document));
+    }
+
+    #[test]
+    fn deeply_nested_lists_are_errors() {
+        let nest = "(define (nest n) (if (= n 0) 1 (list (nest (- n 1)))))";
+        assert!(eval(&format!("{nest} (json-write (nest 100))")).is_ok());
+        assert!(eval(&format!("{nest} (json-write (nest 1000))"))
+            .unwrap_err()
+            .contains("nested deeper than"));
+    }
+}
@@ -0,0 +1,567 @@
+use core::{
+    parser::ast::AST,
+    token::span::{Span, TotalSpan},
+    LexerParser,
+};
+use std::{env, ops::Deref, path::PathBuf, sync::Arc};
+
+use crate::{
+    capabilities::ImportPolicy,
+    library::{library_name, Library},
+    object::ObjectPointer,
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::exception::eval_body;
+
+/// The names an import set brings in, with what each is bound to
+type Imports = Vec<(String, ObjectPointer)>;
+
+fn invalid_set(ast: &AST) -> InterpreterError {
+    InterpreterError::spanned(InterpreterErrorKind::InvalidImportSet, ast.span())
+}
+
+fn invalid_library(ast: &AST) -> InterpreterError {
+    InterpreterError::spanned(InterpreterErrorKind::InvalidLibraryForm, ast.span())
+}
+
+fn ident(ast: &AST) -> Option<&str> {
+    match ast {
+        AST::Identifier(name, _) => Some(name),
+        _ => None,
+    }
+}
+
+/// Reads a library name such as `(scheme base)`, whose parts are identifiers or integers
+fn parse_name(ast: &AST) -> Option<Vec<String>> {
+    let AST::Operation(first, rest, _) = ast else {
+        return None;
+    };
+    std::iter::once(first.deref())
+        .chain(rest)
+        .map(|part| match part {
+            AST::Identifier(name, _) => Some(name.clone()),
+            AST::Literal(lit, _) => Some(lit.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The directory imports written at `span` are found relative to. Inside a library
+/// loaded from a file named after it this is the directory its name starts from,
+/// otherwise it is the directory of the file, and outside any file the current directory
+fn import_dir(interpreter: &InterpreterContext, span: &Span) -> InterpreterResult<PathBuf> {
+    let Some(mut dir) = interpreter
+        .error_writer
+        .read()
+        .unwrap()
+        .id_to_path
+        .get(&span.file_id)
+        .cloned()
+    else {
+        return env::current_dir().map_err(|err| {
+            let kind = InterpreterErrorKind::NoCurrentDirectory(err.to_string());
+            InterpreterError::spanned(kind, *span)
+        });
+    };
+
+    let mut name = interpreter
+        .library_at(span)
+        .map(|l| l.name.clone())
+        .unwrap_or_default();
+    if dir.file_stem().and_then(|s| s.to_str()) == name.last().map(String::as_str) {
+        dir.pop();
+        name.pop();
+        while let Some(part) = name.pop() {
+            if !dir.ends_with(&part) {
+                break;
+            }
+            dir.pop();
+        }
+        return Ok(dir);
+    }
+    dir.pop();
+    Ok(dir)
+}
+
+/// Every file `parts` may name, in the order they are tried. The directory relative
+/// to the import comes first, then each `-I` directory, each directory in
+/// `SCHEME_PATH`, and last the `lib` directory beside the main program
+fn candidates(
+    interpreter: &InterpreterContext,
+    parts: &[String],
+    span: &Span,
+    extensions: &[&str],
+) -> InterpreterResult<Vec<PathBuf>> {
+    let mut dirs = vec![import_dir(interpreter, span)?];
+    dirs.extend(interpreter.search_path.read().unwrap().iter().cloned());
+    if let Some(paths) = env::var_os("SCHEME_PATH") {
+        dirs.extend(env::split_paths(&paths).filter(|p| !p.as_os_str().is_empty()));
+    }
+    if let Some(main) = interpreter.error_writer.read().unwrap().id_to_path.get(&0) {
+        dirs.extend(main.parent().map(|dir| dir.join("lib")));
+    }
+
+    let mut paths = Vec::new();
+    for dir in dirs {
+        for extension in extensions {
+            let mut path = parts.iter().fold(dir.clone(), |l, r| l.join(r));
+            path.set_extension(extension);
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// The first of the candidates for `parts` which exists
+fn locate(
+    interpreter: &InterpreterContext,
+    name: String,
+    parts: &[String],
+    span: Span,
+    extensions: &[&str],
+) -> InterpreterResult<PathBuf> {
+    if interpreter.capabilities.imports == ImportPolicy::Disabled {
+        return Err(InterpreterError::spanned(
+            InterpreterErrorKind::ImportDisabled(name),
+            span,
+        ));
+    }
+
+    // Files the policy denies are passed over, only reported if nothing else is found
+    let tried = candidates(interpreter, parts, &span, extensions)?;
+    let mut denied = None;
+    for path in tried.iter().filter(|path| path.is_file()) {
+        match interpreter.capabilities.check_import(&name, path) {
+            Ok(()) => return Ok(path.clone()),
+            Err(err) => {
+                denied.get_or_insert(err);
+            }
+        }
+    }
+    match denied {
+        Some(mut err) => {
+            err.add_if_not_spanned(span);
+            Err(err)
+        }
+        None => Err(InterpreterError::spanned(
+            InterpreterErrorKind::ImportNotFound(
+                name,
+                tried.iter().map(|p| p.to_string_lossy().into_owned()).collect(),
+            ),
+            span,
+        )),
+    }
+}
+
+/// Evaluates every top level form of a file, unless it has already been loaded
+fn load_file(interpreter: &InterpreterContext, file_path: PathBuf, span: Span) -> InterpreterResult<()> {
+    if interpreter
+        .error_writer
+        .read()
+        .unwrap()
+        .already_loaded(&file_path)
+    {
+        return Ok(());
+    }
+
+    // Read as for `file->string`, so a file which is not UTF-8 cannot be opened either
+    let contents = std::fs::read_to_string(&file_path).map_err(|_| {
+        InterpreterError::spanned(
+            InterpreterErrorKind::CannotOpenFile(file_path.to_str().unwrap().to_string()),
+            span,
+        )
+    })?;
+
+    let id = interpreter
+        .error_writer
+        .write()
+        .unwrap()
+        .add_file(file_path.clone(), contents.clone());
+
+    // The error writer is released before evaluating, as imports made by the file
+    // write to it
+    let parsed = LexerParser::from_string(id, contents, &interpreter.error_writer.read().unwrap())
+        .map_err(|_| InterpreterError::spanned(InterpreterErrorKind::ErrorInParsingImport, span));
+    let loaded = parsed.and_then(|ast| ast.iter().try_for_each(|node| interpreter.interpret(node)));
+
+    // A file which failed is loaded again by the next import of it, its lines are kept
+    // for the errors which point into it
+    if loaded.is_err() {
+        interpreter
+            .error_writer
+            .write()
+            .unwrap()
+            .loaded_paths
+            .remove(&file_path);
+    }
+    loaded
+}
+
+fn find_library(interpreter: &InterpreterContext, name: &[String]) -> Option<Arc<Library>> {
+    interpreter
+        .libraries
+        .read()
+        .unwrap()
+        .iter()
+        .rev()
+        .find(|library| library.name == name)
+        .cloned()
+}
+
+/// Finds a library by name, loading the file named after it the first time it is
+/// imported. Importing a library which is still being loaded is an import cycle
+fn load_library(
+    interpreter: &InterpreterContext,
+    name: Vec<String>,
+    span: Span,
+) -> InterpreterResult<Arc<Library>> {
+    {
+        let loading = interpreter.loading.read().unwrap();
+        if let Some(start) = loading.iter().position(|l| *l == name) {
+            let cycle = loading[start..]
+                .iter()
+                .chain([&name])
+                .map(|l| library_name(l))
+                .collect::<Vec<_>>();
+            return Err(InterpreterError::spanned(
+                InterpreterErrorKind::ImportCycle(cycle.join(" -> ")),
+                span,
+            ));
+        }
+    }
+
+    if let Some(library) = find_library(interpreter, &name) {
+        return Ok(library);
+    }
+
+    let file_path = locate(interpreter, library_name(&name), &name, span, &["sld", "scm"])?;
+
+    interpreter.loading.write().unwrap().push(name.clone());
+    let loaded = load_file(interpreter, file_path.clone(), span);
+    interpreter.loading.write().unwrap().pop();
+    loaded?;
+
+    find_library(interpreter, &name).ok_or_else(|| {
+        InterpreterError::spanned(
+            InterpreterErrorKind::LibraryNotDefined(
+                file_path.to_str().unwrap().to_string(),
+                library_name(&name),
+            ),
+            span,
+        )
+    })
+}
+
+/// The values of everything a library exports, under the names they are exported as
+fn exported(interpreter: &InterpreterContext, library: &Library) -> InterpreterResult<Imports> {
+    let bindings = library.bindings.read().unwrap();
+    let globals = interpreter.ident_mapping.read().unwrap();
+    library
+        .exports
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(internal, external)| {
+            match bindings.get(internal).or_else(|| globals.get(internal)) {
+                Some(p) => Ok((external.clone(), p.clone())),
+                None => Err(InterpreterError::spanned(
+                    InterpreterErrorKind::UndefinedExport(
+                        library_name(&library.name),
+                        internal.clone(),
+                    ),
+                    library.span,
+                )),
+            }
+        })
+        .collect()
+}
+
+/// Takes the entry named by `ast` out of `imports`, erroring if the set lacks it
+fn take_import(imports: &mut Imports, ast: &AST) -> InterpreterResult<(String, ObjectPointer)> {
+    let name = ident(ast).ok_or_else(|| invalid_set(ast))?;
+    match imports.iter().position(|(n, _)| n == name) {
+        Some(i) => Ok(imports.remove(i)),
+        None => Err(InterpreterError::spanned(
+            InterpreterErrorKind::NotExported(name.to_string()),
+            ast.span(),
+        )),
+    }
+}
+
+/// Resolves `(only set id ..)`, `(except set id ..)`, `(prefix set prefix)`,
+/// `(rename set (from to) ..)` or a library name to the bindings it imports
+fn import_set(interpreter: &InterpreterContext, set: &AST) -> InterpreterResult<Imports> {
+    if let AST::Operation(head, rest, _) = set {
+        if let (Some(op), [inner, args @ ..]) = (ident(head), rest.as_slice()) {
+            match op {
+                "only" => {
+                    let mut imports = import_set(interpreter, inner)?;
+                    return args.iter().map(|a| take_import(&mut imports, a)).collect();
+                }
+                "except" => {
+                    let mut imports = import_set(interpreter, inner)?;
+                    for a in args {
+                        take_import(&mut imports, a)?;
+                    }
+                    return Ok(imports);
+                }
+                "prefix" => {
+                    let [prefix] = args else {
+                        return Err(invalid_set(set));
+                    };
+                    let prefix = ident(prefix).ok_or_else(|| invalid_set(prefix))?;
+                    let imports = import_set(interpreter, inner)?;
+                    return Ok(imports
+                        .into_iter()
+                        .map(|(name, p)| (format!("{prefix}{name}"), p))
+                        .collect());
+                }
+                "rename" => {
+                    let mut imports = import_set(interpreter, inner)?;
+                    let mut renamed = Vec::new();
+                    for a in args {
+                        let AST::Operation(from, to, _) = a else {
+                            return Err(invalid_set(a));
+                        };
+                        let [to] = to.as_slice() else {
+                            return Err(invalid_set(a));
+                        };
+                        let to = ident(to).ok_or_else(|| invalid_set(to))?;
+                        let (_, p) = take_import(&mut imports, from)?;
+                        renamed.push((to.to_string(), p));
+                    }
+                    imports.extend(renamed);
+                    return Ok(imports);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    let name = parse_name(set).ok_or_else(|| invalid_set(set))?;
+    let library = load_library(interpreter, name, set.span())?;
+    exported(interpreter, &library)
+}
+
+/// `(import set ..)`, binding what each import set brings in, or the older
+/// `(import dir .. file)` which evaluates `dir/../file.scm` into the globals.
+/// Both look through the search path, see [`candidates`]
+pub fn import(interpreter: &InterpreterContext, ast: Vec<&AST>) -> InterpreterResult<()> {
+    if ast.is_empty() {
+        return Err(InterpreterError::new(InterpreterErrorKind::EmptyImport));
+    }
+
+    if ast.iter().all(|a| ident(a).is_some()) {
+        let total_span = ast.total_span().unwrap();
+        let parts = ast.iter().filter_map(|a| ident(a)).map(String::from).collect::<Vec<_>>();
+        let file_path = locate(interpreter, parts.join(" "), &parts, total_span, &["scm", "sld"])?;
+        return load_file(interpreter, file_path, total_span);
+    }
+
+    for set in ast {
+        for (name, p) in import_set(interpreter, set)? {
+            interpreter.define_global(&name, set.span(), p);
+        }
+    }
+    Ok(())
+}
+
+fn library_declarations(
+    interpreter: &InterpreterContext,
+    library: &Library,
+    declarations: &[&AST],
+) -> InterpreterResult<()> {
+    for declaration in declarations {
+        let AST::Operation(head, rest, _) = declaration else {
+            return Err(invalid_library(declaration));
+        };
+
+        match ident(head) {
+            Some("export") => {
+                let mut exports = library.exports.write().unwrap();
+                for spec in rest {
+                    let export = match spec {
+                        AST::Identifier(name, _) => (name.clone(), name.clone()),
+                        AST::Operation(op, names, _) => match (ident(op), names.as_slice()) {
+                            (Some("rename"), [from, to]) => match (ident(from), ident(to)) {
+                                (Some(from), Some(to)) => (from.to_string(), to.to_string()),
+                                _ => return Err(invalid_library(spec)),
+                            },
+                            _ => return Err(invalid_library(spec)),
+                        },
+                        e => return Err(invalid_library(e)),
+                    };
+                    exports.push(export);
+                }
+            }
+            Some("import") => {
+                for set in rest {
+                    let imports = import_set(interpreter, set)?;
+                    library.bindings.write().unwrap().extend(imports);
+                }
+            }
+            Some("begin") => {
+                eval_body(interpreter, rest)?;
+            }
+            _ => return Err(invalid_library(declaration)),
+        }
+    }
+
+    exported(interpreter, library).map(|_| ())
+}
+
+/// `(define-library (name ..) declaration ..)` with `export`, `import` and `begin`
+/// declarations. Names defined inside are bound in the library rather than globally
+pub fn define_library(interpreter: &InterpreterContext, ast: Vec<&AST>) -> InterpreterResult<()> {
+    if ast.is_empty() {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNOrMoreParams(1.., 0),
+        ));
+    }
+    let name = parse_name(ast[0]).ok_or_else(|| invalid_library(ast[0]))?;
+
+    let library = Library::new(name.clone(), ast.total_span().unwrap());
+    interpreter.libraries.write().unwrap().push(library.clone());
+    // A library loaded by name is already on the loading stack
+    let loaded_by_name = interpreter.loading.read().unwrap().last() == Some(&name);
+    if !loaded_by_name {
+        interpreter.loading.write().unwrap().push(name);
+    }
+
+    let result = library_declarations(interpreter, &library, &ast[1..]);
+
+    if !loaded_by_name {
+        interpreter.loading.write().unwrap().pop();
+    }
+    if result.is_err() {
+        interpreter
+            .libraries
+            .write()
+            .unwrap()
+            .retain(|l| !Arc::ptr_eq(l, &library));
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::{
+        testing::{builder, workspace},
+        Interpreter,
+    };
+
+    fn interpreter(dir: &Path) -> Interpreter {
+        Interpreter::from_context(builder().search_path(dir).build())
+    }
+
+    #[test]
+    fn failed_import_is_retried() {
+        let dir = workspace(
+            "import-retry",
+            &[(
+                "flaky.sld",
+                b"(define-library (flaky) (export x) (begin (define x (+ 1 y))))",
+            )],
+        );
+        let interpreter = interpreter(&dir);
+
+        assert!(interpreter.eval_str("(import (flaky))").is_err());
+        let x = interpreter
+            .eval_str("(define y 41) (import (flaky)) x")
+            .unwrap();
+        assert_eq!(x.get::<i64>(), Some(42));
+    }
+
+    #[test]
+    fn import_of_non_utf8_file_is_an_error() {
+        let dir = workspace("import-utf8", &[("bad.sld", b"\xff\xfe(define-library (bad))")]);
+        let err = interpreter(&dir).eval_str("(import (bad))").unwrap_err();
+        assert!(err.to_string().contains("bad.sld"), "{err}");
+    }
+
+    #[test]
+    fn file_libraries_import_file_libraries() {
+        let dir = workspace(
+            "import-nested",
+            &[
+                ("inner.sld", b"(define-library (inner) (export x) (begin (define x 2)))"),
+                (
+                    "outer.sld",
+                    b"(define-library (outer) (export y) (import (inner)) (begin (define y (* x 3))))",
+                ),
+            ],
+        );
+        let y = interpreter(&dir).eval_str("(import (outer)) y").unwrap();
+        assert_eq!(y.get::<i64>(), Some(6));
+    }
+
+    #[test]
+    fn import_cycles_between_files_are_errors() {
+        let dir = workspace(
+            "import-cycle",
+            &[
+                ("a.sld", b"(define-library (a) (export x) (import (b)) (begin (define x 1)))"),
+                ("b.sld", b"(define-library (b) (export y) (import (a)) (begin (define y 2)))"),
+            ],
+        );
+        let err = interpreter(&dir).eval_str("(import (a))").unwrap_err();
+        assert!(err.to_string().starts_with("Import cycle"), "{err}");
+    }
+
+    #[test]
+    fn search_path_directories_are_tried_in_order() {
+        let first = workspace(
+            "search-first",
+            &[("m.sld", b"(define-library (m) (export x) (begin (define x 1)))")],
+        );
+        let second = workspace(
+            "search-second",
+            &[
+                ("m.sld", b"(define-library (m) (export x) (begin (define x 2)))"),
+                ("n.sld", b"(define-library (n) (export y) (begin (define y 3)))"),
+            ],
+        );
+        let interpreter =
+            Interpreter::from_context(builder().search_path(&first).search_path(&second).build());
+        assert_eq!(interpreter.eval_str("(import (m)) x").unwrap().get::<i64>(), Some(1));
+        assert_eq!(interpreter.eval_str("(import (n)) y").unwrap().get::<i64>(), Some(3));
+    }
+
+    #[test]
+    fn libraries_prefer_sld_and_files_prefer_scm() {
+        let dir = workspace(
+            "search-extensions",
+            &[
+                ("both.sld", b"(define-library (both) (export x) (begin (define x 1)))"),
+                ("both.scm", b"(define x 2)"),
+            ],
+        );
+        let interpreter = interpreter(&dir);
+        assert_eq!(interpreter.eval_str("(import (both)) x").unwrap().get::<i64>(), Some(1));
+        assert_eq!(interpreter.eval_str("(import both) x").unwrap().get::<i64>(), Some(2));
+    }
+
+    #[test]
+    fn scheme_libraries_are_built_in() {
+        let dir = workspace("search-builtin", &[]);
+        let interpreter = interpreter(&dir);
+        let value = interpreter.eval_str("(import (only (scheme cxr) caddr)) (caddr (list 1 2 3))");
+        assert_eq!(value.unwrap().get::<i64>(), Some(3));
+    }
+
+    #[test]
+    fn missing_imports_list_every_location_tried() {
+        let dir = workspace("search-missing", &[]);
+        let err = interpreter(&dir).eval_str("(import (missing lib))").unwrap_err().to_string();
+        assert!(err.starts_with("Import '(missing lib)' cannot be found, tried:"), "{err}");
+        for extension in ["sld", "scm"] {
+            let path = dir.join("missing").join(format!("lib.{extension}"));
+            assert!(err.contains(&path.to_string_lossy().to_string()), "{err}");
+        }
+    }
+}
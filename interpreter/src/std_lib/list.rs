@@ -0,0 +1,395 @@
+use core::literal::{Literal, Numeric};
+use std::{ops::Deref, sync::Arc};
+
+use crate::{
+    comparison::InterpreterEquivalence,
+    deferred::Deferred,
+    deref::InterpreterDeref,
+    list::{InterpreterList, InterpreterListAlloc},
+    object::{HeapObject, ObjectPointer, ObjectRef, StackObject},
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::{
+    pop_params,
+    types::{expect_index, expect_list, expect_pair, expect_params, wrong_type},
+};
+
+type EqFn = fn(&ObjectPointer, &ObjectPointer, &InterpreterContext) -> InterpreterResult<bool>;
+
+fn push_pointer(interpreter: &InterpreterContext, p: ObjectPointer) {
+    interpreter.stack.push_data(StackObject::Ref(p));
+}
+
+fn push_bool(interpreter: &InterpreterContext, b: bool) {
+    interpreter
+        .stack
+        .push_data(StackObject::Value(Literal::Boolean(b)));
+}
+
+pub fn list(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    let list = pop_params(interpreter, n)?.to_list(interpreter)?;
+    push_pointer(interpreter, list);
+    Ok(())
+}
+
+pub fn is_list(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    let (_, tail) = params[0].list_spine(interpreter)?;
+    push_bool(interpreter, tail == ObjectPointer::Null);
+    Ok(())
+}
+
+pub fn is_pair(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    let pair = match params[0].deref(interpreter)? {
+        ObjectRef::Object(o) => matches!(o.deref(), HeapObject::List(_, _)),
+        _ => false,
+    };
+    push_bool(interpreter, pair);
+    Ok(())
+}
+
+pub fn is_null(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    let null = matches!(params[0].deref(interpreter)?, ObjectRef::Null);
+    push_bool(interpreter, null);
+    Ok(())
+}
+
+pub fn length(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    let len = expect_list(interpreter, "length", 1, &params[0])?.len();
+    interpreter
+        .stack
+        .push_data(StackObject::Value(Literal::Numeric(Numeric::Int(len as i32))));
+    Ok(())
+}
+
+/// Copies every list but the last, which becomes the shared tail
+pub fn append(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    let mut params = pop_params(interpreter, n)?;
+    let Some(tail) = params.pop() else {
+        push_pointer(interpreter, ObjectPointer::Null);
+        return Ok(());
+    };
+
+    let mut elements = Vec::new();
+    for (i, list) in params.iter().enumerate() {
+        elements.extend(expect_list(interpreter, "append", i + 1, list)?);
+    }
+    let list = elements.to_list_with_tail(tail, interpreter)?;
+    push_pointer(interpreter, list);
+    Ok(())
+}
+
+pub fn reverse(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    let mut elements = expect_list(interpreter, "reverse", 1, &params[0])?;
+    elements.reverse();
+    let list = elements.to_list(interpreter)?;
+    push_pointer(interpreter, list);
+    Ok(())
+}
+
+pub fn list_tail(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 2)?;
+    let params = pop_params(interpreter, n)?;
+    let k = expect_index(interpreter, "list-tail", 2, &params[1])?;
+    let (spine, tail) = params[0].list_spine(interpreter)?;
+
+    let out = match k.cmp(&spine.len()) {
+        std::cmp::Ordering::Less => spine[k].0.clone(),
+        std::cmp::Ordering::Equal => tail,
+        std::cmp::Ordering::Greater => {
+            return Err(InterpreterError::new(InterpreterErrorKind::IndexOutOfRange(
+                "list-tail".into(),
+                k,
+            )))
+        }
+    };
+    push_pointer(interpreter, out);
+    Ok(())
+}
+
+pub fn list_ref(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 2)?;
+    let params = pop_params(interpreter, n)?;
+    let k = expect_index(interpreter, "list-ref", 2, &params[1])?;
+    let (spine, _) = params[0].list_spine(interpreter)?;
+
+    let Some((_, head)) = spine.get(k) else {
+        return Err(InterpreterError::new(InterpreterErrorKind::IndexOutOfRange(
+            "list-ref".into(),
+            k,
+        )));
+    };
+    push_pointer(interpreter, head.clone());
+    Ok(())
+}
+
+/// Copies the pairs of a list, keeping the tail of an improper list
+pub fn list_copy(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    let (spine, tail) = params[0].list_spine(interpreter)?;
+    let list = spine
+        .into_iter()
+        .map(|(_, head)| head)
+        .collect::<Vec<_>>()
+        .to_list_with_tail(tail, interpreter)?;
+    push_pointer(interpreter, list);
+    Ok(())
+}
+
+pub fn last_pair(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    let (spine, _) = params[0].list_spine(interpreter)?;
+    let Some((pair, _)) = spine.last() else {
+        return Err(wrong_type(interpreter, "last-pair", 1, "a pair", &params[0]));
+    };
+    push_pointer(interpreter, pair.clone());
+    Ok(())
+}
+
+/// Calls `compare` with `x` and each key in turn, pushing the first matching result or `#f`
+fn compare_step(
+    interpreter: &InterpreterContext,
+    compare: ObjectPointer,
+    x: ObjectPointer,
+    candidates: Arc<Vec<(ObjectPointer, ObjectPointer)>>,
+    i: usize,
+) -> InterpreterResult<()> {
+    let Some((_, key)) = candidates.get(i) else {
+        push_bool(interpreter, false);
+        return Ok(());
+    };
+
+    interpreter.defer(Deferred::Apply(compare.clone(), vec![x.clone(), key.clone()]));
+    interpreter.defer(Deferred::then(move |interpreter| {
+        if interpreter.stack.pop_data()?.deref(interpreter)?.is_truthy() {
+            push_pointer(interpreter, candidates[i].0.clone());
+            Ok(())
+        } else {
+            compare_step(
+                interpreter,
+                compare.clone(),
+                x.clone(),
+                candidates.clone(),
+                i + 1,
+            )
+        }
+    }));
+    Ok(())
+}
+
+/// Pushes the result paired with the first key equal to `x`, or `#f`
+fn find_matching(
+    interpreter: &InterpreterContext,
+    x: ObjectPointer,
+    candidates: Vec<(ObjectPointer, ObjectPointer)>,
+    eq: EqFn,
+    compare: Option<ObjectPointer>,
+) -> InterpreterResult<()> {
+    if let Some(compare) = compare {
+        return compare_step(interpreter, compare, x, Arc::new(candidates), 0);
+    }
+
+    for (result, key) in candidates {
+        if eq(&x, &key, interpreter)? {
+            push_pointer(interpreter, result);
+            return Ok(());
+        }
+    }
+    push_bool(interpreter, false);
+    Ok(())
+}
+
+fn member_by(
+    interpreter: &InterpreterContext,
+    n: usize,
+    procedure: &str,
+    eq: EqFn,
+    allow_compare: bool,
+) -> InterpreterResult<()> {
+    if !(n == 2 || allow_compare && n == 3) {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, n),
+        ));
+    }
+
+    let mut params = pop_params(interpreter, n)?;
+    let compare = (n == 3).then(|| params.pop().unwrap());
+    let (spine, tail) = params[1].list_spine(interpreter)?;
+    if tail != ObjectPointer::Null {
+        return Err(wrong_type(interpreter, procedure, 2, "a list", &params[1]));
+    }
+
+    find_matching(interpreter, params[0].clone(), spine, eq, compare)
+}
+
+fn assoc_by(
+    interpreter: &InterpreterContext,
+    n: usize,
+    procedure: &str,
+    eq: EqFn,
+    allow_compare: bool,
+) -> InterpreterResult<()> {
+    if !(n == 2 || allow_compare && n == 3) {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, n),
+        ));
+    }
+
+    let mut params = pop_params(interpreter, n)?;
+    let compare = (n == 3).then(|| params.pop().unwrap());
+    let mut candidates = Vec::new();
+    for entry in expect_list(interpreter, procedure, 2, &params[1])? {
+        let (key, _) = expect_pair(interpreter, procedure, 2, &entry).map_err(|_| {
+            wrong_type(interpreter, procedure, 2, "an association list", &params[1])
+        })?;
+        candidates.push((entry, key));
+    }
+
+    find_matching(interpreter, params[0].clone(), candidates, eq, compare)
+}
+
+pub fn memq(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    member_by(interpreter, n, "memq", ObjectPointer::is_eq, false)
+}
+
+pub fn memv(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    member_by(interpreter, n, "memv", ObjectPointer::is_eqv, false)
+}
+
+pub fn member(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    member_by(interpreter, n, "member", ObjectPointer::is_equal, true)
+}
+
+pub fn assq(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    assoc_by(interpreter, n, "assq", ObjectPointer::is_eq, false)
+}
+
+pub fn assv(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    assoc_by(interpreter, n, "assv", ObjectPointer::is_eqv, false)
+}
+
+pub fn assoc(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    assoc_by(interpreter, n, "assoc", ObjectPointer::is_equal, true)
+}
+
+/// Applies a sequence of `car`s and `cdr`s, read right to left as in the procedure name
+fn cxr(interpreter: &InterpreterContext, n: usize, procedure: &str, path: &str) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+
+    let mut cur = params[0].clone();
+    for op in path.chars().rev() {
+        let next = match cur.deref(interpreter)? {
+            ObjectRef::Object(o) => match o.deref() {
+                HeapObject::List(h, _) if op == 'a' => Some(h.clone()),
+                HeapObject::List(_, t) => Some(t.clone()),
+                _ => None,
+            },
+            _ => None,
+        };
+        cur = next.ok_or_else(|| wrong_type(interpreter, procedure, 1, "a pair", &params[0]))?;
+    }
+    push_pointer(interpreter, cur);
+    Ok(())
+}
+
+macro_rules! cxr {
+    ($name:ident, $procedure:expr, $path:expr) => {
+        pub fn $name(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+            cxr(interpreter, n, $procedure, $path)
+        }
+    };
+}
+
+cxr!(caar, "caar", "aa");
+cxr!(cadr, "cadr", "ad");
+cxr!(cdar, "cdar", "da");
+cxr!(cddr, "cddr", "dd");
+cxr!(caaar, "caaar", "aaa");
+cxr!(caadr, "caadr", "aad");
+cxr!(cadar, "cadar", "ada");
+cxr!(caddr, "caddr", "add");
+cxr!(cdaar, "cdaar", "daa");
+cxr!(cdadr, "cdadr", "dad");
+cxr!(cddar, "cddar", "dda");
+cxr!(cdddr, "cdddr", "ddd");
+
+#[cfg(test)]
+mod test {
+    use crate::testing::eval;
+
+    #[test]
+    fn lists_are_built_and_taken_apart() {
+        assert_eq!(eval("(list 1 2 3)"), Ok("1:2:3:()".to_string()));
+        assert_eq!(eval("(length (list 1 2 3))"), Ok("3".to_string()));
+        assert_eq!(
+            eval("(append (list 1) (list 2 3) (list) (list 4))"),
+            Ok("1:2:3:4:()".to_string())
+        );
+        assert_eq!(eval("(append (list 1) 2)"), Ok("1:2".to_string()));
+        assert_eq!(eval("(append)"), Ok("()".to_string()));
+        assert_eq!(eval("(reverse (list 1 2 3))"), Ok("3:2:1:()".to_string()));
+        assert_eq!(eval("(list-tail (list 1 2 3) 1)"), Ok("2:3:()".to_string()));
+        assert_eq!(eval("(list-ref (list 1 2 3) 2)"), Ok("3".to_string()));
+        assert_eq!(eval("(list-copy (list 1 2))"), Ok("1:2:()".to_string()));
+        assert_eq!(eval("(last-pair (list 1 2 3))"), Ok("3:()".to_string()));
+    }
+
+    #[test]
+    fn list_predicates_check_the_whole_spine() {
+        assert_eq!(eval("(list? (list 1 2))"), Ok("true".to_string()));
+        assert_eq!(eval("(list? (cons 1 2))"), Ok("false".to_string()));
+        assert_eq!(eval("(pair? (cons 1 2))"), Ok("true".to_string()));
+        assert_eq!(eval("(pair? (list))"), Ok("false".to_string()));
+        assert_eq!(eval("(null? (list))"), Ok("true".to_string()));
+    }
+
+    #[test]
+    fn members_and_associations_use_their_equality() {
+        assert_eq!(eval("(memq 2 (list 1 2 3))"), Ok("2:3:()".to_string()));
+        assert_eq!(eval("(memv 1.5 (list 1 1.5 3))"), Ok("1.5:3:()".to_string()));
+        assert_eq!(eval("(member (list 1) (list 2 (list 1) 3))"), Ok("1:():3:()".to_string()));
+        assert_eq!(eval("(memq (list 1) (list 2 (list 1) 3))"), Ok("false".to_string()));
+        assert_eq!(
+            eval("(member 2 (list 1 2 3) (lambda (x y) (= (+ x 1) y)))"),
+            Ok("3:()".to_string())
+        );
+        let alist = "(list (cons \"a\" 1) (cons \"b\" 2))";
+        assert_eq!(eval(&format!("(assv 2 {alist})")), Ok("false".to_string()));
+        assert_eq!(eval("(assv 2 (list (cons 1 3) (cons 2 4)))"), Ok("2:4".to_string()));
+        assert_eq!(eval(&format!("(assoc \"b\" {alist})")), Ok("\"b\":2".to_string()));
+        assert_eq!(eval(&format!("(assq \"b\" {alist})")), Ok("false".to_string()));
+    }
+
+    #[test]
+    fn cxrs_read_right_to_left() {
+        assert_eq!(eval("(cadr (list 1 2 3))"), Ok("2".to_string()));
+        assert_eq!(eval("(cddr (list 1 2 3))"), Ok("3:()".to_string()));
+        assert_eq!(eval("(caddr (list 1 2 3))"), Ok("3".to_string()));
+        assert_eq!(eval("(caar (list (list 1) 2))"), Ok("1".to_string()));
+    }
+
+    #[test]
+    fn errors_name_the_procedure_and_argument() {
+        let error = |source| eval(source).unwrap_err();
+        assert!(error("(length 5)")
+            .contains("'length' expected a list as argument 1, received '5'"));
+        assert!(error("(length (cons 1 2))").contains("'length' expected a list as argument 1"));
+        assert!(error("(list-ref (list 1 2) 5)").contains("'list-ref' index 5 is out of range"));
+        assert!(error("(cadr (list 1))").contains("'cadr' expected a pair as argument 1"));
+        assert!(error("(assoc 1 (list 1 2))")
+            .contains("'assoc' expected an association list as argument 2"));
+    }
+}
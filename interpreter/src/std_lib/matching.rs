@@ -0,0 +1,501 @@
+use core::{
+    parser::ast::AST,
+    token::span::{Span, TotalSpan},
+};
+use std::{ops::Deref, sync::Arc};
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    comparison::InterpreterEquivalence,
+    deferred::Deferred,
+    deref::InterpreterDeref,
+    list::{InterpreterList, InterpreterListAlloc},
+    object::{HeapObject, ObjectPointer, ObjectRef, StackObject},
+    print::InterpreterPrint,
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::exception::{defer_body, thunk};
+
+/// The variables bound by a pattern, in the order they were matched
+type Bindings = Vec<(String, ObjectPointer)>;
+
+/// The results of the `?` predicates a clause has applied so far, in the order it
+/// reaches them. Matching is otherwise pure, so a clause reaching a predicate not yet
+/// applied stops there, to be tried again once it has been applied as deferred work
+#[derive(Default)]
+struct Predicates {
+    results: Vec<bool>,
+    reached: usize,
+    /// The predicate expression to apply next and the value to apply it to
+    pending: Option<(AST, ObjectPointer)>,
+}
+
+impl Predicates {
+    /// The result of the next predicate, or `None` once matching has to stop
+    fn next(&mut self, predicate: &AST, value: &ObjectPointer) -> Option<bool> {
+        if self.pending.is_some() {
+            return None;
+        }
+        let result = self.results.get(self.reached).copied();
+        match result {
+            Some(_) => self.reached += 1,
+            None => self.pending = Some((predicate.clone(), value.clone())),
+        }
+        result
+    }
+}
+
+fn invalid_pattern(ast: &AST) -> InterpreterError {
+    InterpreterError::spanned(
+        InterpreterErrorKind::InvalidMatchPattern(ast.to_string()),
+        ast.span(),
+    )
+}
+
+fn is_ident(ast: &AST, name: &str) -> bool {
+    matches!(ast, AST::Identifier(ident, _) if ident == name)
+}
+
+/// The elements of a list pattern, split around a `...` and a dotted tail
+struct ListPattern<'a> {
+    before: &'a [AST],
+    repeated: Option<&'a AST>,
+    after: &'a [AST],
+    tail: Option<&'a AST>,
+}
+
+impl<'a> ListPattern<'a> {
+    fn parse(items: &'a [AST]) -> InterpreterResult<Self> {
+        let (items, tail) = match items {
+            [rest @ .., AST::Rest(tail, _)] => (rest, Some(tail.deref())),
+            _ => (items, None),
+        };
+
+        let mut ellipses = items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| is_ident(item, "..."));
+        let pattern = match (ellipses.next(), ellipses.next()) {
+            (None, _) => ListPattern {
+                before: items,
+                repeated: None,
+                after: &[],
+                tail,
+            },
+            (Some((i, _)), None) if i > 0 => ListPattern {
+                before: &items[..i - 1],
+                repeated: Some(&items[i - 1]),
+                after: &items[i + 1..],
+                tail,
+            },
+            (Some(_), Some((_, e))) | (Some((_, e)), None) => return Err(invalid_pattern(e)),
+        };
+        Ok(pattern)
+    }
+}
+
+/// Collects the names a pattern binds, so that a repetition which matched nothing
+/// can still bind each of them to the empty list
+fn pattern_vars(pattern: &AST, vars: &mut Vec<String>) {
+    match pattern {
+        AST::Identifier(name, _) if !matches!(name.as_str(), "_" | "...") => {
+            if !vars.contains(name) {
+                vars.push(name.clone());
+            }
+        }
+        AST::Operation(head, rest, _) => match head.deref() {
+            AST::Identifier(op, _) if op == "quote" => (),
+            AST::Identifier(op, _) if op == "?" => {
+                rest.iter().skip(1).for_each(|p| pattern_vars(p, vars))
+            }
+            AST::Identifier(op, _) if matches!(op.as_str(), "and" | "or" | "not") => {
+                rest.iter().for_each(|p| pattern_vars(p, vars))
+            }
+            head => {
+                pattern_vars(head, vars);
+                rest.iter().for_each(|p| pattern_vars(p, vars));
+            }
+        },
+        AST::Rest(tail, _) => pattern_vars(tail, vars),
+        _ => (),
+    }
+}
+
+/// Builds the value of quoted data, nested forms becoming lists. Only the identifiers
+/// in it are looked up, nothing is called
+fn datum(interpreter: &InterpreterContext, ast: &AST) -> InterpreterResult<ObjectPointer> {
+    match ast {
+        AST::Operation(head, rest, _) => {
+            let (items, tail) = match rest.split_last() {
+                Some((AST::Rest(tail, _), items)) => (items, datum(interpreter, tail)?),
+                _ => (rest.as_slice(), ObjectPointer::Null),
+            };
+            let items = std::iter::once(head.deref())
+                .chain(items)
+                .map(|item| datum(interpreter, item))
+                .collect::<InterpreterResult<Vec<_>>>()?;
+            items.into_iter().rev().try_fold(tail, |tail, item| {
+                HeapObject::List(item, tail).heap_alloc(interpreter)
+            })
+        }
+        AST::List(head, tail, _) => {
+            HeapObject::List(datum(interpreter, head)?, datum(interpreter, tail)?)
+                .heap_alloc(interpreter)
+        }
+        AST::Identifier(ident, span) => interpreter.resolve_identifier(ident, *span),
+        AST::Literal(lit, _) => StackObject::Value(*lit).heap_alloc(interpreter),
+        AST::StringLiteral(s, _) => HeapObject::String(s.clone()).heap_alloc(interpreter),
+        AST::EmptyList(_) => Ok(ObjectPointer::Null),
+        ast => Err(invalid_pattern(ast)),
+    }
+}
+
+fn match_all(
+    interpreter: &InterpreterContext,
+    patterns: &[AST],
+    values: &[ObjectPointer],
+    bindings: &mut Bindings,
+    predicates: &mut Predicates,
+) -> InterpreterResult<bool> {
+    for (pattern, value) in patterns.iter().zip(values) {
+        if !match_pattern(interpreter, pattern, value, bindings, predicates)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Matches `repeated` against every value, binding each of its variables to the
+/// list of what it matched
+fn match_repeated(
+    interpreter: &InterpreterContext,
+    repeated: &AST,
+    values: &[ObjectPointer],
+    bindings: &mut Bindings,
+    predicates: &mut Predicates,
+) -> InterpreterResult<bool> {
+    let mut vars = Vec::new();
+    pattern_vars(repeated, &mut vars);
+
+    let mut matched = vec![Vec::new(); vars.len()];
+    for value in values {
+        let mut inner = Bindings::new();
+        if !match_pattern(interpreter, repeated, value, &mut inner, predicates)? {
+            return Ok(false);
+        }
+        for (var, matched) in vars.iter().zip(matched.iter_mut()) {
+            let value = inner.iter().find(|(name, _)| name == var).map(|(_, v)| v.clone());
+            matched.push(value.unwrap_or_default());
+        }
+    }
+
+    for (var, matched) in vars.into_iter().zip(matched) {
+        bindings.push((var, matched.to_list(interpreter)?));
+    }
+    Ok(true)
+}
+
+fn match_list(
+    interpreter: &InterpreterContext,
+    pattern: &ListPattern,
+    value: &ObjectPointer,
+    bindings: &mut Bindings,
+    predicates: &mut Predicates,
+) -> InterpreterResult<bool> {
+    let (spine, end) = value.list_spine(interpreter)?;
+    let fixed = pattern.before.len() + pattern.after.len();
+    if spine.len() < fixed {
+        return Ok(false);
+    }
+
+    // Without a repetition the fixed patterns take the front of the list and a dotted
+    // tail takes what is left, with one they take both ends and the tail the end
+    let taken = match (pattern.repeated, pattern.tail) {
+        (None, Some(_)) => fixed,
+        _ => spine.len(),
+    };
+    if pattern.repeated.is_none() && pattern.tail.is_none() && spine.len() != fixed {
+        return Ok(false);
+    }
+
+    let elements = spine[..taken].iter().map(|(_, head)| head.clone()).collect::<Vec<_>>();
+    let rest = spine.get(taken).map(|(pair, _)| pair.clone()).unwrap_or(end);
+
+    let (front, back) = elements.split_at(pattern.before.len());
+    let (middle, back) = back.split_at(back.len() - pattern.after.len());
+    if !match_all(interpreter, pattern.before, front, bindings, predicates)?
+        || !match_all(interpreter, pattern.after, back, bindings, predicates)?
+    {
+        return Ok(false);
+    }
+    if let Some(repeated) = pattern.repeated {
+        if !match_repeated(interpreter, repeated, middle, bindings, predicates)? {
+            return Ok(false);
+        }
+    }
+
+    match pattern.tail {
+        Some(tail) => match_pattern(interpreter, tail, &rest, bindings, predicates),
+        None => Ok(matches!(rest.deref(interpreter)?, ObjectRef::Null)),
+    }
+}
+
+/// Tries `pattern` against `value`, extending `bindings` as it goes. Bindings made
+/// before a failure are left for the caller to discard
+fn match_pattern(
+    interpreter: &InterpreterContext,
+    pattern: &AST,
+    value: &ObjectPointer,
+    bindings: &mut Bindings,
+    predicates: &mut Predicates,
+) -> InterpreterResult<bool> {
+    match pattern {
+        AST::Identifier(name, _) if name == "_" => Ok(true),
+        AST::Identifier(name, _) if name == "..." => Err(invalid_pattern(pattern)),
+        AST::Rest(..) => Err(invalid_pattern(pattern)),
+        AST::Identifier(name, _) => {
+            // A variable used twice must match equal values each time
+            if let Some((_, bound)) = bindings.iter().find(|(n, _)| n == name) {
+                return bound.is_equal(value, interpreter);
+            }
+            bindings.push((name.clone(), value.clone()));
+            Ok(true)
+        }
+        AST::Literal(lit, _) => {
+            StackObject::Value(*lit).is_equal(&StackObject::Ref(value.clone()), interpreter)
+        }
+        AST::StringLiteral(s, _) => Ok(matches!(
+            value.deref(interpreter)?,
+            ObjectRef::Object(o) if matches!(o.deref(), HeapObject::String(v) if v == s)
+        )),
+        AST::EmptyList(_) => Ok(matches!(value.deref(interpreter)?, ObjectRef::Null)),
+        AST::List(..) => datum(interpreter, pattern)?.is_equal(value, interpreter),
+        AST::Operation(head, rest, _) => match (head.deref(), rest.as_slice()) {
+            (AST::Identifier(op, _), [d]) if op == "quote" => {
+                datum(interpreter, d)?.is_equal(value, interpreter)
+            }
+            (AST::Identifier(op, _), [predicate, patterns @ ..]) if op == "?" => {
+                if predicates.next(predicate, value) != Some(true) {
+                    return Ok(false);
+                }
+                for pattern in patterns {
+                    if !match_pattern(interpreter, pattern, value, bindings, predicates)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            (AST::Identifier(op, _), patterns) if op == "and" => {
+                for pattern in patterns {
+                    if !match_pattern(interpreter, pattern, value, bindings, predicates)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            (AST::Identifier(op, _), patterns) if op == "or" => {
+                for pattern in patterns {
+                    let mut attempt = bindings.clone();
+                    if match_pattern(interpreter, pattern, value, &mut attempt, predicates)? {
+                        *bindings = attempt;
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            (AST::Identifier(op, _), [pattern]) if op == "not" => {
+                let mut attempt = bindings.clone();
+                Ok(!match_pattern(
+                    interpreter,
+                    pattern,
+                    value,
+                    &mut attempt,
+                    predicates,
+                )?)
+            }
+            (AST::Identifier(op, _), _) if matches!(op.as_str(), "quote" | "?" | "not") => {
+                Err(invalid_pattern(pattern))
+            }
+            _ => {
+                let items = std::iter::once(head.deref().clone())
+                    .chain(rest.iter().cloned())
+                    .collect::<Vec<_>>();
+                match_list(
+                    interpreter,
+                    &ListPattern::parse(&items)?,
+                    value,
+                    bindings,
+                    predicates,
+                )
+            }
+        },
+    }
+}
+
+/// Tries the clauses from `index` on, evaluating the body of the first to match `value`
+/// in the frame of the `match`. `results` are those of the predicates the clause at
+/// `index` has already applied
+fn match_clauses(
+    interpreter: &InterpreterContext,
+    clauses: Arc<Vec<AST>>,
+    mut index: usize,
+    value: ObjectPointer,
+    mut results: Vec<bool>,
+    span: Span,
+) -> InterpreterResult<()> {
+    while let Some(clause) = clauses.get(index) {
+        let AST::Operation(pattern, body, _) = clause else {
+            return Err(InterpreterError::spanned(
+                InterpreterErrorKind::InvalidMatchClause,
+                clause.span(),
+            ));
+        };
+
+        let mut bindings = Bindings::new();
+        let mut predicates = Predicates {
+            results,
+            ..Default::default()
+        };
+        let matched = match_pattern(interpreter, pattern, &value, &mut bindings, &mut predicates)?;
+
+        if let Some((predicate, arg)) = predicates.pending {
+            let results = predicates.results;
+            interpreter.defer(Deferred::Apply(thunk(interpreter, &predicate)?, Vec::new()));
+            interpreter.defer(Deferred::then(move |interpreter| {
+                let predicate = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+                interpreter.defer(Deferred::Apply(predicate, vec![arg.clone()]));
+                let (clauses, value, results) = (clauses.clone(), value.clone(), results.clone());
+                interpreter.defer(Deferred::then(move |interpreter| {
+                    let result = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+                    let mut results = results.clone();
+                    results.push(result.deref(interpreter)?.is_truthy());
+                    match_clauses(
+                        interpreter,
+                        clauses.clone(),
+                        index,
+                        value.clone(),
+                        results,
+                        span,
+                    )
+                }));
+                Ok(())
+            }));
+            return Ok(());
+        }
+
+        if !matched {
+            index += 1;
+            results = Vec::new();
+            continue;
+        }
+
+        let mut frame = interpreter.stack.top_frame()?;
+        for (name, value) in bindings {
+            frame.insert_local(&name, value);
+        }
+        drop(frame);
+        return defer_body(interpreter, body);
+    }
+
+    Err(InterpreterError::spanned(
+        InterpreterErrorKind::NoMatchingClause(value.interpreter_fmt(interpreter)),
+        span,
+    ))
+}
+
+/// `(match expr (pattern body ..) ..)`, evaluating the body of the first clause whose
+/// pattern matches the value of `expr` with the pattern's variables bound
+pub fn match_(interpreter: &InterpreterContext, ast: Vec<&AST>) -> InterpreterResult<()> {
+    if ast.is_empty() {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNOrMoreParams(1.., 0),
+        ));
+    }
+    let span = ast.total_span().unwrap();
+
+    interpreter.defer(Deferred::Apply(thunk(interpreter, ast[0])?, Vec::new()));
+    let clauses = Arc::new(
+        ast[1..]
+            .iter()
+            .map(|clause| (*clause).clone())
+            .collect::<Vec<_>>(),
+    );
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let value = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+        match_clauses(interpreter, clauses.clone(), 0, value, Vec::new(), span)
+    }));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::{eval, eval_recursion};
+
+    #[test]
+    fn dotted_patterns_match_the_rest() {
+        assert_eq!(
+            eval("(match (list 1 2 3) ((a . b) b))"),
+            Ok("2:3:()".to_string())
+        );
+        assert_eq!(
+            eval("(match (list 1 2 3) ((a ...) a))"),
+            Ok("1:2:3:()".to_string())
+        );
+        assert_eq!(
+            eval("(match (cons 1 2) ('(1 . 2) #t))"),
+            Ok("true".to_string())
+        );
+    }
+
+    #[test]
+    fn predicates_are_applied_in_order() {
+        let log = "(define seen (list))
+                   (define (note x) (match x (_ (define seen (cons x seen)) #t)))";
+        assert_eq!(
+            eval(&format!(
+                "{log} (match 5 ((and (? note) (? string?)) 1) ((? number? n) n)) "
+            )),
+            Ok("5".to_string())
+        );
+        assert_eq!(
+            eval(&format!(
+                "{log} (match (list 1 2) (((? note) (? note)) seen))"
+            )),
+            Ok("2:1:()".to_string())
+        );
+        assert_eq!(eval("(match 5 ((not (? string?)) 1))"), Ok("1".to_string()));
+        assert_eq!(
+            eval("(guard (e (#t e)) (match 5 ((? (lambda (x) (raise 7))) 1)))"),
+            Ok("7".to_string())
+        );
+    }
+
+    #[test]
+    fn quoted_patterns_are_not_evaluated() {
+        assert_eq!(
+            eval("(match (list 1 (list 2)) ('(1 (2)) #t))"),
+            Ok("true".to_string())
+        );
+        assert_eq!(eval("(match 3 ('(1) 0) (_ 1))"), Ok("1".to_string()));
+    }
+
+    #[test]
+    fn unmatched_values_are_errors() {
+        assert_eq!(
+            eval("(match 3 (1 0) (\"a\" 1))"),
+            Err("No match clause matches '3'".to_string())
+        );
+    }
+
+    #[test]
+    fn match_does_not_grow_the_native_stack() {
+        let f = "(define (len l acc) (match l (() acc) ((x . rest) (len rest (+ acc 1)))))
+                 (define (f n) (len (iota n) 0))";
+        assert_eq!(eval_recursion(f, 1000), Ok("1000".to_string()));
+
+        let f =
+            "(define (f n) (match n ((? (lambda (n) (if (= n 0) #t (= (f (- n 1)) 0)))) 0) (_ 1)))";
+        assert_eq!(eval_recursion(f, 500), Ok("0".to_string()));
+    }
+}
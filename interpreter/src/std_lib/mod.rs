@@ -0,0 +1,648 @@
+use core::literal::Numeric;
+use std::{io::Write, ops::Deref};
+
+use core::{literal::Literal, parser::ast::AST};
+
+use core::token::span::TotalSpan;
+
+use crate::comparison::InterpreterEquivalence;
+use crate::object::UnallocatedObject;
+use crate::print::InterpreterPrint;
+use crate::{
+    alloc::{InterpreterHeapAlloc, InterpreterStackAlloc},
+    deferred::Deferred,
+    deref::InterpreterDeref,
+    func::{Case, Func, MacroStep},
+    object::{HeapObject, ObjectPointer, ObjectRef, StackObject},
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+pub mod control;
+pub mod exception;
+pub mod higher_order;
+pub mod iteration;
+pub mod json;
+pub mod library;
+pub mod list;
+pub mod matching;
+pub mod numeric;
+pub mod ordering;
+pub mod parameter;
+pub mod process;
+pub mod promise;
+pub mod stream;
+pub mod time;
+pub mod types;
+pub mod values;
+
+use types::{expect_numeric, expect_pair, expect_string};
+
+/// The groups of native procedures an interpreter can be built with or without
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StdModule {
+    /// Pairs and lists, `car` to `assoc`
+    List,
+    /// `map`, `fold` and the other procedures taking procedures
+    HigherOrder,
+    /// Arithmetic and numeric comparison
+    Numeric,
+    /// Type predicates and string conversions
+    Types,
+    /// Equivalence, string and character comparison and sorting
+    Ordering,
+    /// `error`, `raise` and error objects
+    Exception,
+    /// Continuations, `dynamic-wind`, multiple values and parameters
+    Control,
+    /// Promises and streams
+    Lazy,
+    /// `json-read` and `json-write`
+    Json,
+    /// `write` and `file->string`
+    Io,
+    /// `command-line`, `exit`, environment variables and `process-run`
+    Process,
+    /// `current-second` and `current-jiffy`
+    Time,
+    /// `stack-trace` and `heap-dump`
+    Debug,
+}
+
+impl StdModule {
+    pub const ALL: &'static [StdModule] = &[
+        StdModule::List,
+        StdModule::HigherOrder,
+        StdModule::Numeric,
+        StdModule::Types,
+        StdModule::Ordering,
+        StdModule::Exception,
+        StdModule::Control,
+        StdModule::Lazy,
+        StdModule::Json,
+        StdModule::Io,
+        StdModule::Process,
+        StdModule::Time,
+        StdModule::Debug,
+    ];
+}
+
+/// Pops `n` parameters off the data stack, returned in the order they were passed
+pub fn pop_params(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<Vec<ObjectPointer>> {
+    let mut params = Vec::new();
+    for _ in 0..n {
+        params.push(interpreter.stack.pop_data()?.heap_alloc(interpreter)?);
+    }
+    params.reverse();
+    Ok(params)
+}
+
+pub fn stack_trace(interpreter: &InterpreterContext, _n: usize) -> InterpreterResult<()> {
+    interpreter.stack.stack_trace();
+    Ok(())
+}
+
+pub fn heap_dump(interpreter: &InterpreterContext, _n: usize) -> InterpreterResult<()> {
+    interpreter.heap.dump(&interpreter);
+    Ok(())
+}
+
+pub fn let_<'a>(
+    interpreter: &InterpreterContext,
+    ast: Vec<&'a AST>,
+    values: Vec<StackObject>,
+) -> InterpreterResult<MacroStep<'a>> {
+    if ast.len() != 2 {
+        return Err(InterpreterError::optional_span(
+            InterpreterErrorKind::InvalidLetStatement,
+            ast.total_span(),
+        ));
+    }
+
+    let bindings = ast[0];
+
+    let AST::Operation(first, others, _) = bindings else {
+        return Err(InterpreterError::spanned(
+            InterpreterErrorKind::InvalidLetBindingForm,
+            bindings.span(),
+        ));
+    };
+    let mut binding_list = vec![first.deref()];
+    binding_list.extend(others);
+
+    let named_bindings = binding_list
+        .into_iter()
+        .map(|b| match b {
+            AST::Operation(name, value, op_span) => {
+                let bind_name = match &**name {
+                    AST::Identifier(name, _) => name,
+                    e => {
+                        return Err(InterpreterError::spanned(
+                            InterpreterErrorKind::InvalidLetBindingName,
+                            e.span(),
+                        ))
+                    }
+                };
+
+                if value.len() > 1 {
+                    return Err(InterpreterError::spanned(
+                        InterpreterErrorKind::InvalidLetBindingForm,
+                        *op_span,
+                    ));
+                }
+
+                Ok((bind_name, &value[0]))
+            }
+            e => Err(InterpreterError::spanned(
+                InterpreterErrorKind::InvalidLetBindingForm,
+                e.span(),
+            )),
+        })
+        .collect::<InterpreterResult<Vec<_>>>()?;
+
+    // The values are evaluated as queued work, so that a continuation captured in one
+    // of them can return to the binding again
+    if values.is_empty() {
+        return Ok(MacroStep::Evaluate(
+            named_bindings.iter().map(|(_, value)| *value).collect(),
+        ));
+    }
+
+    let mut top_frame = interpreter.stack.top_frame()?;
+    for ((name, _), value) in named_bindings.into_iter().zip(values) {
+        top_frame.insert_local(name, value.heap_alloc(interpreter)?);
+    }
+
+    Ok(MacroStep::Expand(ast[1]))
+}
+
+pub fn if_macro<'a>(
+    interpreter: &InterpreterContext,
+    ast: Vec<&'a AST>,
+    values: Vec<StackObject>,
+) -> InterpreterResult<MacroStep<'a>> {
+    if ast.len() != 3 {
+        Err(InterpreterError::spanned(
+            InterpreterErrorKind::ExpectedNParams(3, ast.len()),
+            {
+                if let Some(span) = ast
+                    .iter()
+                    .skip(3)
+                    .map(|l| l.span())
+                    .reduce(|l, r| l.max_span(r))
+                {
+                    span
+                } else {
+                    ast.last().unwrap().span()
+                }
+            },
+        ))?
+    }
+
+    let Some(cond) = values.first() else {
+        return Ok(MacroStep::Evaluate(vec![ast[0]]));
+    };
+
+    if cond.deref(interpreter)?.is_truthy() {
+        Ok(MacroStep::Expand(ast[1]))
+    } else {
+        Ok(MacroStep::Expand(ast[2]))
+    }
+}
+
+/// Splits formals such as `(a b . rest)` into the names before the dot and the one
+/// after it, `invalid` building the error for anything which is not an identifier
+pub(crate) fn formals<'a>(
+    items: impl IntoIterator<Item = &'a AST>,
+    invalid: impl Fn(&AST) -> InterpreterError,
+) -> InterpreterResult<(Vec<String>, Option<String>)> {
+    let mut names = Vec::new();
+    for item in items {
+        match item {
+            AST::Identifier(name, _) => names.push(name.clone()),
+            AST::Rest(rest, _) => match rest.deref() {
+                AST::Identifier(rest, _) => return Ok((names, Some(rest.clone()))),
+                e => return Err(invalid(e)),
+            },
+            e => return Err(invalid(e)),
+        }
+    }
+    Ok((names, None))
+}
+
+/// A procedure taking `params`, and the rest of its arguments as a list if `rest` is
+/// given. Only a `case-lambda` clause can take a rest list, so it is made into one
+fn procedure(name: Option<String>, params: Vec<String>, rest: Option<String>, body: AST) -> Func {
+    match rest {
+        Some(_) => Func::CaseLambda(vec![Case { params, rest, body }]),
+        None => Func::Defined(name, params, body),
+    }
+}
+
+pub fn define(interpreter: &InterpreterContext, mut ast: Vec<&AST>) -> InterpreterResult<()> {
+    if ast.len() > 2 || ast.is_empty() {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, ast.len()),
+        ));
+    }
+
+    let mut ast = ast.drain(..);
+    match ast.next().unwrap() {
+        // Define a value
+        AST::Identifier(ident, span) => {
+            let value = exception::thunk(interpreter, ast.next().unwrap())?;
+            interpreter.defer(Deferred::Apply(value, Vec::new()));
+            let (ident, span) = (ident.clone(), *span);
+            interpreter.defer(Deferred::then(move |interpreter| {
+                let p = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+                interpreter.define_global(&ident, span, p);
+                Ok(())
+            }));
+        }
+        // Define a function
+        AST::Operation(op_name, op_params, _) => {
+            let AST::Identifier(op_name, op_span) = &**op_name else {
+                return Err(InterpreterError::new(InterpreterErrorKind::CannotCall(
+                    op_name.to_string(),
+                )));
+            };
+
+            let (param_names, rest) = formals(op_params, |e| {
+                InterpreterError::spanned(InterpreterErrorKind::InvalidFuncParamNames, e.span())
+            })?;
+            let p = HeapObject::Func(procedure(
+                Some(op_name.clone()),
+                param_names,
+                rest,
+                ast.next().unwrap().clone(),
+            ))
+            .heap_alloc(interpreter)?;
+            interpreter.define_global(op_name, *op_span, p);
+        }
+        e => {
+            return Err(InterpreterError::new(InterpreterErrorKind::CannotCall(
+                e.to_string(),
+            )))
+        }
+    };
+    Ok(())
+}
+
+pub fn lambda(interpreter: &InterpreterContext, mut ast: Vec<&AST>) -> InterpreterResult<()> {
+    if ast.len() > 2 || ast.is_empty() {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, ast.len()),
+        ));
+    }
+
+    let mut ast = ast.drain(..);
+    let (param_names, rest) = match ast.next().unwrap() {
+        AST::Identifier(ident, _) => (vec![ident.clone()], None),
+        AST::Operation(op_name, op_params, _) => {
+            formals(std::iter::once(op_name.deref()).chain(op_params), |e| {
+                InterpreterError::spanned(
+                    InterpreterErrorKind::IsNotParamName(e.to_string()),
+                    e.span(),
+                )
+            })?
+        }
+        AST::EmptyList(_) => (Vec::new(), None),
+        e => {
+            return Err(InterpreterError::new(InterpreterErrorKind::CannotCall(
+                e.to_string(),
+            )))
+        }
+    };
+
+    let obj = HeapObject::Func(procedure(None, param_names, rest, ast.next().unwrap().clone()))
+        .stack_alloc(interpreter)?;
+
+    interpreter.stack.push_data(obj);
+
+    Ok(())
+}
+
+pub fn write(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    let mut data = Vec::new();
+    for _ in 0..n {
+        data.push(interpreter.stack.pop_data()?);
+    }
+    data.reverse();
+    let line = data
+        .iter()
+        .map(|d| d.interpreter_fmt(interpreter))
+        .collect::<String>();
+
+    writeln!(interpreter.output.lock().unwrap(), "{line}").map_err(|err| {
+        InterpreterError::new(InterpreterErrorKind::CannotWriteOutput(err.to_string()))
+    })
+}
+
+macro_rules! bin_op {
+    ($name:ident, $l:ident, $r:ident, $calc:expr, $op:expr) => {
+        pub fn $name(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+            let mut objs = Vec::new();
+            for _ in 0..n {
+                objs.push(interpreter.stack.pop_data()?);
+            }
+            objs.reverse();
+
+            let Some(first) = objs.first() else {
+                return Err(InterpreterError::new(
+                    InterpreterErrorKind::ExpectedNOrMoreParams(1usize.., 0),
+                ));
+            };
+
+            let mut out = expect_numeric(interpreter, $op, 1, first)?;
+            for (i, obj) in objs.iter().enumerate().skip(1) {
+                let $l = out;
+                let $r = expect_numeric(interpreter, $op, i + 1, obj)?;
+                out = $calc;
+            }
+
+            interpreter
+                .stack
+                .push_data(StackObject::Value(Literal::Numeric(out)));
+
+            Ok(())
+        }
+    };
+}
+
+bin_op!(add, l, r, l + r, "+");
+bin_op!(sub, l, r, l - r, "-");
+bin_op!(mul, l, r, l * r, "*");
+bin_op!(div, l, r, l / r, "/");
+
+macro_rules! cmp_op {
+    ($name:ident, $l:ident, $r:ident, $i:ident, $calc:expr) => {
+        pub fn $name(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+            let mut objs = Vec::new();
+            for _ in 0..n {
+                objs.push(interpreter.stack.pop_data()?);
+            }
+            objs.reverse();
+
+            let drain = objs.windows(2);
+            let out = drain.fold(Ok(true), |out, objs| match out {
+                Ok(out) => Ok(out && {
+                    let $l = &objs[0];
+                    let $r = &objs[1];
+                    let $i = &interpreter;
+                    $calc
+                }),
+                e => e,
+            });
+            interpreter
+                .stack
+                .push_data(StackObject::Value(Literal::Boolean(out?)));
+
+            Ok(())
+        }
+    };
+}
+
+cmp_op!(eq, l, r, i, l.is_eq(r, i)?);
+cmp_op!(eqv, l, r, i, l.is_eqv(r, i)?);
+cmp_op!(equal, l, r, i, l.is_equal(r, i)?);
+
+macro_rules! num_cmp_op {
+    ($name:ident, $op:expr, $l:ident, $r:ident, $calc:expr) => {
+        pub fn $name(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+            let mut objs = Vec::new();
+            for _ in 0..n {
+                objs.push(interpreter.stack.pop_data()?);
+            }
+            objs.reverse();
+
+            let nums = objs
+                .iter()
+                .enumerate()
+                .map(|(i, obj)| expect_numeric(interpreter, $op, i + 1, obj))
+                .collect::<InterpreterResult<Vec<_>>>()?;
+
+            let out = nums.windows(2).all(|nums| {
+                let $l = nums[0];
+                let $r = nums[1];
+                $calc
+            });
+            interpreter
+                .stack
+                .push_data(StackObject::Value(Literal::Boolean(out)));
+
+            Ok(())
+        }
+    };
+}
+
+num_cmp_op!(num_eq, "=", l, r, l.cmp(&r).is_eq());
+num_cmp_op!(lt, "<", l, r, l.cmp(&r).is_lt());
+num_cmp_op!(lteq, "<=", l, r, l.cmp(&r).is_le());
+num_cmp_op!(gt, ">", l, r, l.cmp(&r).is_gt());
+num_cmp_op!(gteq, ">=", l, r, l.cmp(&r).is_ge());
+
+pub fn empty(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if n != 1 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(1, n),
+        ));
+    }
+
+    let val = {
+        let stack_object = interpreter.stack.pop_data()?;
+        let list = stack_object.deref(interpreter)?;
+        match list {
+            ObjectRef::Null => true,
+            _ => false,
+        }
+    };
+
+    interpreter
+        .stack
+        .push_data(StackObject::Value(Literal::Boolean(val)));
+    Ok(())
+}
+
+pub fn car(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if n != 1 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(1, n),
+        ));
+    }
+
+    let p = {
+        let stack_object = interpreter.stack.pop_data()?;
+        let (head, _) = expect_pair(interpreter, "car", 1, &stack_object)?;
+        head
+    };
+    let p = p.stack_alloc(interpreter)?;
+    interpreter.stack.push_data(p);
+    Ok(())
+}
+
+pub fn cdr(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if n != 1 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(1, n),
+        ));
+    }
+
+    let p = {
+        let stack_object = interpreter.stack.pop_data()?;
+        let (_, tail) = expect_pair(interpreter, "cdr", 1, &stack_object)?;
+        tail
+    };
+    let p = p.stack_alloc(interpreter)?;
+    interpreter.stack.push_data(p);
+    Ok(())
+}
+
+pub fn cons(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if n != 2 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, n),
+        ));
+    }
+
+    let ptr = {
+        let tail = interpreter.stack.pop_data()?;
+        let head = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+
+        UnallocatedObject::List(head, tail.heap_alloc(interpreter)?).stack_alloc(interpreter)?
+    };
+
+    interpreter.stack.push_data(ptr);
+
+    Ok(())
+}
+
+pub fn file_to_string(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if n != 1 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, n),
+        ));
+    }
+
+    let file_name = interpreter.stack.pop_data()?;
+
+    let contents = {
+        let file_name = expect_string(interpreter, "file->string", 1, &file_name)?;
+
+        interpreter.capabilities.check_read(file_name.as_ref())?;
+        std::fs::read_to_string(&file_name).map_err(|_| {
+            InterpreterError::new(InterpreterErrorKind::CannotOpenFile(file_name))
+        })?
+    };
+
+    let obj = UnallocatedObject::String(contents).stack_alloc(interpreter)?;
+    interpreter.stack.push_data(obj);
+
+    Ok(())
+}
+
+pub fn string_to_chars(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if n != 1 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, n),
+        ));
+    }
+
+    let string = {
+        let s = interpreter.stack.pop_data()?;
+        expect_string(interpreter, "string->chars", 1, &s)?
+    };
+
+    let chars = string.chars();
+
+    let head = chars
+        .rev()
+        .fold(Ok(ObjectPointer::Null), |tail, next| match tail {
+            Ok(tail_pointer) => {
+                let head =
+                    UnallocatedObject::Value(Literal::Character(next)).heap_alloc(interpreter)?;
+                UnallocatedObject::List(head, tail_pointer).heap_alloc(interpreter)
+            }
+            e => e,
+        })?;
+
+    interpreter.stack.push_data(head.stack_alloc(interpreter)?);
+
+    Ok(())
+}
+
+macro_rules! string_parse {
+    ($name:ident,$procedure:expr,$ty:ident,$out:ident,$out_alloc:expr,$ty_name:expr) => {
+        pub fn $name(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+            if n != 1 {
+                return Err(InterpreterError::new(
+                    InterpreterErrorKind::ExpectedNParams(2, n),
+                ));
+            }
+
+            let string = {
+                let s = interpreter.stack.pop_data()?;
+                expect_string(interpreter, $procedure, 1, &s)?
+            };
+
+            let $out = string.parse::<$ty>().map_err(|_| {
+                InterpreterError::new(InterpreterErrorKind::CannotConvertType(
+                    "String".into(),
+                    $ty_name.into(),
+                ))
+            })?;
+
+            interpreter.stack.push_data($out_alloc);
+
+            Ok(())
+        }
+    };
+}
+
+string_parse!(
+    string_to_int,
+    "string->int",
+    i32,
+    v,
+    StackObject::Value(Literal::Numeric(Numeric::Int(v))),
+    "Int"
+);
+
+string_parse!(
+    string_to_float,
+    "string->float",
+    f32,
+    v,
+    StackObject::Value(Literal::Numeric(Numeric::Float(v))),
+    "Int"
+);
+
+#[cfg(test)]
+mod test {
+    use crate::testing::eval;
+
+    #[test]
+    fn dotted_formals_take_the_rest() {
+        assert_eq!(eval("(define (f a . b) b) (f 1 2 3)"), Ok("2:3:()".to_string()));
+        assert_eq!(eval("(define (f a . b) b) (f 1)"), Ok("()".to_string()));
+        assert_eq!(eval("((lambda (a b . c) (list a b c)) 1 2 3)"), Ok("1:2:3:():()".to_string()));
+        assert!(eval("(define (f a . b) b) (f)").is_err());
+    }
+
+    #[test]
+    fn dotted_case_lambda_clauses_take_the_rest() {
+        let f = "(define f (case-lambda ((a) 'one) ((a b . rest) rest)))";
+        assert_eq!(eval(&format!("{f} (f 1 2 3)")), Ok("3:()".to_string()));
+        assert_eq!(eval(&format!("{f} (f 1 2)")), Ok("()".to_string()));
+        assert_eq!(
+            eval("((case-lambda ((a b . rest) rest)) 1)"),
+            Err("Procedure accepts 2+ parameters, received 1".to_string())
+        );
+    }
+
+    #[test]
+    fn dotted_forms_are_not_evaluated() {
+        assert!(eval("(define . 5)").is_err());
+        assert!(eval("(+ 1 . 2)").is_err());
+        assert_eq!(eval("(car (cdr '(1 2 . 3)))"), Ok("2".to_string()));
+        assert_eq!(eval("(cdr (cdr '(1 2 . 3)))"), Ok("3".to_string()));
+    }
+}
@@ -0,0 +1,70 @@
+use core::literal::{Literal, Numeric};
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    object::{HeapObject, ObjectPointer},
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::{
+    pop_params,
+    types::{expect_index, expect_integer, expect_params},
+    values::push_values,
+};
+
+fn int(interpreter: &InterpreterContext, i: i32) -> InterpreterResult<ObjectPointer> {
+    HeapObject::Value(Literal::Numeric(Numeric::Int(i))).heap_alloc(interpreter)
+}
+
+/// Pops the integer dividend and non-zero divisor of a division
+fn division_params(
+    interpreter: &InterpreterContext,
+    n: usize,
+    procedure: &'static str,
+) -> InterpreterResult<(i32, i32)> {
+    expect_params(n, 2)?;
+    let params = pop_params(interpreter, n)?;
+    let n1 = expect_integer(interpreter, procedure, 1, &params[0])?;
+    let n2 = expect_integer(interpreter, procedure, 2, &params[1])?;
+    if n2 == 0 {
+        return Err(InterpreterError::new(InterpreterErrorKind::DivisionByZero(
+            procedure,
+        )));
+    }
+    Ok((n1, n2))
+}
+
+/// `(floor/ n1 n2)`, the quotient rounded towards negative infinity and its remainder
+pub fn floor_div(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    let (n1, n2) = division_params(interpreter, n, "floor/")?;
+    let mut q = n1 / n2;
+    if n1 % n2 != 0 && (n1 < 0) != (n2 < 0) {
+        q -= 1;
+    }
+    let values = vec![int(interpreter, q)?, int(interpreter, n1 - n2 * q)?];
+    push_values(interpreter, values)
+}
+
+/// `(truncate/ n1 n2)`, the quotient rounded towards zero and its remainder
+pub fn truncate_div(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    let (n1, n2) = division_params(interpreter, n, "truncate/")?;
+    let values = vec![int(interpreter, n1 / n2)?, int(interpreter, n1 % n2)?];
+    push_values(interpreter, values)
+}
+
+/// `(exact-integer-sqrt k)`, the largest `s` with `s * s <= k` and the rest `k - s * s`
+pub fn exact_integer_sqrt(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    let k = expect_index(interpreter, "exact-integer-sqrt", 1, &params[0])? as i64;
+
+    let mut s = (k as f64).sqrt() as i64;
+    while s * s > k {
+        s -= 1;
+    }
+    while (s + 1) * (s + 1) <= k {
+        s += 1;
+    }
+    let values = vec![int(interpreter, s as i32)?, int(interpreter, (k - s * s) as i32)?];
+    push_values(interpreter, values)
+}
@@ -0,0 +1,310 @@
+use core::literal::{Literal, Numeric};
+use std::{
+    cmp::Ordering,
+    collections::VecDeque,
+    mem,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    comparison::InterpreterComparison,
+    deferred::Deferred,
+    deref::InterpreterDeref,
+    list::InterpreterListAlloc,
+    object::{ObjectPointer, StackObject},
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::{
+    pop_params,
+    types::{expect_char, expect_list, expect_params, expect_procedure, expect_string},
+};
+
+type ExtractFn<T> = fn(&InterpreterContext, &str, usize, &ObjectPointer) -> InterpreterResult<T>;
+
+/// Checks that `test` holds for the ordering of every neighbouring pair of arguments
+fn chain_by<T: Ord>(
+    interpreter: &InterpreterContext,
+    n: usize,
+    procedure: &str,
+    extract: ExtractFn<T>,
+    test: fn(Ordering) -> bool,
+) -> InterpreterResult<()> {
+    let keys = pop_params(interpreter, n)?
+        .iter()
+        .enumerate()
+        .map(|(i, p)| extract(interpreter, procedure, i + 1, p))
+        .collect::<InterpreterResult<Vec<_>>>()?;
+
+    let out = keys.windows(2).all(|keys| test(keys[0].cmp(&keys[1])));
+    interpreter
+        .stack
+        .push_data(StackObject::Value(Literal::Boolean(out)));
+    Ok(())
+}
+
+fn string_key(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    p: &ObjectPointer,
+) -> InterpreterResult<String> {
+    expect_string(interpreter, procedure, arg_index, p)
+}
+
+fn string_ci_key(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    p: &ObjectPointer,
+) -> InterpreterResult<String> {
+    Ok(expect_string(interpreter, procedure, arg_index, p)?.to_lowercase())
+}
+
+fn char_key(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    p: &ObjectPointer,
+) -> InterpreterResult<char> {
+    expect_char(interpreter, procedure, arg_index, p)
+}
+
+fn char_ci_key(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    p: &ObjectPointer,
+) -> InterpreterResult<char> {
+    let c = expect_char(interpreter, procedure, arg_index, p)?;
+    Ok(c.to_lowercase().next().unwrap_or(c))
+}
+
+macro_rules! chain_cmp {
+    ($name:ident, $procedure:expr, $extract:expr, $test:ident) => {
+        pub fn $name(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+            chain_by(interpreter, n, $procedure, $extract, Ordering::$test)
+        }
+    };
+}
+
+chain_cmp!(string_eq, "string=?", string_key, is_eq);
+chain_cmp!(string_lt, "string<?", string_key, is_lt);
+chain_cmp!(string_gt, "string>?", string_key, is_gt);
+chain_cmp!(string_lteq, "string<=?", string_key, is_le);
+chain_cmp!(string_gteq, "string>=?", string_key, is_ge);
+
+chain_cmp!(string_ci_eq, "string-ci=?", string_ci_key, is_eq);
+chain_cmp!(string_ci_lt, "string-ci<?", string_ci_key, is_lt);
+chain_cmp!(string_ci_gt, "string-ci>?", string_ci_key, is_gt);
+chain_cmp!(string_ci_lteq, "string-ci<=?", string_ci_key, is_le);
+chain_cmp!(string_ci_gteq, "string-ci>=?", string_ci_key, is_ge);
+
+chain_cmp!(char_eq, "char=?", char_key, is_eq);
+chain_cmp!(char_lt, "char<?", char_key, is_lt);
+chain_cmp!(char_gt, "char>?", char_key, is_gt);
+chain_cmp!(char_lteq, "char<=?", char_key, is_le);
+chain_cmp!(char_gteq, "char>=?", char_key, is_ge);
+
+chain_cmp!(char_ci_eq, "char-ci=?", char_ci_key, is_eq);
+chain_cmp!(char_ci_lt, "char-ci<?", char_ci_key, is_lt);
+chain_cmp!(char_ci_gt, "char-ci>?", char_ci_key, is_gt);
+chain_cmp!(char_ci_lteq, "char-ci<=?", char_ci_key, is_le);
+chain_cmp!(char_ci_gteq, "char-ci>=?", char_ci_key, is_ge);
+
+/// `(compare a b)`, returning -1, 0 or 1. Numbers, characters, booleans and strings
+/// compare against their own kind and lists compare lexicographically
+pub fn compare(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 2)?;
+    let params = pop_params(interpreter, n)?;
+    let ordering = params[0].object_cmp(&params[1], interpreter)?;
+    interpreter
+        .stack
+        .push_data(StackObject::Value(Literal::Numeric(Numeric::Int(ordering as i32))));
+    Ok(())
+}
+
+/// A bottom up merge sort which hands each comparison back to the interpreter,
+/// merging neighbouring runs pass by pass so that equal elements keep their order
+struct MergeSort {
+    less: ObjectPointer,
+    runs: VecDeque<Vec<ObjectPointer>>,
+    next_pass: Vec<Vec<ObjectPointer>>,
+    left: VecDeque<ObjectPointer>,
+    right: VecDeque<ObjectPointer>,
+    merged: Vec<ObjectPointer>,
+}
+
+impl MergeSort {
+    /// Advances until a comparison is needed, returning the arguments to `less`,
+    /// or `None` once the list is sorted
+    fn advance(&mut self) -> Option<(ObjectPointer, ObjectPointer)> {
+        loop {
+            if let (Some(l), Some(r)) = (self.left.front(), self.right.front()) {
+                return Some((r.clone(), l.clone()));
+            }
+
+            if !self.left.is_empty() || !self.right.is_empty() || !self.merged.is_empty() {
+                let mut merged = mem::take(&mut self.merged);
+                merged.extend(self.left.drain(..));
+                merged.extend(self.right.drain(..));
+                self.next_pass.push(merged);
+            }
+
+            match (self.runs.pop_front(), self.runs.pop_front()) {
+                (Some(left), Some(right)) => {
+                    self.left = left.into();
+                    self.right = right.into();
+                }
+                (Some(last), None) => self.next_pass.push(last),
+                _ => {
+                    if self.next_pass.len() <= 1 {
+                        return None;
+                    }
+                    self.runs = mem::take(&mut self.next_pass).into();
+                }
+            }
+        }
+    }
+
+    /// Takes from the right run when `(less right left)` held, otherwise from the left
+    fn take(&mut self, right_first: bool) {
+        let next = if right_first {
+            self.right.pop_front()
+        } else {
+            self.left.pop_front()
+        };
+        self.merged.extend(next);
+    }
+
+    fn take_sorted(&mut self) -> Vec<ObjectPointer> {
+        self.next_pass.pop().unwrap_or_default()
+    }
+}
+
+fn sort_step(interpreter: &InterpreterContext, state: Arc<Mutex<MergeSort>>) -> InterpreterResult<()> {
+    let (next, less) = {
+        let mut sort = state.lock().unwrap();
+        (sort.advance(), sort.less.clone())
+    };
+
+    let Some((r, l)) = next else {
+        let sorted = state.lock().unwrap().take_sorted();
+        let list = sorted.to_list(interpreter)?;
+        interpreter.stack.push_data(StackObject::Ref(list));
+        return Ok(());
+    };
+
+    interpreter.defer(Deferred::Apply(less, vec![r, l]));
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let right_first = interpreter.stack.pop_data()?.deref(interpreter)?.is_truthy();
+        state.lock().unwrap().take(right_first);
+        sort_step(interpreter, state.clone())
+    }));
+    Ok(())
+}
+
+fn sort_by(
+    interpreter: &InterpreterContext,
+    elements: Vec<ObjectPointer>,
+    less: Option<ObjectPointer>,
+) -> InterpreterResult<()> {
+    let Some(less) = less else {
+        let mut result = Ok(());
+        let mut sorted = elements;
+        sorted.sort_by(|l, r| {
+            l.object_cmp(r, interpreter).unwrap_or_else(|e| {
+                result = Err(e);
+                Ordering::Equal
+            })
+        });
+        result?;
+
+        let list = sorted.to_list(interpreter)?;
+        interpreter.stack.push_data(StackObject::Ref(list));
+        return Ok(());
+    };
+
+    let state = MergeSort {
+        less,
+        runs: elements.into_iter().map(|x| vec![x]).collect(),
+        next_pass: Vec::new(),
+        left: VecDeque::new(),
+        right: VecDeque::new(),
+        merged: Vec::new(),
+    };
+    sort_step(interpreter, Arc::new(Mutex::new(state)))
+}
+
+/// `(sort list [less?])`, a stable sort falling back to `compare` without a procedure
+pub fn sort(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if !(1..=2).contains(&n) {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, n),
+        ));
+    }
+
+    let mut params = pop_params(interpreter, n)?;
+    let less = params.get(1).cloned();
+    if let Some(less) = &less {
+        expect_procedure(interpreter, "sort", 2, less)?;
+    }
+    let elements = expect_list(interpreter, "sort", 1, &params.remove(0))?;
+    sort_by(interpreter, elements, less)
+}
+
+/// `(list-sort less? list)`
+pub fn list_sort(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 2)?;
+    let params = pop_params(interpreter, n)?;
+    expect_procedure(interpreter, "list-sort", 1, &params[0])?;
+    let elements = expect_list(interpreter, "list-sort", 2, &params[1])?;
+    sort_by(interpreter, elements, Some(params[0].clone()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::eval;
+
+    #[test]
+    fn numeric_orderings_chain_and_reject_other_types() {
+        assert_eq!(eval("(< 1 2 3)"), Ok("true".to_string()));
+        assert_eq!(eval("(< 1 3 2)"), Ok("false".to_string()));
+        assert_eq!(eval("(>= 3 3 1)"), Ok("true".to_string()));
+        assert!(eval("(< \"a\" \"b\")")
+            .unwrap_err()
+            .contains("'<' expected a number as argument 1"));
+    }
+
+    #[test]
+    fn strings_and_characters_compare_with_and_without_case() {
+        assert_eq!(eval("(string<? \"abc\" \"abd\")"), Ok("true".to_string()));
+        assert_eq!(eval("(string<? \"a\" \"b\" \"c\")"), Ok("true".to_string()));
+        assert_eq!(eval("(string=? \"a\" \"A\")"), Ok("false".to_string()));
+        assert_eq!(eval("(string-ci=? \"AbC\" \"abc\")"), Ok("true".to_string()));
+        assert_eq!(eval("(string-ci<? \"a\" \"B\")"), Ok("true".to_string()));
+        assert_eq!(eval("(char<? #\\a #\\b)"), Ok("true".to_string()));
+        assert_eq!(eval("(char-ci=? #\\a #\\A)"), Ok("true".to_string()));
+    }
+
+    #[test]
+    fn compare_orders_within_a_kind() {
+        assert_eq!(eval("(compare (list 1 2) (list 1 3))"), Ok("-1".to_string()));
+        assert_eq!(eval("(compare \"b\" \"a\")"), Ok("1".to_string()));
+        assert_eq!(eval("(compare 1 1)"), Ok("0".to_string()));
+        assert!(eval("(compare 1 \"a\")").unwrap_err().contains("Cannot compare"));
+    }
+
+    #[test]
+    fn sorts_are_stable() {
+        assert_eq!(eval("(sort (list 3 1 2))"), Ok("1:2:3:()".to_string()));
+        assert_eq!(eval("(list-sort < (list 3 1 2))"), Ok("1:2:3:()".to_string()));
+        assert_eq!(eval("(sort (list \"b\" \"a\") string<?)"), Ok("\"a\":\"b\":()".to_string()));
+        assert_eq!(
+            eval("(sort (list (cons 1 \"a\") (cons 0 \"b\") (cons 1 \"c\"))
+                        (lambda (x y) (< (car x) (car y))))"),
+            Ok("0:\"b\":1:\"a\":1:\"c\":()".to_string())
+        );
+        assert_eq!(eval("(car (sort (iota 2000) >))"), Ok("1999".to_string()));
+    }
+}
@@ -0,0 +1,178 @@
+use core::parser::ast::AST;
+use std::ops::Deref;
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    deferred::Deferred,
+    deref::InterpreterDeref,
+    func::Func,
+    object::{HeapObject, ObjectPointer, ObjectRef, StackObject},
+    parameter::Parameter,
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::{
+    exception::{defer_body, thunk},
+    pop_params,
+    types::{expect_procedure, wrong_type},
+};
+
+fn expect_parameter(
+    interpreter: &InterpreterContext,
+    obj: &ObjectPointer,
+) -> InterpreterResult<Parameter> {
+    if let ObjectRef::Object(o) = obj.deref(interpreter)? {
+        if let HeapObject::Func(Func::Parameter(p)) = o.deref() {
+            return Ok(p.clone());
+        }
+    }
+    Err(wrong_type(
+        interpreter,
+        "parameterize",
+        1,
+        "a parameter",
+        obj,
+    ))
+}
+
+/// `(make-parameter value [converter])`, the converter applied to the initial value
+/// and to every value given by `parameterize`
+pub fn make_parameter(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if !(1..=2).contains(&n) {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(1, n),
+        ));
+    }
+    let mut params = pop_params(interpreter, n)?;
+    let value = params.remove(0);
+
+    let Some(converter) = params.pop() else {
+        let p = HeapObject::Func(Func::Parameter(Parameter::new(value, None)))
+            .heap_alloc(interpreter)?;
+        interpreter.stack.push_data(StackObject::Ref(p));
+        return Ok(());
+    };
+
+    expect_procedure(interpreter, "make-parameter", 2, &converter)?;
+    interpreter.defer(Deferred::Apply(converter.clone(), vec![value]));
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let value = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+        let parameter = Parameter::new(value, Some(converter.clone()));
+        let p = HeapObject::Func(Func::Parameter(parameter)).heap_alloc(interpreter)?;
+        interpreter.stack.push_data(StackObject::Ref(p));
+        Ok(())
+    }));
+    Ok(())
+}
+
+/// Splits `((param value) ..)` into its bindings
+fn parameter_bindings(bindings: &AST) -> InterpreterResult<Vec<(&AST, &AST)>> {
+    let list = match bindings {
+        AST::EmptyList(_) => return Ok(Vec::new()),
+        AST::Operation(first, rest, _) => std::iter::once(first.deref()).chain(rest),
+        e => {
+            return Err(InterpreterError::spanned(
+                InterpreterErrorKind::InvalidParameterizeForm,
+                e.span(),
+            ))
+        }
+    };
+
+    list.map(|binding| match binding {
+        AST::Operation(param, value, _) if value.len() == 1 => Ok((param.deref(), &value[0])),
+        e => Err(InterpreterError::spanned(
+            InterpreterErrorKind::InvalidParameterizeForm,
+            e.span(),
+        )),
+    })
+    .collect()
+}
+
+/// `(parameterize ((param value) ..) body ..)`, binding each parameter to its converted
+/// value for the dynamic extent of the body. Exceptions and continuations leaving the
+/// body restore the bindings along with the rest of the dynamic state
+pub fn parameterize(interpreter: &InterpreterContext, ast: Vec<&AST>) -> InterpreterResult<()> {
+    if ast.len() < 2 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNOrMoreParams(2.., ast.len()),
+        ));
+    }
+
+    let bindings = parameter_bindings(ast[0])?;
+    for (param, value) in &bindings {
+        interpreter.defer(Deferred::Apply(thunk(interpreter, param)?, Vec::new()));
+        interpreter.defer(Deferred::Apply(thunk(interpreter, value)?, Vec::new()));
+        interpreter.defer(Deferred::then(|interpreter| {
+            let value = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+            let param = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+            let parameter = expect_parameter(interpreter, &param)?;
+            interpreter.stack.push_data(StackObject::Ref(param));
+            match parameter.converter() {
+                Some(converter) => {
+                    interpreter.defer(Deferred::Apply(converter.clone(), vec![value]))
+                }
+                None => interpreter.stack.push_data(StackObject::Ref(value)),
+            }
+            Ok(())
+        }));
+    }
+
+    let n = bindings.len();
+    let depth = interpreter.parameters.read().unwrap().len();
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let mut parameters = Vec::new();
+        for _ in 0..n {
+            let value = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+            let param = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+            parameters.push((expect_parameter(interpreter, &param)?, value));
+        }
+        parameters.reverse();
+        interpreter.parameters.write().unwrap().extend(parameters);
+        Ok(())
+    }));
+
+    defer_body(interpreter, ast[1..].iter().copied())?;
+
+    interpreter.defer(Deferred::then(move |interpreter| {
+        interpreter.parameters.write().unwrap().truncate(depth);
+        Ok(())
+    }));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::{eval, eval_recursion};
+
+    #[test]
+    fn parameterize_rebinds_for_the_body() {
+        let p = "(define p (make-parameter 1))";
+        assert_eq!(eval(&format!("{p} (parameterize ((p 2)) (p))")), Ok("2".to_string()));
+        assert_eq!(eval(&format!("{p} (parameterize ((p 2)) (p)) (p)")), Ok("1".to_string()));
+        assert_eq!(
+            eval("(define p (make-parameter 1 (lambda (x) (* x 10)))) (list (p) (parameterize ((p 2)) (p)))"),
+            Ok("10:20:()".to_string())
+        );
+        assert!(eval("(parameterize ((5 2)) 1)").is_err());
+    }
+
+    #[test]
+    fn parameterize_does_not_grow_the_native_stack() {
+        let f = "(define p (make-parameter 0))
+                 (define (f n) (if (= n 0) (p) (parameterize ((p (+ (f (- n 1)) 1))) (p))))";
+        assert_eq!(eval_recursion(f, 500), Ok("500".to_string()));
+    }
+
+    #[test]
+    fn raising_values_restore_the_parameters() {
+        let p = "(define p (make-parameter 1))";
+        assert_eq!(
+            eval(&format!("{p} (guard (e (#t (p))) (parameterize ((p 2)) (raise 0)))")),
+            Ok("1".to_string())
+        );
+        assert_eq!(
+            eval(&format!("{p} (guard (e (#t e)) (parameterize ((p 2) (p (raise 7))) (p)))")),
+            Ok("7".to_string())
+        );
+    }
+}
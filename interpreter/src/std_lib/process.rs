@@ -0,0 +1,240 @@
+use core::literal::{Literal, Numeric};
+use std::process::{Command, Stdio};
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    convert::IntoScheme,
+    deferred::Deferred,
+    deref::InterpreterDeref,
+    list::InterpreterListAlloc,
+    object::{HeapObject, ObjectPointer, StackObject},
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::{
+    pop_params,
+    types::{expect_params, expect_string, wrong_type},
+    values::push_values,
+};
+
+/// `(command-line)`, the script's name followed by the arguments passed to it
+pub fn command_line(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 0)?;
+    pop_params(interpreter, n)?;
+
+    let command_line = interpreter.command_line.clone().into_scheme(interpreter)?;
+    interpreter.stack.push_data(command_line);
+    Ok(())
+}
+
+/// The status `exit` and `emergency-exit` are given, `#t` or nothing meaning success
+/// and `#f` failure
+fn exit_status(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    n: usize,
+) -> InterpreterResult<i32> {
+    if n > 1 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(1, n),
+        ));
+    }
+
+    let params = pop_params(interpreter, n)?;
+    let Some(status) = params.first() else {
+        return Ok(0);
+    };
+    match status.deref(interpreter)?.literal() {
+        Some(Literal::Boolean(true)) => Ok(0),
+        Some(Literal::Boolean(false)) => Ok(1),
+        Some(Literal::Numeric(Numeric::Int(i))) => Ok(i),
+        _ => Err(wrong_type(interpreter, procedure, 1, "an exit status", status)),
+    }
+}
+
+/// `(exit [status])`, running the after thunks of every `dynamic-wind` left and then
+/// stopping the program
+pub fn exit(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    let status = exit_status(interpreter, "exit", n)?;
+
+    // Taken all at once, so an after thunk which exits does not run the others again
+    let winders = std::mem::take(&mut *interpreter.winders.write().unwrap());
+    for winder in winders.iter().rev() {
+        interpreter.defer(Deferred::Apply(winder.after.clone(), Vec::new()));
+        interpreter.defer(Deferred::discard());
+    }
+    interpreter.defer(Deferred::then(move |_| {
+        Err(InterpreterError::new(InterpreterErrorKind::Exit(status)))
+    }));
+    Ok(())
+}
+
+/// `(emergency-exit [status])`, stopping the program without running any after thunks
+pub fn emergency_exit(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    let status = exit_status(interpreter, "emergency-exit", n)?;
+    Err(InterpreterError::new(InterpreterErrorKind::Exit(status)))
+}
+
+/// `(get-environment-variable name)`, the value of the variable or `#f` if it is unset
+pub fn get_environment_variable(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    let name = expect_string(interpreter, "get-environment-variable", 1, &params[0])?;
+
+    interpreter.capabilities.check_environment(&name)?;
+    let value = std::env::var(&name).ok().into_scheme(interpreter)?;
+    interpreter.stack.push_data(value);
+    Ok(())
+}
+
+/// `(get-environment-variables)`, an association list of every variable which may be
+/// read, sorted by name
+pub fn get_environment_variables(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 0)?;
+    pop_params(interpreter, n)?;
+
+    let mut variables = std::env::vars()
+        .filter(|(name, _)| interpreter.capabilities.check_environment(name).is_ok())
+        .collect::<Vec<_>>();
+    variables.sort();
+    let list = variables
+        .into_iter()
+        .map(|(name, value)| {
+            HeapObject::List(
+                name.into_scheme_pointer(interpreter)?,
+                value.into_scheme_pointer(interpreter)?,
+            )
+            .heap_alloc(interpreter)
+        })
+        .collect::<InterpreterResult<Vec<_>>>()?
+        .to_list(interpreter)?;
+    interpreter.stack.push_data(StackObject::Ref(list));
+    Ok(())
+}
+
+/// `(process-run program arg ..)`, running `program` to completion with no input.
+/// Returns three values, the exit status, or `#f` if it was killed by a signal, then
+/// everything it wrote to stdout and to stderr
+pub fn process_run(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if n == 0 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(1, n),
+        ));
+    }
+
+    let params = pop_params(interpreter, n)?;
+    let mut args = params
+        .iter()
+        .enumerate()
+        .map(|(i, p)| expect_string(interpreter, "process-run", i + 1, p))
+        .collect::<InterpreterResult<Vec<_>>>()?;
+    let program = args.remove(0);
+
+    interpreter.capabilities.check_run(&program)?;
+    let output = Command::new(&program)
+        .args(&args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| {
+            InterpreterError::new(InterpreterErrorKind::CannotRunProcess(
+                program.clone(),
+                err.to_string(),
+            ))
+        })?;
+
+    let status = output.status.code().map(i64::from);
+    let values: Vec<ObjectPointer> = vec![
+        status.into_scheme_pointer(interpreter)?,
+        String::from_utf8_lossy(&output.stdout).into_owned().into_scheme_pointer(interpreter)?,
+        String::from_utf8_lossy(&output.stderr).into_owned().into_scheme_pointer(interpreter)?,
+    ];
+    push_values(interpreter, values)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::{
+        capabilities::Capabilities,
+        testing::{builder, interpreter},
+        Interpreter,
+    };
+
+    #[test]
+    fn exit_runs_the_after_thunks_but_emergency_exit_does_not() {
+        let interpreter = interpreter();
+        let afters = Arc::new(AtomicUsize::new(0));
+        let counter = afters.clone();
+        interpreter.register_typed("after", move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        let wound = |exit: &str| {
+            format!(
+                "(dynamic-wind (lambda () 0)
+                   (lambda () (dynamic-wind (lambda () 0) (lambda () ({exit} #f)) after))
+                   after)"
+            )
+        };
+
+        let err = interpreter.eval_str(&wound("exit")).unwrap_err();
+        assert_eq!(err.exit_status(), Some(1));
+        assert_eq!(afters.load(Ordering::Relaxed), 2);
+
+        let err = interpreter.eval_str(&wound("emergency-exit")).unwrap_err();
+        assert_eq!(err.exit_status(), Some(1));
+        assert_eq!(afters.load(Ordering::Relaxed), 2);
+
+        assert_eq!(interpreter.eval_str("(exit)").unwrap_err().exit_status(), Some(0));
+        assert_eq!(interpreter.eval_str("(exit #t)").unwrap_err().exit_status(), Some(0));
+        assert_eq!(interpreter.eval_str("(exit \"1\")").unwrap_err().exit_status(), None);
+    }
+
+    #[test]
+    fn command_line_is_given_by_the_host() {
+        let context = builder().command_line(["script.scm", "a"]).build();
+        let value = Interpreter::from_context(context).eval_str("(command-line)").unwrap();
+        assert_eq!(value.get::<Vec<String>>(), Some(vec!["script.scm".into(), "a".into()]));
+    }
+
+    #[test]
+    fn environment_variables_are_read_if_allowed() {
+        let capabilities = Capabilities {
+            environment: Some(vec!["PATH".to_string(), "INTERPRETER_UNSET".to_string()]),
+            ..Capabilities::default()
+        };
+        let interpreter = Interpreter::from_context(builder().capabilities(capabilities).build());
+        let path = std::env::var("PATH").ok();
+
+        let value = interpreter.eval_str("(get-environment-variable \"PATH\")").unwrap();
+        assert_eq!(value.get::<Option<String>>(), Some(path.clone()));
+        let value = interpreter.eval_str("(get-environment-variable \"INTERPRETER_UNSET\")");
+        assert_eq!(value.unwrap().get::<Option<String>>(), Some(None));
+        let value = interpreter.eval_str("(get-environment-variables)").unwrap();
+        let expected = path.map(|path| vec![("PATH".to_string(), path)]).unwrap_or_default();
+        assert_eq!(
+            value.get::<std::collections::HashMap<String, String>>(),
+            Some(expected.into_iter().collect())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn processes_give_their_status_and_output() {
+        let value = interpreter()
+            .eval_str(
+                "(call-with-values
+                   (lambda () (process-run \"sh\" \"-c\" \"echo out; echo err >&2; exit 3\"))
+                   list)",
+            )
+            .unwrap();
+        assert_eq!(
+            value.get::<(Option<i64>, String, String)>(),
+            Some((Some(3), "out\n".to_string(), "err\n".to_string()))
+        );
+        assert!(interpreter().eval_str("(process-run \"/nonexistent/program\")").is_err());
+    }
+}
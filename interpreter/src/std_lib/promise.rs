@@ -0,0 +1,108 @@
+use core::{literal::Literal, parser::ast::AST};
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    func::Func,
+    object::{HeapObject, ObjectPointer, StackObject},
+    promise::{self, as_promise, capture_bindings, Promise, Thunk},
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::{pop_params, types::expect_params};
+
+/// A promise which evaluates `expr` with the bindings visible now
+pub fn delay_expr(
+    interpreter: &InterpreterContext,
+    expr: &AST,
+    chained: bool,
+) -> InterpreterResult<ObjectPointer> {
+    let thunk =
+        HeapObject::Func(Func::Defined(None, Vec::new(), expr.clone())).heap_alloc(interpreter)?;
+    let thunk = Thunk::Procedure(thunk, capture_bindings(interpreter));
+    HeapObject::Promise(Promise::delayed(thunk, chained)).heap_alloc(interpreter)
+}
+
+fn delay_by(
+    interpreter: &InterpreterContext,
+    ast: Vec<&AST>,
+    chained: bool,
+) -> InterpreterResult<()> {
+    if ast.len() != 1 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(1, ast.len()),
+        ));
+    }
+    let promise = delay_expr(interpreter, ast[0], chained)?;
+    interpreter.stack.push_data(StackObject::Ref(promise));
+    Ok(())
+}
+
+/// `(delay expr)`
+pub fn delay(interpreter: &InterpreterContext, ast: Vec<&AST>) -> InterpreterResult<()> {
+    delay_by(interpreter, ast, false)
+}
+
+/// `(delay-force expr)`, where `expr` evaluates to a promise forced in its place
+pub fn delay_force(interpreter: &InterpreterContext, ast: Vec<&AST>) -> InterpreterResult<()> {
+    delay_by(interpreter, ast, true)
+}
+
+/// `(make-promise obj)`, an already forced promise unless `obj` is a promise
+pub fn make_promise(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let mut params = pop_params(interpreter, n)?;
+    let obj = params.pop().unwrap();
+    let promise = match as_promise(interpreter, &obj)? {
+        Some(_) => obj,
+        None => HeapObject::Promise(Promise::done(obj)).heap_alloc(interpreter)?,
+    };
+    interpreter.stack.push_data(StackObject::Ref(promise));
+    Ok(())
+}
+
+pub fn is_promise(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    let result = as_promise(interpreter, &params[0])?.is_some();
+    interpreter
+        .stack
+        .push_data(StackObject::Value(Literal::Boolean(result)));
+    Ok(())
+}
+
+/// `(force obj)`, objects which are not promises are returned as they are
+pub fn force(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let mut params = pop_params(interpreter, n)?;
+    promise::force(interpreter, params.pop().unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::{eval, eval_recursion};
+
+    #[test]
+    fn promises_are_forced_once() {
+        let promise = "(define count 0)
+                       (define p (delay (match (define count (+ count 1)) (_ (* 6 7)))))";
+        assert_eq!(eval(&format!("{promise} (promise? p)")), Ok("true".to_string()));
+        assert_eq!(eval(&format!("{promise} (force p) (force p)")), Ok("42".to_string()));
+        assert_eq!(eval(&format!("{promise} (force p) (force p) count")), Ok("1".to_string()));
+        assert_eq!(eval(&format!("{promise} count")), Ok("0".to_string()));
+    }
+
+    #[test]
+    fn make_promise_wraps_values_once() {
+        assert_eq!(eval("(force (make-promise 5))"), Ok("5".to_string()));
+        assert_eq!(eval("(force (make-promise (make-promise 5)))"), Ok("5".to_string()));
+        assert_eq!(eval("(force 7)"), Ok("7".to_string()));
+        assert_eq!(eval("(promise? 7)"), Ok("false".to_string()));
+    }
+
+    #[test]
+    fn delay_force_chains_do_not_grow_the_native_stack() {
+        let f = "(define (chain n) (if (= n 0) (make-promise 0) (delay-force (chain (- n 1)))))
+                 (define (f n) (force (chain n)))";
+        assert_eq!(eval_recursion(f, 20000), Ok("0".to_string()));
+    }
+}
@@ -0,0 +1,420 @@
+use core::{literal::Literal, parser::ast::AST};
+use std::{
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    deferred::Deferred,
+    deref::InterpreterDeref,
+    list::InterpreterListAlloc,
+    object::{HeapObject, ObjectPointer, ObjectRef, StackObject},
+    promise::{as_promise, force, force_then, Promise, Thunk},
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::{
+    pop_params,
+    promise::delay_expr,
+    types::{expect_index, expect_list, expect_params, expect_procedure, wrong_type},
+};
+
+// A stream is a promise of either `()` or a pair of a promise of the head and a stream
+
+/// A stream whose first node is already known
+fn stream(
+    interpreter: &InterpreterContext,
+    node: ObjectPointer,
+) -> InterpreterResult<ObjectPointer> {
+    HeapObject::Promise(Promise::done(node)).heap_alloc(interpreter)
+}
+
+fn stream_pair(
+    interpreter: &InterpreterContext,
+    head: ObjectPointer,
+    tail: ObjectPointer,
+) -> InterpreterResult<ObjectPointer> {
+    let node = HeapObject::List(head, tail).heap_alloc(interpreter)?;
+    stream(interpreter, node)
+}
+
+/// A stream computed by native work pushing another stream, which it then stands for
+fn lazy_stream(
+    interpreter: &InterpreterContext,
+    f: impl Fn(&InterpreterContext) -> InterpreterResult<()> + Send + Sync + 'static,
+) -> InterpreterResult<ObjectPointer> {
+    HeapObject::Promise(Promise::delayed(Thunk::Native(Arc::new(f)), true)).heap_alloc(interpreter)
+}
+
+fn push(interpreter: &InterpreterContext, obj: ObjectPointer) -> InterpreterResult<()> {
+    interpreter.stack.push_data(StackObject::Ref(obj));
+    Ok(())
+}
+
+fn expect_stream(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    obj: &ObjectPointer,
+) -> InterpreterResult<()> {
+    match as_promise(interpreter, obj)? {
+        Some(_) => Ok(()),
+        None => Err(wrong_type(
+            interpreter,
+            procedure,
+            arg_index,
+            "a stream",
+            obj,
+        )),
+    }
+}
+
+/// The head and tail of a forced stream, or `None` once it has ended
+fn stream_node(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    node: &ObjectPointer,
+) -> InterpreterResult<Option<(ObjectPointer, ObjectPointer)>> {
+    match node.deref(interpreter)? {
+        ObjectRef::Null => Ok(None),
+        ObjectRef::Object(o) => match o.deref() {
+            HeapObject::List(h, t) => Ok(Some((h.clone(), t.clone()))),
+            _ => Err(wrong_type(
+                interpreter,
+                procedure,
+                arg_index,
+                "a stream",
+                node,
+            )),
+        },
+        _ => Err(wrong_type(
+            interpreter,
+            procedure,
+            arg_index,
+            "a stream",
+            node,
+        )),
+    }
+}
+
+/// `(stream-cons head tail)`, delaying both
+pub fn stream_cons(interpreter: &InterpreterContext, ast: Vec<&AST>) -> InterpreterResult<()> {
+    if ast.len() != 2 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, ast.len()),
+        ));
+    }
+    let head = delay_expr(interpreter, ast[0], false)?;
+    let tail = delay_expr(interpreter, ast[1], true)?;
+    let s = stream_pair(interpreter, head, tail)?;
+    push(interpreter, s)
+}
+
+pub fn empty_stream(interpreter: &InterpreterContext) -> InterpreterResult<ObjectPointer> {
+    stream(interpreter, ObjectPointer::Null)
+}
+
+fn stream_test(
+    interpreter: &InterpreterContext,
+    n: usize,
+    test: fn(&ObjectPointer) -> bool,
+) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let mut params = pop_params(interpreter, n)?;
+    let s = params.pop().unwrap();
+    if as_promise(interpreter, &s)?.is_none() {
+        interpreter
+            .stack
+            .push_data(StackObject::Value(Literal::Boolean(false)));
+        return Ok(());
+    }
+    force_then(interpreter, s, move |interpreter, node| {
+        interpreter
+            .stack
+            .push_data(StackObject::Value(Literal::Boolean(test(&node))));
+        Ok(())
+    })
+}
+
+pub fn is_stream_null(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    stream_test(interpreter, n, |node| *node == ObjectPointer::Null)
+}
+
+pub fn is_stream_pair(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    stream_test(interpreter, n, |node| *node != ObjectPointer::Null)
+}
+
+/// Forces a stream argument and hands its head and tail to `f`
+fn with_stream_pair(
+    interpreter: &InterpreterContext,
+    n: usize,
+    procedure: &'static str,
+    f: fn(&InterpreterContext, ObjectPointer, ObjectPointer) -> InterpreterResult<()>,
+) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let mut params = pop_params(interpreter, n)?;
+    let s = params.pop().unwrap();
+    expect_stream(interpreter, procedure, 1, &s)?;
+
+    force_then(
+        interpreter,
+        s.clone(),
+        move |interpreter, node| match stream_node(interpreter, procedure, 1, &node)? {
+            Some((head, tail)) => f(interpreter, head, tail),
+            None => Err(wrong_type(
+                interpreter,
+                procedure,
+                1,
+                "a non-empty stream",
+                &s,
+            )),
+        },
+    )
+}
+
+pub fn stream_car(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    with_stream_pair(interpreter, n, "stream-car", |interpreter, head, _| {
+        force(interpreter, head)
+    })
+}
+
+pub fn stream_cdr(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    with_stream_pair(interpreter, n, "stream-cdr", |interpreter, _, tail| {
+        push(interpreter, tail)
+    })
+}
+
+fn take(
+    interpreter: &InterpreterContext,
+    n: usize,
+    s: ObjectPointer,
+) -> InterpreterResult<ObjectPointer> {
+    lazy_stream(interpreter, move |interpreter| {
+        if n == 0 {
+            let empty = empty_stream(interpreter)?;
+            return push(interpreter, empty);
+        }
+        force_then(interpreter, s.clone(), move |interpreter, node| {
+            let out = match stream_node(interpreter, "stream-take", 2, &node)? {
+                Some((head, tail)) => {
+                    let rest = take(interpreter, n - 1, tail)?;
+                    stream_pair(interpreter, head, rest)?
+                }
+                None => empty_stream(interpreter)?,
+            };
+            push(interpreter, out)
+        })
+    })
+}
+
+/// `(stream-take n stream)`, a stream of at most the first `n` elements
+pub fn stream_take(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 2)?;
+    let params = pop_params(interpreter, n)?;
+    let count = expect_index(interpreter, "stream-take", 1, &params[0])?;
+    expect_stream(interpreter, "stream-take", 2, &params[1])?;
+    let s = take(interpreter, count, params[1].clone())?;
+    push(interpreter, s)
+}
+
+fn map(
+    interpreter: &InterpreterContext,
+    func: ObjectPointer,
+    s: ObjectPointer,
+) -> InterpreterResult<ObjectPointer> {
+    lazy_stream(interpreter, move |interpreter| {
+        let func = func.clone();
+        force_then(interpreter, s.clone(), move |interpreter, node| {
+            let Some((head, tail)) = stream_node(interpreter, "stream-map", 2, &node)? else {
+                let empty = empty_stream(interpreter)?;
+                return push(interpreter, empty);
+            };
+
+            let f = func.clone();
+            let mapped = Thunk::Native(Arc::new(move |interpreter| {
+                let f = f.clone();
+                force_then(interpreter, head.clone(), move |interpreter, x| {
+                    interpreter.defer(Deferred::Apply(f.clone(), vec![x]));
+                    Ok(())
+                })
+            }));
+            let head =
+                HeapObject::Promise(Promise::delayed(mapped, false)).heap_alloc(interpreter)?;
+            let rest = map(interpreter, func.clone(), tail)?;
+            let out = stream_pair(interpreter, head, rest)?;
+            push(interpreter, out)
+        })
+    })
+}
+
+/// `(stream-map proc stream)`, applying `proc` to each element only once it is needed
+pub fn stream_map(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 2)?;
+    let params = pop_params(interpreter, n)?;
+    expect_procedure(interpreter, "stream-map", 1, &params[0])?;
+    expect_stream(interpreter, "stream-map", 2, &params[1])?;
+    let s = map(interpreter, params[0].clone(), params[1].clone())?;
+    push(interpreter, s)
+}
+
+fn filter(
+    interpreter: &InterpreterContext,
+    pred: ObjectPointer,
+    s: ObjectPointer,
+) -> InterpreterResult<ObjectPointer> {
+    lazy_stream(interpreter, move |interpreter| {
+        let pred = pred.clone();
+        force_then(interpreter, s.clone(), move |interpreter, node| {
+            let Some((head, tail)) = stream_node(interpreter, "stream-filter", 2, &node)? else {
+                let empty = empty_stream(interpreter)?;
+                return push(interpreter, empty);
+            };
+
+            let pred = pred.clone();
+            force_then(interpreter, head.clone(), move |interpreter, x| {
+                interpreter.defer(Deferred::Apply(pred.clone(), vec![x]));
+                let (pred, head, tail) = (pred.clone(), head.clone(), tail.clone());
+                interpreter.defer(Deferred::then(move |interpreter| {
+                    let keep = interpreter
+                        .stack
+                        .pop_data()?
+                        .deref(interpreter)?
+                        .is_truthy();
+                    let rest = filter(interpreter, pred.clone(), tail.clone())?;
+                    // A rejected element leaves the rest in its place, which is forced in turn
+                    let out = if keep {
+                        stream_pair(interpreter, head.clone(), rest)?
+                    } else {
+                        rest
+                    };
+                    push(interpreter, out)
+                }));
+                Ok(())
+            })
+        })
+    })
+}
+
+/// `(stream-filter pred stream)`
+pub fn stream_filter(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 2)?;
+    let params = pop_params(interpreter, n)?;
+    expect_procedure(interpreter, "stream-filter", 1, &params[0])?;
+    expect_stream(interpreter, "stream-filter", 2, &params[1])?;
+    let s = filter(interpreter, params[0].clone(), params[1].clone())?;
+    push(interpreter, s)
+}
+
+/// `(list->stream list)`
+pub fn list_to_stream(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 1)?;
+    let params = pop_params(interpreter, n)?;
+    let elements = expect_list(interpreter, "list->stream", 1, &params[0])?;
+
+    let mut s = empty_stream(interpreter)?;
+    for x in elements.into_iter().rev() {
+        let head = HeapObject::Promise(Promise::done(x)).heap_alloc(interpreter)?;
+        s = stream_pair(interpreter, head, s)?;
+    }
+    push(interpreter, s)
+}
+
+fn collect(
+    interpreter: &InterpreterContext,
+    s: ObjectPointer,
+    remaining: Option<usize>,
+    acc: Arc<Mutex<Vec<ObjectPointer>>>,
+) -> InterpreterResult<()> {
+    if remaining == Some(0) {
+        let list = std::mem::take(&mut *acc.lock().unwrap()).to_list(interpreter)?;
+        return push(interpreter, list);
+    }
+
+    force_then(interpreter, s, move |interpreter, node| {
+        let Some((head, tail)) = stream_node(interpreter, "stream->list", 1, &node)? else {
+            let list = std::mem::take(&mut *acc.lock().unwrap()).to_list(interpreter)?;
+            return push(interpreter, list);
+        };
+        let (acc, tail) = (acc.clone(), tail.clone());
+        force_then(interpreter, head, move |interpreter, x| {
+            acc.lock().unwrap().push(x);
+            collect(
+                interpreter,
+                tail.clone(),
+                remaining.map(|r| r - 1),
+                acc.clone(),
+            )
+        })
+    })
+}
+
+/// `(stream->list stream [n])`, the elements of a finite stream or at most its first `n`
+pub fn stream_to_list(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    if !(1..=2).contains(&n) {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(1, n),
+        ));
+    }
+    let params = pop_params(interpreter, n)?;
+    expect_stream(interpreter, "stream->list", 1, &params[0])?;
+    let remaining = match params.get(1) {
+        Some(count) => Some(expect_index(interpreter, "stream->list", 2, count)?),
+        None => None,
+    };
+    collect(
+        interpreter,
+        params[0].clone(),
+        remaining,
+        Arc::new(Mutex::new(Vec::new())),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::{eval, eval_recursion};
+
+    const INTS: &str = "(define (ints n) (stream-cons n (ints (+ n 1))))";
+
+    #[test]
+    fn infinite_streams_are_taken_lazily() {
+        assert_eq!(
+            eval(&format!("{INTS} (stream->list (stream-take 5 (ints 0)))")),
+            Ok("0:1:2:3:4:()".to_string())
+        );
+        assert_eq!(
+            eval(&format!(
+                "{INTS} (stream->list (stream-take 4 (stream-map (lambda (x) (* x x)) (ints 0))))"
+            )),
+            Ok("0:1:4:9:()".to_string())
+        );
+        assert_eq!(
+            eval(&format!(
+                "{INTS} (stream->list (stream-take 3 (stream-filter (lambda (x) (> x 2)) \
+                 (ints 0))))"
+            )),
+            Ok("3:4:5:()".to_string())
+        );
+    }
+
+    #[test]
+    fn streams_are_built_from_lists() {
+        assert_eq!(
+            eval("(stream-car (stream-cdr (list->stream (list 1 2 3))))"),
+            Ok("2".to_string())
+        );
+        assert_eq!(eval("(stream->list (list->stream (list 1 2)))"), Ok("1:2:()".to_string()));
+        assert_eq!(eval("(stream-null? stream-null)"), Ok("true".to_string()));
+        assert_eq!(eval(&format!("{INTS} (stream-pair? (ints 0))")), Ok("true".to_string()));
+        assert_eq!(eval("(stream-pair? stream-null)"), Ok("false".to_string()));
+    }
+
+    #[test]
+    fn long_filters_do_not_grow_the_native_stack() {
+        let f = format!(
+            "{INTS} (define (f n) (stream-car (stream-filter (lambda (x) (>= x n)) (ints 0))))"
+        );
+        assert_eq!(eval_recursion(&f, 10000), Ok("10000".to_string()));
+    }
+}
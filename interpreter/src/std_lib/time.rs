@@ -0,0 +1,44 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{convert::IntoScheme, InterpreterContext, InterpreterResult};
+
+use super::{pop_params, types::expect_params};
+
+/// Jiffies are milliseconds, as integers are 32 bit
+const JIFFIES_PER_SECOND: i64 = 1000;
+
+/// `(current-second)`, the seconds since the Unix epoch. Exact rather than inexact
+/// as R7RS has it, since a single precision float cannot hold the time to the second
+pub fn current_second(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 0)?;
+    pop_params(interpreter, n)?;
+
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() as i64);
+    let seconds = seconds.into_scheme(interpreter)?;
+    interpreter.stack.push_data(seconds);
+    Ok(())
+}
+
+/// `(current-jiffy)`, the jiffies since the interpreter was created, wrapping around
+/// after about 24 days
+pub fn current_jiffy(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 0)?;
+    pop_params(interpreter, n)?;
+
+    let jiffies = interpreter.started.elapsed().as_millis() as i32 as i64;
+    let jiffies = jiffies.into_scheme(interpreter)?;
+    interpreter.stack.push_data(jiffies);
+    Ok(())
+}
+
+/// `(jiffies-per-second)`
+pub fn jiffies_per_second(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 0)?;
+    pop_params(interpreter, n)?;
+
+    let jiffies = JIFFIES_PER_SECOND.into_scheme(interpreter)?;
+    interpreter.stack.push_data(jiffies);
+    Ok(())
+}
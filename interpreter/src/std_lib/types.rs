@@ -0,0 +1,243 @@
+use core::literal::{Literal, Numeric};
+use std::ops::Deref;
+
+use crate::{
+    deref::InterpreterDeref,
+    func::Func,
+    list::InterpreterList,
+    object::{HeapObject, ObjectPointer, ObjectRef, StackObject},
+    print::InterpreterPrint,
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::pop_params;
+
+/// Builds the error for an argument of the wrong type, `arg_index` counting from 1
+pub fn wrong_type(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    expected: &'static str,
+    got: &impl InterpreterPrint,
+) -> InterpreterError {
+    InterpreterError::new(InterpreterErrorKind::WrongType {
+        procedure: procedure.to_string(),
+        arg_index,
+        expected,
+        got: got.interpreter_fmt(interpreter),
+    })
+}
+
+pub fn expect_params(n: usize, expected: usize) -> InterpreterResult<()> {
+    if n != expected {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(expected, n),
+        ));
+    }
+    Ok(())
+}
+
+pub fn expect_list(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    list: &ObjectPointer,
+) -> InterpreterResult<Vec<ObjectPointer>> {
+    list.list_to_vec(interpreter)
+        .map_err(|_| wrong_type(interpreter, procedure, arg_index, "a list", list))
+}
+
+pub fn expect_pair(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    pair: &(impl InterpreterDeref + InterpreterPrint),
+) -> InterpreterResult<(ObjectPointer, ObjectPointer)> {
+    if let ObjectRef::Object(o) = pair.deref(interpreter)? {
+        if let HeapObject::List(h, t) = o.deref() {
+            return Ok((h.clone(), t.clone()));
+        }
+    }
+    Err(wrong_type(interpreter, procedure, arg_index, "a pair", pair))
+}
+
+pub fn expect_numeric(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    number: &(impl InterpreterDeref + InterpreterPrint),
+) -> InterpreterResult<Numeric> {
+    match number.deref(interpreter)?.literal() {
+        Some(Literal::Numeric(n)) => Ok(n),
+        _ => Err(wrong_type(interpreter, procedure, arg_index, "a number", number)),
+    }
+}
+
+pub fn expect_integer(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    integer: &(impl InterpreterDeref + InterpreterPrint),
+) -> InterpreterResult<i32> {
+    match integer.deref(interpreter)?.literal() {
+        Some(Literal::Numeric(Numeric::Int(i))) => Ok(i),
+        _ => Err(wrong_type(interpreter, procedure, arg_index, "an integer", integer)),
+    }
+}
+
+pub fn expect_char(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    character: &(impl InterpreterDeref + InterpreterPrint),
+) -> InterpreterResult<char> {
+    match character.deref(interpreter)?.literal() {
+        Some(Literal::Character(c)) => Ok(c),
+        _ => Err(wrong_type(interpreter, procedure, arg_index, "a character", character)),
+    }
+}
+
+pub fn expect_index(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    index: &ObjectPointer,
+) -> InterpreterResult<usize> {
+    match index.deref(interpreter)?.literal() {
+        Some(Literal::Numeric(Numeric::Int(i))) if i >= 0 => Ok(i as usize),
+        _ => Err(wrong_type(
+            interpreter,
+            procedure,
+            arg_index,
+            "a non-negative integer",
+            index,
+        )),
+    }
+}
+
+pub fn expect_string(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    string: &(impl InterpreterDeref + InterpreterPrint),
+) -> InterpreterResult<String> {
+    if let ObjectRef::Object(o) = string.deref(interpreter)? {
+        if let HeapObject::String(s) = o.deref() {
+            return Ok(s.clone());
+        }
+    }
+    Err(wrong_type(interpreter, procedure, arg_index, "a string", string))
+}
+
+pub fn expect_procedure(
+    interpreter: &InterpreterContext,
+    procedure: &str,
+    arg_index: usize,
+    func: &ObjectPointer,
+) -> InterpreterResult<()> {
+    if is_applicable(&func.deref(interpreter)?) {
+        return Ok(());
+    }
+    Err(wrong_type(interpreter, procedure, arg_index, "a procedure", func))
+}
+
+/// Syntax such as `if` or `define` is a function object but cannot be applied to values
+fn is_applicable(obj: &ObjectRef) -> bool {
+    match obj {
+        ObjectRef::Object(o) => matches!(
+            o.deref(),
+            HeapObject::Func(
+                Func::Native(..)
+                    | Func::Defined(..)
+                    | Func::CaseLambda(..)
+                    | Func::Continuation(..)
+                    | Func::Parameter(..)
+            )
+        ),
+        _ => false,
+    }
+}
+
+macro_rules! type_predicate {
+    ($name:ident, $obj:ident, $check:expr) => {
+        pub fn $name(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+            expect_params(n, 1)?;
+            let params = pop_params(interpreter, n)?;
+            let result = {
+                let $obj = params[0].deref(interpreter)?;
+                $check
+            };
+            interpreter
+                .stack
+                .push_data(StackObject::Value(Literal::Boolean(result)));
+            Ok(())
+        }
+    };
+}
+
+type_predicate!(is_number, obj, matches!(obj.literal(), Some(Literal::Numeric(_))));
+type_predicate!(
+    is_integer,
+    obj,
+    matches!(obj.literal(), Some(Literal::Numeric(Numeric::Int(_))))
+);
+type_predicate!(is_boolean, obj, matches!(obj.literal(), Some(Literal::Boolean(_))));
+type_predicate!(is_char, obj, matches!(obj.literal(), Some(Literal::Character(_))));
+type_predicate!(
+    is_string,
+    obj,
+    matches!(&obj, ObjectRef::Object(o) if matches!(o.deref(), HeapObject::String(_)))
+);
+type_predicate!(is_procedure, obj, is_applicable(&obj));
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        embed::Error,
+        error::InterpreterErrorKind,
+        testing::{eval, interpreter},
+    };
+
+    #[test]
+    fn predicates_check_the_type() {
+        for (source, expected) in [
+            ("(number? 1.5)", "true"),
+            ("(number? \"1\")", "false"),
+            ("(integer? 2)", "true"),
+            ("(integer? 2.0)", "false"),
+            ("(string? \"a\")", "true"),
+            ("(string? #\\a)", "false"),
+            ("(boolean? #f)", "true"),
+            ("(boolean? (list))", "false"),
+            ("(char? #\\a)", "true"),
+            ("(procedure? car)", "true"),
+            ("(procedure? (lambda (x) x))", "true"),
+            ("(procedure? 1)", "false"),
+        ] {
+            assert_eq!(eval(source), Ok(expected.to_string()), "{source}");
+        }
+    }
+
+    #[test]
+    fn wrong_types_name_the_procedure_and_argument() {
+        let Err(Error::Eval(err)) = interpreter().eval_str("(+ 1 \"a\")") else {
+            panic!("expected an evaluation error");
+        };
+        assert_eq!(
+            err.kind,
+            InterpreterErrorKind::WrongType {
+                procedure: "+".to_string(),
+                arg_index: 2,
+                expected: "a number",
+                got: "\"a\"".to_string(),
+            }
+        );
+        assert_eq!(
+            eval("(guard (e (#t (error-object-message e))) (car 5))"),
+            Ok("\"'car' expected a pair as argument 1, received '5'\"".to_string())
+        );
+        assert!(eval("(string=? 1 \"a\")")
+            .unwrap_err()
+            .contains("'string=?' expected a string as argument 1"));
+    }
+}
@@ -0,0 +1,298 @@
+use core::parser::ast::AST;
+use std::ops::Deref;
+
+use crate::{
+    alloc::InterpreterHeapAlloc,
+    deferred::Deferred,
+    deref::InterpreterDeref,
+    list::InterpreterListAlloc,
+    object::{HeapObject, ObjectPointer, ObjectRef, StackObject},
+    InterpreterContext, InterpreterError, InterpreterErrorKind, InterpreterResult,
+};
+
+use super::{
+    exception::thunk,
+    pop_params,
+    types::{expect_params, expect_procedure},
+};
+
+/// The values an object stands for, which is itself unless it came from `values`
+pub fn values_of(
+    interpreter: &InterpreterContext,
+    obj: &ObjectPointer,
+) -> InterpreterResult<Vec<ObjectPointer>> {
+    if let ObjectRef::Object(o) = obj.deref(interpreter)? {
+        if let HeapObject::Values(values) = o.deref() {
+            return Ok(values.clone());
+        }
+    }
+    Ok(vec![obj.clone()])
+}
+
+/// Pushes the values as a single result, wrapping them unless there is exactly one
+pub fn push_values(
+    interpreter: &InterpreterContext,
+    mut values: Vec<ObjectPointer>,
+) -> InterpreterResult<()> {
+    let result = if values.len() == 1 {
+        values.pop().unwrap()
+    } else {
+        HeapObject::Values(values).heap_alloc(interpreter)?
+    };
+    interpreter.stack.push_data(StackObject::Ref(result));
+    Ok(())
+}
+
+pub fn values(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    let params = pop_params(interpreter, n)?;
+    push_values(interpreter, params)
+}
+
+/// `(call-with-values producer consumer)`
+pub fn call_with_values(interpreter: &InterpreterContext, n: usize) -> InterpreterResult<()> {
+    expect_params(n, 2)?;
+    let params = pop_params(interpreter, n)?;
+    expect_procedure(interpreter, "call-with-values", 1, &params[0])?;
+    expect_procedure(interpreter, "call-with-values", 2, &params[1])?;
+
+    let consumer = params[1].clone();
+    interpreter.defer(Deferred::Apply(params[0].clone(), Vec::new()));
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let values = pop_values(interpreter)?;
+        interpreter.defer(Deferred::Apply(consumer.clone(), values));
+        Ok(())
+    }));
+    Ok(())
+}
+
+/// Matches values against formals, either `(ident ..)` taking exactly that many,
+/// `(ident .. . rest)` taking the values left over as a list, or a single identifier
+/// taking them all as a list
+fn bind_formals(
+    interpreter: &InterpreterContext,
+    form: &'static str,
+    formals: &AST,
+    mut values: Vec<ObjectPointer>,
+) -> InterpreterResult<Vec<(String, ObjectPointer)>> {
+    let invalid =
+        |e: &AST| InterpreterError::spanned(InterpreterErrorKind::InvalidFormals(form), e.span());
+    let (names, rest) = match formals {
+        AST::Identifier(name, _) => (Vec::new(), Some(name.clone())),
+        AST::EmptyList(_) => (Vec::new(), None),
+        AST::Operation(first, rest, _) => {
+            super::formals(std::iter::once(first.deref()).chain(rest), invalid)?
+        }
+        e => return Err(invalid(e)),
+    };
+
+    let (expected, accepted) = match rest {
+        Some(_) => (
+            format!("at least {}", names.len()),
+            values.len() >= names.len(),
+        ),
+        None => (names.len().to_string(), values.len() == names.len()),
+    };
+    if !accepted {
+        return Err(InterpreterError::spanned(
+            InterpreterErrorKind::WrongValueCount(form, expected, values.len()),
+            formals.span(),
+        ));
+    }
+
+    let left = values.split_off(names.len());
+    let mut bindings = names.into_iter().zip(values).collect::<Vec<_>>();
+    if let Some(rest) = rest {
+        bindings.push((rest, left.to_list(interpreter)?));
+    }
+    Ok(bindings)
+}
+
+/// Pops the result of a deferred expression, returning the values it produced
+fn pop_values(interpreter: &InterpreterContext) -> InterpreterResult<Vec<ObjectPointer>> {
+    let produced = interpreter.stack.pop_data()?.heap_alloc(interpreter)?;
+    values_of(interpreter, &produced)
+}
+
+/// Binds in the frame the form runs in, which is popped once the form returns
+fn bind_local(
+    interpreter: &InterpreterContext,
+    bindings: Vec<(String, ObjectPointer)>,
+) -> InterpreterResult<()> {
+    let mut top_frame = interpreter.stack.top_frame()?;
+    for (name, obj) in bindings {
+        top_frame.insert_local(&name, obj);
+    }
+    Ok(())
+}
+
+/// Splits `((formals init) ..)` into its bindings
+fn value_bindings(bindings: &AST) -> InterpreterResult<Vec<(&AST, &AST)>> {
+    let list = match bindings {
+        AST::EmptyList(_) => return Ok(Vec::new()),
+        AST::Operation(first, rest, _) => std::iter::once(first.deref()).chain(rest),
+        e => {
+            return Err(InterpreterError::spanned(
+                InterpreterErrorKind::InvalidLetBindingForm,
+                e.span(),
+            ))
+        }
+    };
+
+    list.map(|binding| match binding {
+        AST::Operation(formals, init, _) if init.len() == 1 => Ok((formals.deref(), &init[0])),
+        e => Err(InterpreterError::spanned(
+            InterpreterErrorKind::InvalidLetBindingForm,
+            e.span(),
+        )),
+    })
+    .collect()
+}
+
+/// `(let-values (((a b) expr) ..) body)`, evaluating every expr before binding
+pub fn let_values(interpreter: &InterpreterContext, ast: Vec<&AST>) -> InterpreterResult<()> {
+    if ast.len() != 2 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, ast.len()),
+        ));
+    }
+
+    let bindings = value_bindings(ast[0])?;
+    for (_, init) in &bindings {
+        interpreter.defer(Deferred::Apply(thunk(interpreter, init)?, Vec::new()));
+    }
+    let formals = bindings
+        .into_iter()
+        .map(|(formals, _)| formals.clone())
+        .collect::<Vec<_>>();
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let mut produced = Vec::new();
+        for _ in 0..formals.len() {
+            produced.push(pop_values(interpreter)?);
+        }
+        for (formals, values) in formals.iter().zip(produced.into_iter().rev()) {
+            let bindings = bind_formals(interpreter, "let-values", formals, values)?;
+            bind_local(interpreter, bindings)?;
+        }
+        Ok(())
+    }));
+    interpreter.defer(Deferred::Apply(thunk(interpreter, ast[1])?, Vec::new()));
+    Ok(())
+}
+
+/// `(let*-values (((a b) expr) ..) body)`, binding each in turn
+pub fn let_star_values(interpreter: &InterpreterContext, ast: Vec<&AST>) -> InterpreterResult<()> {
+    if ast.len() != 2 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, ast.len()),
+        ));
+    }
+
+    for (formals, init) in value_bindings(ast[0])? {
+        interpreter.defer(Deferred::Apply(thunk(interpreter, init)?, Vec::new()));
+        let formals = formals.clone();
+        interpreter.defer(Deferred::then(move |interpreter| {
+            let values = pop_values(interpreter)?;
+            let bindings = bind_formals(interpreter, "let*-values", &formals, values)?;
+            bind_local(interpreter, bindings)
+        }));
+    }
+    interpreter.defer(Deferred::Apply(thunk(interpreter, ast[1])?, Vec::new()));
+    Ok(())
+}
+
+/// `(receive formals expr body)`
+pub fn receive(interpreter: &InterpreterContext, ast: Vec<&AST>) -> InterpreterResult<()> {
+    if ast.len() != 3 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(3, ast.len()),
+        ));
+    }
+
+    interpreter.defer(Deferred::Apply(thunk(interpreter, ast[1])?, Vec::new()));
+    let formals = ast[0].clone();
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let values = pop_values(interpreter)?;
+        let bindings = bind_formals(interpreter, "receive", &formals, values)?;
+        bind_local(interpreter, bindings)
+    }));
+    interpreter.defer(Deferred::Apply(thunk(interpreter, ast[2])?, Vec::new()));
+    Ok(())
+}
+
+/// `(define-values formals expr)`
+pub fn define_values(interpreter: &InterpreterContext, ast: Vec<&AST>) -> InterpreterResult<()> {
+    if ast.len() != 2 {
+        return Err(InterpreterError::new(
+            InterpreterErrorKind::ExpectedNParams(2, ast.len()),
+        ));
+    }
+
+    interpreter.defer(Deferred::Apply(thunk(interpreter, ast[1])?, Vec::new()));
+    let formals = ast[0].clone();
+    interpreter.defer(Deferred::then(move |interpreter| {
+        let values = pop_values(interpreter)?;
+        for (name, obj) in bind_formals(interpreter, "define-values", &formals, values)? {
+            interpreter.define_global(&name, formals.span(), obj);
+        }
+        Ok(())
+    }));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::eval;
+
+    #[test]
+    fn value_forms_bind_at_top_level() {
+        assert_eq!(
+            eval("(let-values (((a b) (values 1 2)) ((c) (values 3))) (list a b c))"),
+            Ok("1:2:3:()".to_string())
+        );
+        assert_eq!(
+            eval("(let*-values (((a) (values 1)) ((b) (values (+ a 1)))) b)"),
+            Ok("2".to_string())
+        );
+        assert_eq!(
+            eval("(receive (a b) (values 1 2) (+ a b))"),
+            Ok("3".to_string())
+        );
+    }
+
+    #[test]
+    fn value_bindings_do_not_leak() {
+        assert!(
+            eval("(define (f) (let-values (((leaked) (values 1))) leaked) leaked) (f)").is_err()
+        );
+        assert!(eval("(define (f) (receive (leaked) (values 1) leaked) leaked) (f)").is_err());
+        assert!(eval("(let-values (((leaked) (values 1))) leaked) leaked").is_err());
+    }
+
+    #[test]
+    fn rest_formals_take_the_remaining_values() {
+        assert_eq!(
+            eval("(receive (a . rest) (values 1 2 3) rest)"),
+            Ok("2:3:()".to_string())
+        );
+        assert_eq!(
+            eval("(receive all (values 1 2) all)"),
+            Ok("1:2:()".to_string())
+        );
+        assert_eq!(
+            eval("(define-values (a . b) (values 1 2 3)) b"),
+            Ok("2:3:()".to_string())
+        );
+        assert_eq!(
+            eval("(let-values (((a b . c) (values 1 2))) c)"),
+            Ok("()".to_string())
+        );
+        assert_eq!(
+            eval("(receive (a b . c) (values 1) c)"),
+            Err("'receive' expected at least 2 values, received 1".to_string())
+        );
+        assert_eq!(
+            eval("(receive (a b) (values 1) b)"),
+            Err("'receive' expected 2 values, received 1".to_string())
+        );
+    }
+}
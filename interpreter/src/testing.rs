@@ -0,0 +1,49 @@
+//! Helpers shared by the test modules of the crate
+
+use std::path::PathBuf;
+
+use crate::{Interpreter, InterpreterBuilder};
+
+/// Stack given to [`eval_recursion`], small enough that any form which uses the Rust
+/// stack for each level of recursion overflows it
+const RECURSION_STACK: usize = 512 * 1024;
+
+/// A builder for an interpreter which never collects in the background
+pub fn builder() -> InterpreterBuilder {
+    InterpreterBuilder::new().gc_interval(None)
+}
+
+pub fn interpreter() -> Interpreter {
+    Interpreter::from_context(builder().build())
+}
+
+/// Evaluates `source` in a fresh interpreter, returning the printed value or error
+pub fn eval(source: &str) -> Result<String, String> {
+    interpreter()
+        .eval_str(source)
+        .map(|value| value.to_string())
+        .map_err(|err| err.to_string())
+}
+
+/// Evaluates `(f depth)` after `definition`, which should recurse through the form
+/// being tested once for each level
+pub fn eval_recursion(definition: &str, depth: usize) -> Result<String, String> {
+    let source = format!("{definition} (f {depth})");
+    std::thread::Builder::new()
+        .stack_size(RECURSION_STACK)
+        .spawn(move || eval(&source))
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+/// A fresh directory holding the given files
+pub fn workspace(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file, contents) in files {
+        std::fs::write(dir.join(file), contents).unwrap();
+    }
+    dir
+}
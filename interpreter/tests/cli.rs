@@ -0,0 +1,192 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_interpreter"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn run_with_stdin(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_interpreter"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn status(args: &[&str]) -> Option<i32> {
+    run(args).status.code()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+/// Writes a program to a file of its own, returning its path
+fn program(name: &str, source: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("interpreter-cli-{}", std::process::id()));
+    let path = dir.join(name);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, source).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+#[test]
+fn successful_programs_exit_cleanly() {
+    let output = run(&["-e", "(write (+ 1 2))"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output).trim(), "3");
+    assert_eq!(status(&["--help"]), Some(0));
+}
+
+#[test]
+fn failing_programs_exit_with_failure() {
+    assert_eq!(status(&["-e", "(+ 1"]), Some(1));
+    assert_eq!(status(&["-e", "(car 5)"]), Some(1));
+}
+
+#[test]
+fn syntax_errors_stop_every_program() {
+    let output = run(&["-e", "(write 12345)", "-e", "(+ 1"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!stdout(&output).contains("12345"));
+}
+
+#[test]
+fn bad_usage_exits_with_usage_error() {
+    assert_eq!(status(&[]), Some(2));
+    assert_eq!(status(&["--unknown"]), Some(2));
+    assert_eq!(status(&["-e"]), Some(2));
+    assert_eq!(status(&["/nonexistent/program.scm"]), Some(2));
+}
+
+#[test]
+fn sources_run_in_order() {
+    let first = program("first.scm", "(define x 1) (write x)");
+    let second = program("second.scm", "(write (+ x 1))");
+    let output = run_with_stdin(&[&first, "-", &second, "-e", "(write 4)"], "(write 2)");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output).split_whitespace().collect::<Vec<_>>(), ["1", "2", "2", "4"]);
+}
+
+#[test]
+fn scripts_receive_their_arguments() {
+    let script = program("args.scm", "#!/usr/bin/env interpreter\n(write (command-line))");
+    let output = run(&["-s", &script, "a", "b"]);
+    assert_eq!(stdout(&output).trim(), format!("{script:?}:\"a\":\"b\":()"));
+
+    let output = run(&["-e", "(write (cdr (command-line)))", "--", "x"]);
+    assert_eq!(stdout(&output).trim(), "\"x\":()");
+}
+
+#[cfg(unix)]
+#[test]
+fn imports_without_a_current_directory_are_errors() {
+    let dir = std::env::temp_dir().join(format!("interpreter-cli-gone-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // The directory is removed from under the interpreter before it starts
+    let output = Command::new("sh")
+        .args(["-c", r#"cd "$1" && rmdir "$1" && exec "$0" -e "(import (foo))""#])
+        .arg(env!("CARGO_BIN_EXE_interpreter"))
+        .arg(&dir)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("Cannot find the current directory"));
+}
+
+#[test]
+fn imports_search_the_include_path_then_scheme_path_then_lib() {
+    let library = |name: &str, value: i32| {
+        format!("(define-library ({name}) (export x) (begin (define x {value})))")
+    };
+    let included = program("included/m.sld", &library("m", 1));
+    let scheme_path = program("scheme-path/m.sld", &library("m", 2));
+    program("scheme-path/n.sld", &library("n", 3));
+    program("lib/n.sld", &library("n", 4));
+    program("lib/o.sld", &library("o", 5));
+    let main = program("main.scm", "(import (only (m) x)) (write x)");
+    let dir_of = |path: &str| std::path::Path::new(path).parent().unwrap().to_owned();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_interpreter"))
+        .arg("-I")
+        .arg(dir_of(&included))
+        .arg(&main)
+        .env("SCHEME_PATH", dir_of(&scheme_path))
+        .output()
+        .unwrap();
+    assert_eq!(stdout(&output).trim(), "1");
+
+    let run_in_scheme_path = |source: &str| {
+        let main = program("main.scm", source);
+        let output = Command::new(env!("CARGO_BIN_EXE_interpreter"))
+            .arg(&main)
+            .env("SCHEME_PATH", dir_of(&scheme_path))
+            .output()
+            .unwrap();
+        stdout(&output).trim().to_string()
+    };
+    assert_eq!(run_in_scheme_path("(import (m)) (write x)"), "2");
+    assert_eq!(run_in_scheme_path("(import (n)) (write x)"), "3");
+    assert_eq!(run_in_scheme_path("(import (o)) (write x)"), "5");
+}
+
+#[test]
+fn tokens_are_printed_with_their_spans() {
+    let output = run(&["--tokens", "-e", "(write 1)"]);
+    assert_eq!(output.status.code(), Some(0));
+    let lines = stdout(&output);
+    let lines = lines.lines().collect::<Vec<_>>();
+    assert_eq!(lines.first(), Some(&"1:1-1:1 Symbol(\"(\")"));
+    assert_eq!(lines.last(), Some(&"1:10-1:10 EOF"));
+    assert!(!lines.contains(&"1"));
+
+    assert_eq!(status(&["--tokens", "-e", "\"abc"]), Some(1));
+}
+
+#[test]
+fn syntax_trees_are_printed() {
+    let output = run(&["--ast", "-e", "(write 1)"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output).lines().collect::<Vec<_>>(),
+        ["Operation 1:1-1:9", "  Identifier write 1:2-1:6", "  Literal 1 1:8-1:8"]
+    );
+    assert_eq!(status(&["--ast", "-e", "(+ 1"]), Some(1));
+}
+
+#[test]
+fn check_only_reports_syntax_errors() {
+    let output = run(&["--check", "-e", "(write 12345)"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!stdout(&output).contains("12345"));
+    assert_eq!(status(&["--check", "-e", "(car 5)"]), Some(0));
+    assert_eq!(status(&["--check", "-e", "(+ 1"]), Some(1));
+}
+
+#[test]
+fn include_directories_may_be_attached_to_the_option() {
+    let library = program("attached/a.sld", "(define-library (a) (export y) (begin (define y 7)))");
+    let dir = std::path::Path::new(&library).parent().unwrap().to_string_lossy().to_string();
+    let output = run(&[&format!("-I{dir}"), "-e", "(import (a)) (write y)"]);
+    assert_eq!(stdout(&output).trim(), "7");
+    let output = run(&["-I", &dir, "-e", "(import (a)) (write y)"]);
+    assert_eq!(stdout(&output).trim(), "7");
+    assert_eq!(status(&["-e", "(import (a))"]), Some(1));
+    assert_eq!(status(&["-e", "1", "-I"]), Some(2));
+}
+
+#[test]
+fn exit_passes_its_status_through() {
+    assert_eq!(status(&["-e", "(exit 3)"]), Some(3));
+    assert_eq!(status(&["-e", "(exit 3)", "-e", "(exit 4)"]), Some(3));
+    assert_eq!(status(&["-e", "(guard (e (#t 0)) (exit 5))"]), Some(5));
+}
@@ -1,18 +1,16 @@
 use anyhow::Result;
 use completion::ReplCompleter;
-use core::{error::ErrorWriter, LexerParser};
-use interpreter::{deref::InterpreterDeref, print::InterpreterPrint, InterpreterContext};
-use std::sync::Arc;
+use interpreter::Interpreter;
 
 use rustyline::{config::Configurer, error::ReadlineError, CompletionType, Editor};
 
 pub mod completion;
 
 fn main() -> Result<()> {
-    let context = Arc::new(InterpreterContext::new(ErrorWriter::empty()));
+    let interpreter = Interpreter::new();
 
     let mut editor = Editor::new()?;
-    editor.set_helper(Some(ReplCompleter(context.clone())));
+    editor.set_helper(Some(ReplCompleter(interpreter.context().clone())));
     editor.set_completion_type(CompletionType::List);
 
     loop {
@@ -21,21 +19,12 @@ fn main() -> Result<()> {
             Ok(line) => {
                 editor.add_history_entry(line.as_str())?;
 
-                let file_id = context
-                    .error_writer
-                    .write()
-                    .unwrap()
-                    .load_string(line.clone());
-                let Ok(ast) =
-                    LexerParser::from_string(file_id, line, &context.error_writer.read().unwrap())
-                else {
-                    continue;
-                };
-
-                context.start(ast);
-                if let Ok(p) = context.stack.pop_data() {
-                    let obj = p.deref(&context)?;
-                    println!("{}", obj.interpreter_fmt(&context));
+                match interpreter.eval_all(&line) {
+                    Ok(values) => values.iter().flatten().for_each(|value| println!("{value}")),
+                    Err(err) => match err.exit_status() {
+                        Some(status) => std::process::exit(status),
+                        None => interpreter.report(&err),
+                    },
                 }
             }
             Err(ReadlineError::Interrupted) => {